@@ -0,0 +1,261 @@
+use bevy::{
+    math::Vec3Swizzles,
+    prelude::*,
+    render::render_resource::{
+        Extent3d, FilterMode, SamplerDescriptor, TextureDimension, TextureFormat,
+    },
+};
+use decorum::Total;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use std::cmp::Reverse;
+
+use crate::{
+    intersect::{circle_entry, path_distance, truncate},
+    projectile::MotionModel,
+    ui::FunctionStatus,
+    validation::parse_shot,
+    Ball, DespawnOnExit, Field, Game, Mine, Owner, PlayState, PlayerLabel, RenderLayer,
+};
+
+/// Distance at which a rocket touches an item
+pub const CONTACT_DIST: f32 = 0.3;
+/// Distance past contact over which near misses still count for a bit
+const NEAR_DIST: f32 = 0.6;
+/// How much a near miss counts for at most, compared to a hit
+const NEAR_WEIGHT: f32 = 0.25;
+/// Number of points sampled along each candidate's path
+pub const PATH_SAMPLES: usize = 128;
+/// Number of candidate shots sampled per analysis
+pub const NUM_CANDIDATES: usize = 300;
+/// Number of heat map cells along each side of the field
+const HEAT_CELLS: usize = 32;
+
+/// What a shot can interact with, as seen from the player taking it
+#[derive(Clone, Debug, Default)]
+pub struct Board {
+    /// Where the shooter's rockets start
+    pub start: Vec2,
+    /// Balls the shooter wants to hit
+    pub targets: Vec<Vec2>,
+    /// Mines, which stop a rocket
+    pub mines: Vec<Vec2>,
+}
+
+/// A sampled shot and how good it is
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub x: String,
+    pub y: String,
+    pub score: f32,
+    pub path: Vec<Vec2>,
+}
+
+/// Makes up a random shot from a few common shapes
+fn random_shot(rng: &mut impl Rng) -> (String, String) {
+    let a = rng.gen_range(-8.0..8.0);
+    let b = rng.gen_range(-8.0..8.0);
+    let c = rng.gen_range(-8.0..8.0);
+    match rng.gen_range(0..4) {
+        0 => (format!("{:.2} * t", a), format!("{:.2} * t", b)),
+        1 => (format!("{:.2} * t", a), format!("{:.2} * t^2 + {:.2} * t", b, c)),
+        2 => (format!("{:.2} * t", a), format!("{:.2} * sin({:.2} * t)", b / 2.0, c)),
+        _ => (
+            format!("{:.2} * t * cos({:.2} * t)", a, c),
+            format!("{:.2} * t * sin({:.2} * t)", a, c),
+        ),
+    }
+}
+
+/// Samples the path a rocket would take, stopping where the path stops being finite
+pub fn sample_path(motion: &MotionModel) -> Vec<Vec2> {
+    sample_path_with(motion, PATH_SAMPLES)
+}
+
+/// Like `sample_path`, but with some other number of samples
+pub fn sample_path_with(motion: &MotionModel, samples: usize) -> Vec<Vec2> {
+    (0..=samples)
+        .map(|i| motion.position(i as f32 / samples as f32))
+        .take_while(|pos| pos.is_finite())
+        .collect()
+}
+
+/// Scores a path by the targets it hits before hitting a mine, plus a bit for near misses
+pub fn score_path(board: &Board, path: &[Vec2]) -> f32 {
+    let stop = board
+        .mines
+        .iter()
+        .filter_map(|mine| circle_entry(path, *mine, CONTACT_DIST))
+        .fold(1.0, f32::min);
+    let path = truncate(path, stop);
+
+    board
+        .targets
+        .iter()
+        .map(|target| path_distance(&path, *target))
+        .map(|dist| {
+            if dist < CONTACT_DIST {
+                1.0
+            } else {
+                NEAR_WEIGHT * (1.0 - (dist - CONTACT_DIST) / NEAR_DIST).max(0.0)
+            }
+        })
+        .sum()
+}
+
+/// Samples random shots and scores them, best first
+pub fn sample_candidates(
+    board: &Board,
+    num_candidates: usize,
+    rng: &mut impl Rng,
+) -> Vec<Candidate> {
+    let mut candidates = (0..num_candidates)
+        .filter_map(|_| {
+            let (x, y) = random_shot(rng);
+            let parametric = parse_shot(&x, &y, "").ok()?;
+            let path = sample_path(&MotionModel::parametric(parametric, board.start));
+            let score = score_path(board, &path);
+            Some(Candidate { x, y, score, path })
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|candidate| Reverse(Total::from(candidate.score)));
+    candidates
+}
+
+/// How much good shots pass through each cell of the field, from 0 to 1.
+/// Cells are stored row by row, starting from the bottom left.
+pub fn heat_map(candidates: &[Candidate], half_size: Vec2) -> Vec<f32> {
+    let mut heat = vec![0.0; HEAT_CELLS * HEAT_CELLS];
+    let mut visited = vec![false; HEAT_CELLS * HEAT_CELLS];
+
+    for candidate in candidates {
+        visited.fill(false);
+        for pos in &candidate.path {
+            let cell = (*pos + half_size) / (2.0 * half_size) * HEAT_CELLS as f32;
+            if cell.cmplt(Vec2::ZERO).any() || cell.cmpge(Vec2::splat(HEAT_CELLS as f32)).any() {
+                continue;
+            }
+            let index = cell.y as usize * HEAT_CELLS + cell.x as usize;
+            if !visited[index] {
+                visited[index] = true;
+                heat[index] += candidate.score;
+            }
+        }
+    }
+
+    let max = heat.iter().copied().fold(0.0, f32::max);
+    if max > 0.0 {
+        for h in &mut heat {
+            *h /= max;
+        }
+    }
+    heat
+}
+
+/// Whether the analysis overlay is shown
+#[derive(Debug, Default)]
+pub struct AnalysisMode {
+    pub enabled: bool,
+    /// Player the overlay was computed for
+    shown_for: Option<u32>,
+}
+
+/// Labels the analysis heat map, which is one sprite with a pixel per cell
+#[derive(Component)]
+pub struct HeatMap;
+
+/// Toggles the analysis overlay with F2, and recomputes it when the turn changes
+pub fn update_analysis(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut mode: ResMut<AnalysisMode>,
+    game: Res<Game>,
+    player_comps: Query<(&Owner, &Transform), With<PlayerLabel>>,
+    balls: Query<(&Transform, Option<&Owner>), With<Ball>>,
+    mines: Query<&Transform, With<Mine>>,
+    cells: Query<Entity, With<HeatMap>>,
+    field: Query<Entity, With<Field>>,
+    mut status: Query<&mut Text, With<FunctionStatus>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if keys.just_pressed(KeyCode::F2) {
+        mode.enabled = !mode.enabled;
+    }
+
+    let player = game.player_turn();
+    if mode.enabled == (mode.shown_for == Some(player)) {
+        return;
+    }
+
+    for entity in cells.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    mode.shown_for = None;
+    if !mode.enabled {
+        return;
+    }
+
+    let start = if let Some((_, transform)) = player_comps.iter().find(|(o, _)| o.0 == player) {
+        transform.translation.xy()
+    } else {
+        return;
+    };
+    let board = Board {
+        start,
+        targets: balls
+            .iter()
+            .filter(|(_, owner)| owner.map_or(true, |owner| owner.0 != player))
+            .map(|(transform, _)| transform.translation.xy())
+            .collect(),
+        mines: mines.iter().map(|transform| transform.translation.xy()).collect(),
+    };
+
+    let mut rng = Pcg64::seed_from_u64(((game.round_index as u64) << 32) | player as u64);
+    let candidates = sample_candidates(&board, NUM_CANDIDATES, &mut rng);
+    let heat = heat_map(&candidates, game.half_size());
+
+    // Cells go up from the bottom of the field, but image rows go down from the top
+    let mut pixels = vec![0; HEAT_CELLS * HEAT_CELLS * 4];
+    for (index, h) in heat.iter().enumerate().filter(|(_, h)| **h > 0.0) {
+        let row = HEAT_CELLS - 1 - index / HEAT_CELLS;
+        let pixel = (row * HEAT_CELLS + index % HEAT_CELLS) * 4;
+        let color = Color::rgba(1.0, 0.4, 0.0, 0.6 * h).as_rgba_f32();
+        for (byte, channel) in pixels[pixel..pixel + 4].iter_mut().zip(color) {
+            *byte = (channel * 255.0).round() as u8;
+        }
+    }
+    let size =
+        Extent3d { width: HEAT_CELLS as u32, height: HEAT_CELLS as u32, depth_or_array_layers: 1 };
+    let mut image = Image::new(size, TextureDimension::D2, pixels, TextureFormat::Rgba8UnormSrgb);
+    // Cells stay sharp instead of blurring into each other
+    image.sampler_descriptor = SamplerDescriptor {
+        mag_filter: FilterMode::Nearest,
+        min_filter: FilterMode::Nearest,
+        ..Default::default()
+    };
+
+    commands.entity(field.single()).with_children(|node| {
+        node.spawn_bundle(SpriteBundle {
+            sprite: Sprite { custom_size: Some(2.0 * game.half_size()), ..Default::default() },
+            texture: images.add(image),
+            transform: Transform::from_translation(Vec3::Z * RenderLayer::Heat.z()),
+            ..Default::default()
+        })
+        .insert(HeatMap)
+        .insert(DespawnOnExit(PlayState::Enter));
+    });
+
+    if let Some(best) = candidates.first() {
+        let mut status = status.single_mut();
+        status.sections[0].value =
+            format!("Suggested: x(t) = {}, y(t) = {} (score {:.2})\n", best.x, best.y, best.score);
+        status.sections[0].style.color = Color::BLACK;
+    }
+    mode.shown_for = Some(player);
+}
+
+/// Forgets the overlay when leaving the entering phase, which despawns its cells,
+/// so it gets recomputed next time
+pub fn clear_analysis(mut mode: ResMut<AnalysisMode>) {
+    mode.shown_for = None;
+}
@@ -45,11 +45,17 @@ assets! {
 
 impl From<Asset> for HandleId {
     fn from(asset: Asset) -> Self {
-        asset.to_string().into()
+        crate::mods::asset_path(&asset.to_string()).into()
     }
 }
 
 pub fn load_assets(asset_server: Res<AssetServer>, mut used_assets: ResMut<Vec<HandleUntyped>>) {
+    load_all(&asset_server, &mut used_assets);
+}
+
+/// Starts loading every asset from where mods say it is.
+/// Handles from earlier loads are kept, since things on screen may still use them.
+pub fn load_all(asset_server: &AssetServer, used_assets: &mut Vec<HandleUntyped>) {
     // load_folder doesn't work in wasm
     for asset_path in [
         "ball.png",
@@ -69,7 +75,7 @@ pub fn load_assets(asset_server: Res<AssetServer>, mut used_assets: ResMut<Vec<H
     .chain((1..=4).map(|i| format!("rocket{}.png", i)))
     .chain((1..=4).map(|i| format!("rocket_move{}.ogg", i)))
     {
-        used_assets.push(asset_server.load_untyped(&asset_path));
+        used_assets.push(asset_server.load_untyped(&crate::mods::asset_path(&asset_path)));
     }
     //*used_assets = asset_server.load_folder("./.").expect("Could not load assets");
 }
@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use egui::Align2;
+use std::f32::consts::TAU;
+
+use crate::ui::{
+    entry_box_index, ButtonsEnabled, FunctionEntryBox, FunctionX, FunctionY, Textbox,
+    TextboxesEditable,
+};
+
+/// How far an arc shot drops below a straight one by the end of its flight, in field units
+const ARC_DROP: f32 = 4.0;
+/// Turns a spiral shot makes over its flight
+const SPIRAL_TURNS: f32 = 1.5;
+/// Range of the strength widget, which is how far a straight shot goes, in field units
+const STRENGTH_RANGE: std::ops::RangeInclusive<f32> = 1.0..=12.0;
+
+/// Shapes of shots the assist mode can write
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Template {
+    Straight,
+    Arc,
+    Spiral,
+}
+
+impl Template {
+    const ALL: [Self; 3] = [Self::Straight, Self::Arc, Self::Spiral];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Straight => "Straight",
+            Self::Arc => "Arc",
+            Self::Spiral => "Spiral",
+        }
+    }
+
+    /// x(t) and y(t) of a shot aimed at some angle, in radians counterclockwise from the right,
+    /// with some strength
+    pub fn functions(self, angle: f32, strength: f32) -> (String, String) {
+        let (vx, vy) = (strength * angle.cos(), strength * angle.sin());
+        match self {
+            Self::Straight => (format!("{:.2} * t", vx), format!("{:.2} * t", vy)),
+            Self::Arc => {
+                (format!("{:.2} * t", vx), format!("{:.2} * t - {:.2} * t^2", vy, ARC_DROP))
+            }
+            Self::Spiral => {
+                let turn = format!("({:.3} + {:.3} * t)", angle, SPIRAL_TURNS * TAU);
+                (
+                    format!("{:.2} * t * cos {}", strength, turn),
+                    format!("{:.2} * t * sin {}", strength, turn),
+                )
+            }
+        }
+    }
+}
+
+/// Choices in the assist window, which writes shots for players who'd rather not type functions
+#[derive(Debug)]
+pub struct Assist {
+    pub template: Template,
+    /// Degrees counterclockwise from the right
+    pub angle: f32,
+    pub strength: f32,
+}
+
+impl Default for Assist {
+    fn default() -> Self {
+        Self { template: Template::Straight, angle: 0.0, strength: 6.0 }
+    }
+}
+
+/// Shows the assist window, where a shot gets picked from a template and aimed
+/// with a direction and strength. Changing anything rewrites the textboxes.
+pub fn show_assist(
+    mut assist: ResMut<Assist>,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+    buttons_enabled: Res<ButtonsEnabled>,
+    textboxes_editable: Res<TextboxesEditable>,
+) {
+    if !buttons_enabled.0 || !textboxes_editable.0 {
+        return;
+    }
+
+    let assist = &mut *assist;
+    let mut changed = false;
+    egui::Window::new("Assist")
+        .anchor(Align2::LEFT_BOTTOM, [8.0, -8.0])
+        .default_open(false)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for template in Template::ALL {
+                    changed |= ui
+                        .selectable_value(&mut assist.template, template, template.name())
+                        .changed();
+                }
+            });
+            changed |= ui
+                .add(egui::Slider::new(&mut assist.angle, -180.0..=180.0).text("direction"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut assist.strength, STRENGTH_RANGE).text("strength"))
+                .changed();
+            changed |= ui.button("Use").clicked();
+        });
+
+    if changed {
+        let (x, y) = assist.template.functions(assist.angle.to_radians(), assist.strength);
+        let functions = [x, y, String::new()];
+        for (mut textbox, fx, fy) in entry_boxes.iter_mut() {
+            textbox.text = functions[entry_box_index(fx, fy)].clone();
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::{fs, process};
+
+/// Lines shown before the first difference
+const CONTEXT_LINES: usize = 3;
+
+/// Tick a match log line happened on, if it's from while rockets flew
+fn tick_of(line: &str) -> Option<&str> {
+    line.strip_prefix("tick ")?.split_whitespace().next()
+}
+
+/// Compares two match logs and prints where they first differ
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (a_path, b_path) = match &args[..] {
+        [a, b] => (a, b),
+        _ => {
+            eprintln!("usage: logdiff <log a> <log b>");
+            process::exit(2);
+        }
+    };
+    let read = |path: &String| {
+        fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("failed to read {}: {}", path, err);
+            process::exit(2);
+        })
+    };
+    let (a, b) = (read(a_path), read(b_path));
+    let (a_lines, b_lines) = (a.lines().collect::<Vec<_>>(), b.lines().collect::<Vec<_>>());
+
+    let first_diff =
+        (0..a_lines.len().max(b_lines.len())).find(|&i| a_lines.get(i) != b_lines.get(i));
+    let index = match first_diff {
+        Some(index) => index,
+        None => {
+            println!("logs match ({} lines)", a_lines.len());
+            return;
+        }
+    };
+
+    let round = a_lines[..index].iter().rev().find(|line| line.starts_with("round "));
+    let tick = a_lines.get(index).or_else(|| b_lines.get(index)).and_then(|line| tick_of(line));
+    println!(
+        "first difference at line {} ({}, tick {})",
+        index + 1,
+        round.copied().unwrap_or("before the first round"),
+        tick.unwrap_or("-")
+    );
+    for line in &a_lines[index.saturating_sub(CONTEXT_LINES)..index] {
+        println!("  {}", line);
+    }
+    println!("< {}", a_lines.get(index).copied().unwrap_or("(end of log)"));
+    println!("> {}", b_lines.get(index).copied().unwrap_or("(end of log)"));
+    process::exit(1);
+}
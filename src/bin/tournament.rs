@@ -0,0 +1,18 @@
+use graph_war::bot::{round_robin, Agent, AnalysisAgent, RandomAgent};
+
+/// Runs a round-robin tournament between the built-in bots and prints the standings
+fn main() {
+    let seed = std::env::args().nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+
+    let mut agents: Vec<Box<dyn Agent>> =
+        vec![Box::new(RandomAgent::new(seed)), Box::new(AnalysisAgent::new(seed))];
+    let standings = round_robin(&mut agents, 3, seed);
+
+    println!("{:<16} {:>4} {:>4} {:>4} {:>6}", "agent", "W", "D", "L", "balls");
+    for standing in standings {
+        println!(
+            "{:<16} {:>4} {:>4} {:>4} {:>6}",
+            standing.name, standing.wins, standing.draws, standing.losses, standing.balls
+        );
+    }
+}
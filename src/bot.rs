@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+use rand::{prelude::Distribution, Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+use crate::{
+    analysis::{sample_candidates, sample_path, Board, CONTACT_DIST, NUM_CANDIDATES},
+    projectile::MotionModel,
+    validation::parse_shot,
+    Game, FIELD_CONFIGS, NUM_BALLS, NUM_MINES,
+};
+
+/// Public state of a game, as seen by the player whose turn it is
+#[derive(Clone, Debug)]
+pub struct GameView {
+    /// Index of the player choosing a shot
+    pub player: u32,
+    /// 1-indexed, like `Game::round_index`
+    pub round_index: u32,
+    pub num_rounds: u32,
+    /// Half the width and height of the field
+    pub half_size: Vec2,
+    pub player_positions: Vec<Vec2>,
+    pub num_balls: Vec<u32>,
+    /// Balls anyone can collect
+    pub balls: Vec<Vec2>,
+    /// Balls belonging to players, along with their owners. Only present in the destruction round.
+    pub player_balls: Vec<(u32, Vec2)>,
+    pub mines: Vec<Vec2>,
+}
+
+impl GameView {
+    pub fn num_players(&self) -> u32 {
+        self.player_positions.len() as u32
+    }
+
+    pub fn is_on_destruction_round(&self) -> bool {
+        self.round_index == self.num_rounds + 1
+    }
+
+    /// The board from the point of view of the player choosing a shot
+    pub fn board(&self) -> Board {
+        Board {
+            start: self.player_positions[self.player as usize],
+            targets: self
+                .balls
+                .iter()
+                .copied()
+                .chain(
+                    self.player_balls
+                        .iter()
+                        .filter(|(owner, _)| *owner != self.player)
+                        .map(|(_, pos)| *pos),
+                )
+                .collect(),
+            mines: self.mines.clone(),
+        }
+    }
+}
+
+/// Something that can play the game, such as a bot
+pub trait Agent {
+    fn name(&self) -> &str;
+
+    /// Chooses x(t) and y(t) for a rocket. Functions that don't parse count as not firing.
+    fn choose_shot(&mut self, view: &GameView) -> (String, String);
+}
+
+/// Fires in a random straight line
+pub struct RandomAgent {
+    rng: Pcg64,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Pcg64::seed_from_u64(seed) }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn choose_shot(&mut self, _view: &GameView) -> (String, String) {
+        let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+        let speed = self.rng.gen_range(2.0..10.0);
+        (format!("{:.2} * t", speed * angle.cos()), format!("{:.2} * t", speed * angle.sin()))
+    }
+}
+
+/// Fires the best shot found by the shot analysis
+pub struct AnalysisAgent {
+    rng: Pcg64,
+}
+
+impl AnalysisAgent {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Pcg64::seed_from_u64(seed) }
+    }
+}
+
+impl Agent for AnalysisAgent {
+    fn name(&self) -> &str {
+        "analysis"
+    }
+
+    fn choose_shot(&mut self, view: &GameView) -> (String, String) {
+        sample_candidates(&view.board(), NUM_CANDIDATES, &mut self.rng)
+            .into_iter()
+            .next()
+            .map(|candidate| (candidate.x, candidate.y))
+            .unwrap_or_default()
+    }
+}
+
+/// Plays a whole game without any rendering and returns the number of balls each agent ended with.
+///
+/// This is a simplified version of the real game: shots are resolved one after another
+/// in turn order instead of simultaneously, and rockets don't collide with each other.
+pub fn play_match(agents: &mut [&mut dyn Agent], num_rounds: u32, rng: &mut Pcg64) -> Vec<u32> {
+    let num_players = agents.len();
+    let config = &FIELD_CONFIGS[num_players];
+    let half_size = Game::default().half_size();
+    let item_distribution = config.item_region.scaled(half_size);
+
+    let mut view = GameView {
+        player: 0,
+        round_index: 0,
+        num_rounds,
+        half_size,
+        player_positions: config.positions.iter().map(|pos| *pos * half_size).collect(),
+        num_balls: vec![0; num_players],
+        balls: vec![],
+        player_balls: vec![],
+        mines: vec![],
+    };
+
+    for round_index in 1..=num_rounds + 1 {
+        view.round_index = round_index;
+        if view.is_on_destruction_round() {
+            view.balls.clear();
+            view.mines.clear();
+            view.player_balls = (0..num_players as u32)
+                .flat_map(|player| {
+                    let num_balls = view.num_balls[player as usize] as usize;
+                    (&item_distribution)
+                        .sample_iter(&mut *rng)
+                        .take(num_balls)
+                        .map(move |pos| (player, pos))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+        } else {
+            view.balls = (&item_distribution).sample_iter(&mut *rng).take(NUM_BALLS).collect();
+            view.mines = (&item_distribution).sample_iter(&mut *rng).take(NUM_MINES).collect();
+        }
+
+        let shots = (0..num_players as u32)
+            .map(|player| {
+                view.player = player;
+                agents[player as usize].choose_shot(&view)
+            })
+            .collect::<Vec<_>>();
+
+        for (player, (x, y)) in shots.iter().enumerate() {
+            let parametric = if let Ok(p) = parse_shot(x, y, "") { p } else { continue };
+            let start = view.player_positions[player];
+            let path = sample_path(&MotionModel::parametric(parametric, start));
+
+            for pos in path {
+                let touches = |item: &Vec2| item.distance(pos) < CONTACT_DIST;
+
+                if let Some(index) = view.mines.iter().position(touches) {
+                    view.mines.swap_remove(index);
+                    break;
+                }
+
+                let num_before = view.balls.len();
+                view.balls.retain(|ball| !touches(ball));
+                view.num_balls[player] += (num_before - view.balls.len()) as u32;
+
+                let num_balls = &mut view.num_balls;
+                view.player_balls.retain(|(owner, ball)| {
+                    let hit = touches(ball);
+                    if hit {
+                        num_balls[*owner as usize] -= 1;
+                    }
+                    !hit
+                });
+            }
+        }
+    }
+
+    view.num_balls
+}
+
+/// Results of an agent in a tournament
+#[derive(Clone, Debug, Default)]
+pub struct Standing {
+    pub name: String,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Balls left at the end of all matches, added up
+    pub balls: u32,
+}
+
+/// Plays every pair of agents against each other twice, once from each side of the field,
+/// and returns the standings sorted from best to worst
+pub fn round_robin(agents: &mut [Box<dyn Agent>], num_rounds: u32, seed: u64) -> Vec<Standing> {
+    let mut rng = Pcg64::seed_from_u64(seed);
+    let mut standings = agents
+        .iter()
+        .map(|agent| Standing { name: agent.name().to_owned(), ..Default::default() })
+        .collect::<Vec<_>>();
+
+    for j in 0..agents.len() {
+        for i in 0..j {
+            for (first, second) in [(i, j), (j, i)] {
+                let balls = {
+                    let (left, right) = agents.split_at_mut(j);
+                    let (a, b) = (&mut *left[i], &mut *right[0]);
+                    let mut seats: [&mut dyn Agent; 2] = if first == i { [a, b] } else { [b, a] };
+                    play_match(&mut seats, num_rounds, &mut rng)
+                };
+
+                for (seat, index) in [first, second].into_iter().enumerate() {
+                    let standing = &mut standings[index];
+                    let (mine, theirs) = (balls[seat], balls[1 - seat]);
+                    standing.balls += mine;
+                    match mine.cmp(&theirs) {
+                        std::cmp::Ordering::Greater => standing.wins += 1,
+                        std::cmp::Ordering::Equal => standing.draws += 1,
+                        std::cmp::Ordering::Less => standing.losses += 1,
+                    }
+                }
+            }
+        }
+    }
+
+    standings.sort_by_key(|s| std::cmp::Reverse((2 * s.wins + s.draws, s.balls)));
+    standings
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+use crate::{asset, projectile::Trail, RelativeTextSize, RenderLayer};
+
+/// Something that happened to a projectile along its path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteKind {
+    Hit,
+    Bounce,
+    Destroyed,
+    Expired,
+    /// The rocket's path stopped being defined, like at a division by zero, so it fizzled out
+    Fizzled,
+}
+
+impl NoteKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Hit => "hit",
+            Self::Bounce => "bounce",
+            Self::Destroyed => "boom",
+            Self::Expired => "end",
+            Self::Fizzled => "fizzle",
+        }
+    }
+}
+
+/// Something happened to a projectile at some parameter of its motion. This is an event.
+pub struct FlightNote {
+    pub projectile: Entity,
+    pub param: f32,
+    pub position: Vec2,
+    pub kind: NoteKind,
+}
+
+/// Whether the trajectory breakdown, which labels graphs with the t-values
+/// where things happened, is shown
+#[derive(Debug, Default)]
+pub struct Breakdown {
+    pub enabled: bool,
+}
+
+/// Label on a graph saying what happened there
+#[derive(Component)]
+pub struct BreakdownLabel;
+
+/// Labels the graphs of projectiles with their flight notes.
+/// The labels belong to the graphs so they go away along with them.
+pub fn annotate_flights(
+    mut commands: Commands,
+    mut notes: EventReader<FlightNote>,
+    trails: Query<&Trail>,
+    breakdown: Res<Breakdown>,
+    fonts: Res<Assets<Font>>,
+) {
+    for note in notes.iter() {
+        let trail = if let Ok(trail) = trails.get(note.projectile) { trail } else { continue };
+
+        commands.entity(trail.0).with_children(|node| {
+            node.spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    format!("{} t={:.2}", note.kind.label(), note.param),
+                    TextStyle {
+                        color: Color::BLACK,
+                        font: fonts.get_handle(asset::Font),
+                        font_size: 0.0,
+                    },
+                    TextAlignment {
+                        horizontal: HorizontalAlign::Left,
+                        vertical: VerticalAlign::Bottom,
+                    },
+                ),
+                transform: Transform::from_translation(
+                    note.position.extend(RenderLayer::Score.z()),
+                ),
+                visibility: Visibility { is_visible: breakdown.enabled },
+                ..Default::default()
+            })
+            .insert(RelativeTextSize(0.15))
+            .insert(BreakdownLabel);
+        });
+    }
+}
+
+/// Shows or hides the trajectory breakdown with F3
+pub fn toggle_breakdown(
+    keys: Res<Input<KeyCode>>,
+    mut breakdown: ResMut<Breakdown>,
+    mut labels: Query<&mut Visibility, With<BreakdownLabel>>,
+) {
+    if !keys.just_pressed(KeyCode::F3) {
+        return;
+    }
+
+    breakdown.enabled = !breakdown.enabled;
+    for mut visibility in labels.iter_mut() {
+        visibility.is_visible = breakdown.enabled;
+    }
+}
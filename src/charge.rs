@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioChannel, AudioSource};
+
+use crate::{asset, graph::SendAction, rules::GameRules};
+
+/// Radius of the explosion of an uncharged rocket
+pub const BLAST_RADIUS: f32 = 0.25;
+/// How much a full charge adds to the explosion radius, relative to an uncharged one
+pub const CHARGE_BLAST_BONUS: f32 = 2.0;
+/// How much a full charge adds to the wait before the next rocket, relative to the normal wait
+pub const CHARGE_COOLDOWN_BONUS: f32 = 2.0;
+
+/// Explosion radius of a rocket with some charge from 0 to 1
+pub fn blast_radius(charge: f32) -> f32 {
+    BLAST_RADIUS * (1.0 + CHARGE_BLAST_BONUS * charge)
+}
+
+/// How charged a rocket is, from 0 to 1
+#[derive(Clone, Copy, Debug, Component)]
+pub struct Charge(pub f32);
+
+fn charge_channel() -> AudioChannel {
+    AudioChannel::new("Charge".into())
+}
+
+/// The button being held down to charge a rocket, if any
+#[derive(Debug, Default)]
+pub struct Charging {
+    /// The action of the button and the time it started getting held, in seconds since startup
+    started: Option<(SendAction, f64)>,
+}
+
+impl Charging {
+    pub fn start(
+        &mut self,
+        action: SendAction,
+        now: f64,
+        player: u32,
+        audio: &Audio,
+        sounds: &Assets<AudioSource>,
+    ) {
+        self.started = Some((action, now));
+        audio.play_looped_in_channel(
+            sounds.get_handle(asset::RocketMove(player)),
+            &charge_channel(),
+        );
+        audio.set_volume_in_channel(0.0, &charge_channel());
+    }
+
+    /// Charge built up so far, from 0 to 1
+    pub fn level(&self, now: f64, max_charge: f32) -> f32 {
+        self.started.map_or(0.0, |(_, start)| ((now - start) as f32 / max_charge).clamp(0.0, 1.0))
+    }
+
+    /// Stops charging. Returns the charge if the button with some action was the one being held.
+    pub fn finish(
+        &mut self,
+        action: SendAction,
+        now: f64,
+        max_charge: f32,
+        audio: &Audio,
+    ) -> Option<f32> {
+        match self.started {
+            Some((held, _)) if held == action => {
+                let charge = self.level(now, max_charge);
+                self.cancel(audio);
+                Some(charge)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn cancel(&mut self, audio: &Audio) {
+        if self.started.take().is_some() {
+            audio.stop_channel(&charge_channel());
+        }
+    }
+}
+
+/// Makes the charge sound louder the longer the button is held
+pub fn update_charge_sound(
+    charging: Res<Charging>,
+    rules: Res<GameRules>,
+    time: Res<Time>,
+    audio: Res<Audio>,
+) {
+    const MAX_VOLUME: f32 = 2.0;
+
+    if let (Some(max_charge), Some(_)) = (rules.max_charge, charging.started) {
+        let charge = charging.level(time.seconds_since_startup(), max_charge);
+        audio.set_volume_in_channel(charge * MAX_VOLUME, &charge_channel());
+    }
+}
@@ -1,11 +1,24 @@
-use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy::{ecs::system::SystemParam, math::Vec3Swizzles, prelude::*};
 use bevy_kira_audio::{Audio, AudioSource};
 use bevy_rapier2d::prelude::*;
 use bitflags::bitflags;
 use decorum::Total;
-use fxhash::FxHashSet;
+use fxhash::{FxHashMap, FxHashSet};
 
-use crate::{asset, Ball, Mine, Owner, Player};
+use crate::{
+    asset,
+    breakdown::{FlightNote, NoteKind},
+    charge::{blast_radius, Charge},
+    debris::Debris,
+    dilation::{DilationPickup, DILATION_ROUNDS},
+    emp::{EmpPickup, EMP_ROUNDS},
+    intersect::point_on_path,
+    projectile::{MotionModel, Projectile},
+    ricochet::{Bounces, Ricochet, RICOCHET_ROUNDS},
+    status::{StatusEffects, StatusKind},
+    style::StylePoints,
+    Ball, Mine, Owner, Player, PlayerLabel,
+};
 
 bitflags! {
     pub struct CollisionGroups: u32 {
@@ -13,6 +26,8 @@ bitflags! {
         const ROCKET_CAST = 0b001;
         const BALL        = 0b010;
         const MINE        = 0b100;
+        const OBSTACLE    = 0b1000;
+        const PICKUP      = 0b10000;
     }
 }
 
@@ -26,39 +41,162 @@ pub fn update_prev_positions(mut positions: Query<(&mut PrevPosition, &GlobalTra
     }
 }
 
-/// Rocket collision event
-pub struct RocketCollision {
-    pub rocket: Entity,
+/// Parameter value of a projectile's motion on the previous frame
+#[derive(Component)]
+pub struct PrevParam(pub f32);
+
+pub fn update_prev_params(mut params: Query<(&mut PrevParam, &Timer)>) {
+    for (mut prev, timer) in params.iter_mut() {
+        prev.0 = timer.percent();
+    }
+}
+
+/// Number of segments the path of a projectile is split into each frame when sweeping.
+/// A single segment would cut corners on curves that bend a lot between frames.
+const SWEEP_SEGMENTS: usize = 8;
+
+/// Samples the path a projectile took since the previous frame
+fn sweep_path(
+    prev_pos: Vec2,
+    curr_pos: Vec2,
+    prev_param: f32,
+    curr_param: f32,
+    motion: &MotionModel,
+) -> Vec<Vec2> {
+    (0..=SWEEP_SEGMENTS)
+        .map(|i| {
+            if i == 0 {
+                prev_pos
+            } else if i == SWEEP_SEGMENTS {
+                curr_pos
+            } else {
+                let fraction = i as f32 / SWEEP_SEGMENTS as f32;
+                let t = prev_param + (curr_param - prev_param) * fraction;
+                motion.try_position(t).unwrap_or_else(|| prev_pos.lerp(curr_pos, fraction))
+            }
+        })
+        .collect()
+}
+
+/// Projectile collision event
+pub struct ProjectileCollision {
+    pub projectile: Entity,
     pub other: Entity,
 }
 
+fn has_status(
+    statuses: &Query<(&Owner, &mut StatusEffects), With<PlayerLabel>>,
+    player_index: u32,
+    kind: StatusKind,
+) -> bool {
+    statuses.iter().any(|(owner, effects)| owner.0 == player_index && effects.has(kind))
+}
+
+/// Number of round starts the curse from hitting a mine survives
+const CURSE_ROUNDS: u32 = 2;
+/// Number of round starts the invulnerability from losing a ball survives
+const INVULNERABLE_ROUNDS: u32 = 1;
+
+/// What kind of item a rocket ran into
+#[derive(SystemParam)]
+pub struct ItemKinds<'w, 's> {
+    balls: Query<'w, 's, (Entity, &'static GlobalTransform), With<Ball>>,
+    mines: Query<'w, 's, &'static Mine>,
+    debris: Query<'w, 's, &'static GlobalTransform, With<Debris>>,
+    ricochets: Query<'w, 's, &'static Ricochet>,
+    emps: Query<'w, 's, &'static EmpPickup>,
+    dilations: Query<'w, 's, &'static DilationPickup>,
+}
+
+/// Scores a ball hit by some player's rocket. Returns whether the ball got used up.
+fn hit_ball(
+    ball: Entity,
+    player_index: u32,
+    owned: &Query<&Owner>,
+    statuses: &mut Query<(&Owner, &mut StatusEffects), With<PlayerLabel>>,
+    players: &mut [Player],
+    style: u32,
+    audio: &Audio,
+    sounds: &Assets<AudioSource>,
+) -> bool {
+    if let Ok(owner) = owned.get(ball) {
+        // Destruction round
+        if has_status(statuses, owner.0, StatusKind::Invulnerable) {
+            return false;
+        }
+        players[owner.0 as usize].num_balls -= 1;
+        if let Some((_, mut effects)) = statuses.iter_mut().find(|(o, _)| o.0 == owner.0) {
+            effects.add(StatusKind::Invulnerable, INVULNERABLE_ROUNDS);
+        }
+        audio.play(sounds.get_handle(asset::PlayerBallPickup));
+    } else {
+        // Normal round
+        if !has_status(statuses, player_index, StatusKind::Curse) {
+            players[player_index as usize].num_balls += 1;
+            players[player_index as usize].style_points += style;
+        }
+        audio.play(sounds.get_handle(asset::BallPickup));
+    }
+    true
+}
+
 pub fn collect_balls(
-    mut rockets: Query<(
-        Entity,
-        &PrevPosition,
-        &mut Transform,
-        &RigidBodyCollidersComponent,
-        &Owner,
-    )>,
+    mut rockets: Query<
+        (
+            Entity,
+            &PrevPosition,
+            &PrevParam,
+            &mut Transform,
+            &Timer,
+            &mut MotionModel,
+            &RigidBodyCollidersComponent,
+            &Owner,
+            Option<&mut Bounces>,
+        ),
+        With<Projectile>,
+    >,
     owned: Query<&Owner>,
     query_pipeline: Res<QueryPipeline>,
     collider_query: QueryPipelineColliderComponentsQuery,
     collider_shapes: Query<&ColliderShapeComponent>,
     parents: Query<&Parent>,
-    balls: Query<&Ball>,
-    mines: Query<&Mine>,
+    kinds: ItemKinds,
+    charges: Query<&Charge>,
+    styles: Query<&StylePoints>,
+    mut statuses: Query<(&Owner, &mut StatusEffects), With<PlayerLabel>>,
     mut commands: Commands,
     mut players: ResMut<Vec<Player>>,
-    mut rocket_collisions: EventWriter<RocketCollision>,
+    mut rocket_collisions: EventWriter<ProjectileCollision>,
+    mut flight_notes: EventWriter<FlightNote>,
     audio: Res<Audio>,
     sounds: Res<Assets<AudioSource>>,
 ) {
     let collider_set = QueryPipelineColliderComponentsSet(&collider_query);
 
+    // The path each rocket took since the previous frame
+    let paths = rockets
+        .iter()
+        .map(|(rocket, prev_pos, prev_param, curr_transform, timer, motion, ..)| {
+            let path = sweep_path(
+                prev_pos.0,
+                curr_transform.translation.xy(),
+                prev_param.0,
+                timer.percent(),
+                motion,
+            );
+            (rocket, path)
+        })
+        .collect::<FxHashMap<_, _>>();
+    // The parameters of each rocket's motion at the start and end of its path
+    let params = rockets
+        .iter()
+        .map(|(rocket, _, prev_param, _, timer, ..)| (rocket, (prev_param.0, timer.percent())))
+        .collect::<FxHashMap<_, _>>();
+
     // Each impact contains a player index, a player rocket entity, an optional other player index, a ball/mine/rocket entity, and a time of impact.
     let mut impacts = vec![];
 
-    for (rocket, prev_pos, curr_transform, colliders, owner) in rockets.iter() {
+    for (rocket, prev_pos, _, curr_transform, _, _, colliders, owner, bounces) in rockets.iter() {
         let prev_pos = prev_pos.0;
         let curr_pos = curr_transform.translation.xy();
 
@@ -75,112 +213,230 @@ pub fn collect_balls(
         // Perform shape-casts one at a time to get all the balls swept
         // Unfortunately, kinematic-static CCD doesn't work so mines are getting swept as well here.
         let rocket_collider_entity = colliders.0 .0[0].entity();
-        let mut curr_toi = 0.0;
         let shape = collider_shapes.get(rocket_collider_entity).unwrap();
-        let velocity = curr_pos - prev_pos;
+        let path = &paths[&rocket];
         let groups = InteractionGroups::new(
             CollisionGroups::ROCKET_CAST.bits(),
-            (CollisionGroups::BALL | CollisionGroups::MINE).bits(),
+            (CollisionGroups::BALL
+                | CollisionGroups::MINE
+                | CollisionGroups::OBSTACLE
+                | CollisionGroups::PICKUP)
+                .bits(),
         );
         let mut collided_items = FxHashSet::default();
-        while let Some((item_collider, hit)) = query_pipeline.cast_shape(
-            &collider_set,
-            &Isometry::new(prev_pos.lerp(curr_pos, curr_toi).into(), 0.0),
-            &velocity.into(),
-            &*shape.0 .0,
-            1.0 - curr_toi,
-            groups,
-            Some(&|item_collider| !collided_items.contains(&item_collider.entity())),
-        ) {
-            curr_toi += hit.toi;
-            collided_items.insert(item_collider.entity());
-            let parent = parents.get(item_collider.entity()).unwrap().0;
-            impacts.push((owner.0, rocket, None, parent, curr_toi));
-
-            if mines.get(parent).is_ok() {
-                break;
+        'segments: for (i, segment) in path.windows(2).enumerate() {
+            let velocity = segment[1] - segment[0];
+            let mut segment_toi = 0.0;
+            while let Some((item_collider, hit)) = query_pipeline.cast_shape(
+                &collider_set,
+                &Isometry::new(segment[0].lerp(segment[1], segment_toi).into(), 0.0),
+                &velocity.into(),
+                &*shape.0 .0,
+                1.0 - segment_toi,
+                groups,
+                Some(&|item_collider| !collided_items.contains(&item_collider.entity())),
+            ) {
+                segment_toi += hit.toi;
+                collided_items.insert(item_collider.entity());
+                let parent = parents.get(item_collider.entity()).unwrap().0;
+                // A rocket that just bounced off an obstacle is still touching it
+                if bounces.map_or(false, |bounces| bounces.last_obstacle == Some(parent)) {
+                    continue;
+                }
+                let toi = (i as f32 + segment_toi) / SWEEP_SEGMENTS as f32;
+                impacts.push((owner.0, rocket, None, parent, toi));
+
+                if kinds.mines.get(parent).is_ok() || kinds.debris.get(parent).is_ok() {
+                    break 'segments;
+                }
             }
         }
 
         // Collision with other rockets
-        for (other, other_prev_pos, other_curr_transform, other_colliders, other_owner) in
-            rockets.iter()
-        {
+        for (other, _, _, _, _, _, other_colliders, other_owner, _) in rockets.iter() {
             if other_owner.0 > owner.0 {
-                let other_curr_pos = other_curr_transform.translation.xy();
-                // Account for the other rocket's motion
-                // Collider X goes a->b, and collider Y goes c->d.
-                // From Y's point of view, X goes (a - c) -> (b - d).
-                // This implies a velocity of (b - d) - (a - c) = (b - a) - (d - c)
-                // Since Y is now at d, the shapecast should start at a - c + d
-                let position = prev_pos - other_prev_pos.0 + other_curr_pos;
-                let position = Isometry::new(position.into(), 0.0);
-                let velocity = velocity - (other_curr_pos - other_prev_pos.0);
+                let other_path = &paths[&other];
+                let other_curr_pos = *other_path.last().unwrap();
                 let groups = InteractionGroups::new(
                     CollisionGroups::ROCKET_CAST.bits(),
                     CollisionGroups::ROCKET.bits(),
                 );
 
-                if let Some((_, hit)) = query_pipeline.cast_shape(
-                    &collider_set,
-                    &position,
-                    &velocity.into(),
-                    &*shape.0 .0,
-                    1.0,
-                    groups,
-                    Some(&|rocket_collider| rocket_collider == other_colliders.0 .0[0]),
-                ) {
-                    impacts.push((owner.0, rocket, Some(other_owner.0), other, hit.toi));
+                // Both rockets move along their segments during the same slice of time
+                for (i, (segment, other_segment)) in
+                    path.windows(2).zip(other_path.windows(2)).enumerate()
+                {
+                    // Account for the other rocket's motion
+                    // Collider X goes a->b, and collider Y goes c->d.
+                    // From Y's point of view, X goes (a - c) -> (b - d).
+                    // This implies a velocity of (b - d) - (a - c) = (b - a) - (d - c)
+                    // Since Y is now at d, the shapecast should start at a - c + d
+                    let position = segment[0] - other_segment[0] + other_curr_pos;
+                    let position = Isometry::new(position.into(), 0.0);
+                    let velocity =
+                        (segment[1] - segment[0]) - (other_segment[1] - other_segment[0]);
+
+                    if let Some((_, hit)) = query_pipeline.cast_shape(
+                        &collider_set,
+                        &position,
+                        &velocity.into(),
+                        &*shape.0 .0,
+                        1.0,
+                        groups,
+                        Some(&|rocket_collider| rocket_collider == other_colliders.0 .0[0]),
+                    ) {
+                        let toi = (i as f32 + hit.toi) / SWEEP_SEGMENTS as f32;
+                        impacts.push((owner.0, rocket, Some(other_owner.0), other, toi));
+                        break;
+                    }
                 }
             }
         }
     }
-
     // Figure out which rockets hit which items first
     impacts.sort_by_key(|(_, _, _, _, toi)| Total::from(*toi));
     let mut items_reached = FxHashSet::default();
-    let mut live_rockets = vec![true; players.len()];
-    let mut tois = vec![None; players.len()];
+    // Times of impact of rockets that are no longer alive
+    let mut tois = FxHashMap::default();
+    // Times of the first bounce of rockets that bounced. The rest of their swept path doesn't count.
+    let mut bounce_tois = FxHashMap::default();
+    // Times of impact of balls hit by rockets, for the flight notes
+    let mut hit_tois = vec![];
     for (player_index, rocket, other_player_index, item, toi) in impacts {
-        if let Some(other_player_index) = other_player_index {
+        if bounce_tois.get(&rocket).map_or(false, |bounce_toi| toi > *bounce_toi) {
+            continue;
+        }
+        if other_player_index.is_some() {
             // Rocket-rocket collision. Both rockets must be alive for the collision to happen.
-            if live_rockets[player_index as usize] && live_rockets[other_player_index as usize] {
+            if !tois.contains_key(&rocket) && !tois.contains_key(&item) {
                 commands.entity(rocket).despawn_recursive();
                 commands.entity(item).despawn_recursive();
-                live_rockets[player_index as usize] = false;
-                live_rockets[other_player_index as usize] = false;
-                tois[player_index as usize] = Some(toi);
-                tois[other_player_index as usize] = Some(toi);
-                rocket_collisions.send(RocketCollision { rocket, other: item });
+                tois.insert(rocket, toi);
+                tois.insert(item, toi);
+                rocket_collisions.send(ProjectileCollision { projectile: rocket, other: item });
                 audio.play(sounds.get_handle(asset::Explosion));
             }
-        } else if live_rockets[player_index as usize] && items_reached.insert(item) {
-            commands.entity(item).despawn_recursive();
-
-            if balls.get(item).is_ok() {
-                if let Ok(owner) = owned.get(item) {
-                    // Destruction round
-                    players[owner.0 as usize].num_balls -= 1;
-                    audio.play(sounds.get_handle(asset::PlayerBallPickup));
-                } else {
-                    // Normal round
-                    players[player_index as usize].num_balls += 1;
-                    audio.play(sounds.get_handle(asset::BallPickup));
+        } else if let Ok(debris_transform) = kinds.debris.get(item) {
+            // Debris stays around and can stop any number of rockets
+            if !tois.contains_key(&rocket) {
+                let contact = point_on_path(&paths[&rocket], toi);
+                let normal = (contact - debris_transform.translation.xy()).normalize_or_zero();
+                if let Ok((.., mut motion, _, _, Some(mut bounces))) = rockets.get_mut(rocket) {
+                    if bounces.bounce(&mut motion, contact, normal, Some(item)) {
+                        bounce_tois.insert(rocket, toi);
+                        continue;
+                    }
                 }
-            } else if mines.get(item).is_ok() {
                 commands.entity(rocket).despawn_recursive();
-                live_rockets[player_index as usize] = false;
-                tois[player_index as usize] = Some(toi);
-                rocket_collisions.send(RocketCollision { rocket, other: item });
+                tois.insert(rocket, toi);
+                rocket_collisions.send(ProjectileCollision { projectile: rocket, other: item });
+                audio.play(sounds.get_handle(asset::Explosion));
+            }
+        } else if !tois.contains_key(&rocket) && items_reached.insert(item) {
+            if kinds.balls.get(item).is_ok() {
+                let style = styles.get(rocket).map_or(0, |style| style.0);
+                if !hit_ball(
+                    item,
+                    player_index,
+                    &owned,
+                    &mut statuses,
+                    &mut players,
+                    style,
+                    &audio,
+                    &sounds,
+                ) {
+                    continue;
+                }
+                hit_tois.push((rocket, toi));
+            } else if kinds.mines.get(item).is_ok() {
+                let mut shielded = false;
+                if let Some((_, mut effects)) =
+                    statuses.iter_mut().find(|(owner, _)| owner.0 == player_index)
+                {
+                    shielded = effects.consume(StatusKind::Shield);
+                    if !shielded {
+                        effects.add(StatusKind::Curse, CURSE_ROUNDS);
+                    }
+                }
+                if !shielded {
+                    commands.entity(rocket).despawn_recursive();
+                    tois.insert(rocket, toi);
+                    rocket_collisions.send(ProjectileCollision { projectile: rocket, other: item });
+                }
                 audio.play(sounds.get_handle(asset::Explosion));
+            } else if kinds.ricochets.get(item).is_ok() {
+                if let Some((_, mut effects)) =
+                    statuses.iter_mut().find(|(owner, _)| owner.0 == player_index)
+                {
+                    effects.add(StatusKind::Ricochet, RICOCHET_ROUNDS);
+                }
+                audio.play(sounds.get_handle(asset::BallPickup));
+            } else if kinds.emps.get(item).is_ok() {
+                if let Some((_, mut effects)) =
+                    statuses.iter_mut().find(|(owner, _)| owner.0 == player_index)
+                {
+                    effects.add(StatusKind::Emp, EMP_ROUNDS);
+                }
+                audio.play(sounds.get_handle(asset::BallPickup));
+            } else if kinds.dilations.get(item).is_ok() {
+                if let Some((_, mut effects)) =
+                    statuses.iter_mut().find(|(owner, _)| owner.0 == player_index)
+                {
+                    effects.add(StatusKind::Dilation, DILATION_ROUNDS);
+                }
+                audio.play(sounds.get_handle(asset::BallPickup));
+            }
+
+            commands.entity(item).despawn_recursive();
+        }
+    }
+
+    // Charged rockets also take out the balls around where they blow up
+    for (rocket, toi) in &tois {
+        let charge = if let Ok(charge) = charges.get(*rocket) { charge.0 } else { continue };
+        let player_index =
+            if let Ok((.., owner, _)) = rockets.get(*rocket) { owner.0 } else { continue };
+        let center = point_on_path(&paths[rocket], *toi);
+        let style = styles.get(*rocket).map_or(0, |style| style.0);
+
+        for (ball, transform) in kinds.balls.iter() {
+            if transform.translation.xy().distance(center) < blast_radius(charge)
+                && items_reached.insert(ball)
+                && hit_ball(
+                    ball,
+                    player_index,
+                    &owned,
+                    &mut statuses,
+                    &mut players,
+                    style,
+                    &audio,
+                    &sounds,
+                )
+            {
+                commands.entity(ball).despawn_recursive();
             }
         }
     }
 
-    // Move despawned rockets to impact position. This is relevant for graphing
-    for (_, prev_pos, mut curr_transform, _, owner) in rockets.iter_mut() {
-        if let Some(toi) = tois[owner.0 as usize] {
-            let pos_xy = prev_pos.0.lerp(curr_transform.translation.xy(), toi);
+    let notes = tois
+        .iter()
+        .map(|(rocket, toi)| (*rocket, *toi, NoteKind::Destroyed))
+        .chain(bounce_tois.iter().map(|(rocket, toi)| (*rocket, *toi, NoteKind::Bounce)))
+        .chain(hit_tois.into_iter().map(|(rocket, toi)| (rocket, toi, NoteKind::Hit)));
+    for (rocket, toi, kind) in notes {
+        let (prev_param, curr_param) = params[&rocket];
+        flight_notes.send(FlightNote {
+            projectile: rocket,
+            param: prev_param + (curr_param - prev_param) * toi,
+            position: point_on_path(&paths[&rocket], toi),
+            kind,
+        });
+    }
+
+    // Move despawned and bounced rockets to impact position. This is relevant for graphing
+    for (rocket, _, _, mut curr_transform, ..) in rockets.iter_mut() {
+        if let Some(toi) = tois.get(&rocket).or_else(|| bounce_tois.get(&rocket)) {
+            let pos_xy = point_on_path(&paths[&rocket], *toi);
             curr_transform.translation = pos_xy.extend(curr_transform.translation.z);
         }
     }
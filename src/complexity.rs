@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+
+use crate::{
+    ui::TypedShot,
+    validation::{complexity, point_cost, COMPLEXITY_BUDGET, MAX_POINT_COST},
+};
+
+/// Fraction of a limit past which the meter warns that the functions are getting long or slow
+const WARN_FRACTION: f32 = 0.75;
+
+/// Labels the text that shows how much of the complexity budget the current functions use
+#[derive(Component)]
+pub struct ComplexityText;
+
+/// Writes a count with a `k` or `M` suffix once it gets big
+fn short_count(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{:.1}k", count as f64 / 1e3),
+        _ => format!("{:.1}M", count as f64 / 1e6),
+    }
+}
+
+/// Fraction of the terms and of the steps per point the functions use, whichever is higher
+fn used_fraction(nodes: usize, cost: u64) -> f32 {
+    (nodes as f32 / COMPLEXITY_BUDGET as f32).max(cost as f32 / MAX_POINT_COST as f32)
+}
+
+/// Shows how many terms the current player's functions have, and roughly how much work
+/// each point of the path takes, against their limits as they type
+pub fn update_complexity_meter(
+    shot: Res<TypedShot>,
+    mut text: Query<&mut Text, With<ComplexityText>>,
+) {
+    if !shot.is_changed() {
+        return;
+    }
+
+    let mut text = text.single_mut();
+    let section = &mut text.sections[0];
+    match &shot.parsed {
+        None => section.value = String::new(),
+        Some(Ok(parametric)) => {
+            let nodes = complexity(parametric);
+            let cost = point_cost(parametric);
+            section.value = format!(
+                "Terms: {}/{}, steps per point: {}/{}",
+                nodes,
+                COMPLEXITY_BUDGET,
+                short_count(cost),
+                short_count(MAX_POINT_COST)
+            );
+            let used = used_fraction(nodes, cost);
+            section.style.color = if used > 1.0 {
+                Color::MAROON
+            } else if used > WARN_FRACTION {
+                Color::ORANGE
+            } else {
+                Color::DARK_GREEN
+            };
+        }
+        Some(Err(_)) => {
+            section.value = format!(
+                "Terms: ?/{}, steps per point: ?/{}",
+                COMPLEXITY_BUDGET,
+                short_count(MAX_POINT_COST)
+            );
+            section.style.color = Color::GRAY;
+        }
+    }
+}
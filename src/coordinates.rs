@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+
+use crate::{
+    core::lang::{parse_complex_in, parse_parametric_in, Env, Parametric, ParseError},
+    ui::ButtonsEnabled,
+    validation::{validate_shot, Rejection},
+    Game, Player,
+};
+
+/// How a player's function textboxes are read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoordinateMode {
+    /// x(t) and y(t)
+    Cartesian,
+    /// r(t) and θ(t), with θ in radians
+    Polar,
+    /// A single f(t) over complex numbers, whose real part is x and imaginary part is y
+    Complex,
+}
+
+impl Default for CoordinateMode {
+    fn default() -> Self {
+        Self::Cartesian
+    }
+}
+
+impl CoordinateMode {
+    /// Names of the functions in the textboxes. Textboxes past these are hidden.
+    pub fn axis_names(self) -> &'static [&'static str] {
+        match self {
+            Self::Cartesian => &["x", "y"],
+            Self::Polar => &["r", "θ"],
+            Self::Complex => &["f"],
+        }
+    }
+
+    /// What the mode button says
+    fn label(self) -> &'static str {
+        match self {
+            Self::Cartesian => "Coords: x, y",
+            Self::Polar => "Coords: r, θ",
+            Self::Complex => "Coords: complex",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Self::Cartesian => Self::Polar,
+            Self::Polar => Self::Complex,
+            Self::Complex => Self::Cartesian,
+        }
+    }
+
+    /// Parses a shot entered in this mode into x and y, and validates it
+    pub fn parse(
+        self,
+        fx: &str,
+        fy: &str,
+        assigns: &str,
+        env: &Env,
+    ) -> Result<Parametric, Rejection> {
+        let parametric = self.parse_unvalidated(fx, fy, assigns, env)?;
+        validate_shot(&parametric)?;
+        Ok(parametric)
+    }
+
+    /// Like `parse`, but without checking that the shot is allowed
+    pub fn parse_unvalidated(
+        self,
+        fx: &str,
+        fy: &str,
+        assigns: &str,
+        env: &Env,
+    ) -> Result<Parametric, ParseError> {
+        Ok(match self {
+            Self::Cartesian => parse_parametric_in(fx, fy, assigns, env)?,
+            Self::Polar => parse_parametric_in(fx, fy, assigns, env)?.polar_to_cartesian(),
+            Self::Complex => parse_complex_in(fx, assigns, env)?,
+        })
+    }
+}
+
+/// Button that switches the current player between ways of entering functions
+#[derive(Component)]
+pub struct CoordinateButton;
+
+/// Labels the text of the coordinate mode button
+#[derive(Component)]
+pub struct CoordinateText;
+
+/// Labels the text in front of a function entry textbox, with which of the textboxes it's for
+#[derive(Component)]
+pub struct AxisLabel(pub usize);
+
+/// Labels the row with a function entry textbox and its label, with which of the textboxes it is
+#[derive(Component)]
+pub struct AxisRow(pub usize);
+
+pub fn toggle_coordinate_mode(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<CoordinateButton>)>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    game: Res<Game>,
+    mut players: ResMut<Vec<Player>>,
+) {
+    if !buttons_enabled.0 || !buttons.iter().any(|i| *i == Interaction::Clicked) {
+        return;
+    }
+
+    if let Some(player) = players.get_mut(game.player_turn() as usize) {
+        player.coordinate_mode = player.coordinate_mode.next();
+    }
+}
+
+/// Shows the current player's coordinate mode on the button and the textbox labels,
+/// and hides the textboxes it doesn't use
+pub fn update_coordinate_labels(
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    mut button_text: Query<&mut Text, With<CoordinateText>>,
+    mut labels: Query<(&mut Text, &AxisLabel), Without<CoordinateText>>,
+    mut rows: Query<(&mut Style, &AxisRow)>,
+) {
+    let mode = players
+        .get(game.player_turn() as usize)
+        .map_or(CoordinateMode::Cartesian, |p| p.coordinate_mode);
+    let names = mode.axis_names();
+
+    for mut text in button_text.iter_mut() {
+        if text.sections[0].value != mode.label() {
+            text.sections[0].value = mode.label().to_owned();
+        }
+    }
+
+    for (mut text, AxisLabel(index)) in labels.iter_mut() {
+        let value = names.get(*index).map_or(String::new(), |name| format!("{}(t)=", name));
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+
+    for (mut style, AxisRow(index)) in rows.iter_mut() {
+        let display = if *index < names.len() { Display::Flex } else { Display::None };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
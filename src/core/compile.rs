@@ -0,0 +1,270 @@
+//! Compiling functions to flat bytecode, so rockets don't walk the syntax tree every time
+//! they sample their path
+
+use super::lang::{
+    integral_points, random_value, series_indexes, Call1, Call2, CmpOp, Function, OpType, SeriesOp,
+};
+
+/// An instruction for a stack machine. Instructions that take values pop them,
+/// with the first argument deepest, and push their result.
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    T,
+    Const(f64),
+    /// Runs the code of an assignment
+    Var(usize),
+    Index(usize),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    FloorDiv,
+    Mod,
+    /// Raises the value under the top to the power of the top
+    Pow,
+    Neg,
+    Call1(Call1),
+    Call2(Call2),
+    Cmp(CmpOp),
+    Rand(u32),
+    /// Pops a condition. If it's NaN, pushes NaN and jumps to the end of the `if`.
+    /// If it's 0, skips the `then` branch, which is the next `skip` instructions.
+    If {
+        skip: usize,
+        len: usize,
+    },
+    /// Skips the next `skip` instructions
+    Jump {
+        skip: usize,
+    },
+    /// Integrates the function given by the next `len` instructions from 0 to t
+    Integral {
+        len: usize,
+    },
+    /// Pops the first and last index and adds or multiplies the terms given by the next
+    /// `len` instructions
+    Series {
+        op: SeriesOp,
+        slot: usize,
+        len: usize,
+    },
+}
+
+/// Appends the code for a function, which leaves its value on the stack
+fn compile_into(function: &Function, code: &mut Vec<Op>) {
+    match function {
+        Function::Var(None) => code.push(Op::T),
+        Function::Var(Some(index)) => code.push(Op::Var(*index)),
+        Function::Const(c) => code.push(Op::Const(*c)),
+        // Compiled functions are only real
+        Function::ImagUnit => code.push(Op::Const(f64::NAN)),
+        Function::Index(slot) => code.push(Op::Index(*slot)),
+        // Sums and products start from 0 and 1 like in the syntax tree,
+        // so they come out the same down to the sign of zero
+        Function::Add(terms) => {
+            code.push(Op::Const(0.0));
+            for (term, op) in terms {
+                compile_into(term, code);
+                code.push(if *op == OpType::Normal { Op::Add } else { Op::Sub });
+            }
+        }
+        Function::Mul(factors) => {
+            code.push(Op::Const(1.0));
+            for (factor, op) in factors {
+                compile_into(factor, code);
+                code.push(match op {
+                    OpType::Normal => Op::Mul,
+                    OpType::Inverse => Op::Div,
+                    OpType::Third => Op::FloorDiv,
+                    OpType::Fourth => Op::Mod,
+                });
+            }
+        }
+        // Towers go right to left
+        Function::Exp(fs) => {
+            for f in fs {
+                compile_into(f, code);
+            }
+            code.push(Op::Const(1.0));
+            code.extend(fs.iter().map(|_| Op::Pow));
+        }
+        Function::Neg(f) => {
+            compile_into(f, code);
+            code.push(Op::Neg);
+        }
+        Function::Call1(call, f) => {
+            compile_into(f, code);
+            code.push(Op::Call1(*call));
+        }
+        Function::Call2(call, fs) => {
+            compile_into(&fs[0], code);
+            compile_into(&fs[1], code);
+            code.push(Op::Call2(*call));
+        }
+        Function::Cmp(op, fs) => {
+            compile_into(&fs[0], code);
+            compile_into(&fs[1], code);
+            code.push(Op::Cmp(*op));
+        }
+        Function::If(fs) => {
+            compile_into(&fs[0], code);
+            let then = compile(&fs[1]);
+            let otherwise = compile(&fs[2]);
+            let skip = then.len() + 1;
+            code.push(Op::If { skip, len: skip + otherwise.len() });
+            code.extend(then);
+            code.push(Op::Jump { skip: otherwise.len() });
+            code.extend(otherwise);
+        }
+        Function::Rand(index, bounds) => {
+            compile_into(&bounds[0], code);
+            compile_into(&bounds[1], code);
+            code.push(Op::Rand(*index));
+        }
+        Function::Integral(f) => {
+            let body = compile(f);
+            code.push(Op::Integral { len: body.len() });
+            code.extend(body);
+        }
+        Function::Series(op, slot, fs) => {
+            compile_into(&fs[0], code);
+            compile_into(&fs[1], code);
+            let body = compile(&fs[2]);
+            code.push(Op::Series { op: *op, slot: *slot, len: body.len() });
+            code.extend(body);
+        }
+    }
+}
+
+fn compile(function: &Function) -> Vec<Op> {
+    let mut code = vec![];
+    compile_into(function, &mut code);
+    code
+}
+
+/// A function compiled to bytecode. It evaluates the same as the function it came from,
+/// but doesn't have to chase pointers through the syntax tree.
+#[derive(Clone, Debug)]
+pub struct CompiledFunction {
+    code: Vec<Op>,
+}
+
+impl CompiledFunction {
+    pub fn new(function: &Function) -> Self {
+        Self { code: compile(function) }
+    }
+
+    /// Evaluates the function, with `assigns` being the compiled assignments it refers to
+    pub fn eval(&self, t: f64, assigns: &[CompiledFunction], seed: u64) -> f64 {
+        let mut machine = Machine { assigns, seed, stack: Vec::with_capacity(16) };
+        machine.run(&self.code, t, &[]);
+        machine.stack.pop().unwrap()
+    }
+}
+
+/// State shared by all the code a compiled function runs
+struct Machine<'a> {
+    assigns: &'a [CompiledFunction],
+    seed: u64,
+    stack: Vec<f64>,
+}
+
+impl Machine<'_> {
+    fn pop2(&mut self) -> (f64, f64) {
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+        (a, b)
+    }
+
+    fn binary(&mut self, f: impl FnOnce(f64, f64) -> f64) {
+        let (a, b) = self.pop2();
+        self.stack.push(f(a, b));
+    }
+
+    /// Runs some code, which pushes one value
+    fn run(&mut self, code: &[Op], t: f64, indexes: &[f64]) {
+        let mut pc = 0;
+        while pc < code.len() {
+            match code[pc] {
+                Op::T => self.stack.push(t),
+                Op::Const(c) => self.stack.push(c),
+                Op::Var(index) => {
+                    let assigns = self.assigns;
+                    self.run(&assigns[index].code, t, indexes);
+                }
+                Op::Index(slot) => self.stack.push(indexes[slot]),
+                Op::Add => self.binary(|a, b| a + b),
+                Op::Sub => self.binary(|a, b| a - b),
+                Op::Mul => self.binary(|a, b| a * b),
+                Op::Div => self.binary(|a, b| a / b),
+                Op::FloorDiv => self.binary(f64::div_euclid),
+                Op::Mod => self.binary(f64::rem_euclid),
+                Op::Pow => self.binary(f64::powf),
+                Op::Neg => {
+                    let a = self.stack.pop().unwrap();
+                    self.stack.push(-a);
+                }
+                Op::Call1(call) => {
+                    let a = self.stack.pop().unwrap();
+                    self.stack.push(call.call(a));
+                }
+                Op::Call2(call) => self.binary(|a, b| call.call(a, b)),
+                Op::Cmp(op) => self.binary(|a, b| if op.holds(a, b) { 1.0 } else { 0.0 }),
+                Op::Rand(index) => {
+                    let seed = self.seed;
+                    self.binary(|lo, hi| lo + (hi - lo) * random_value(seed, index));
+                }
+                Op::If { skip, len } => match self.stack.pop().unwrap() {
+                    c if c.is_nan() => {
+                        self.stack.push(f64::NAN);
+                        pc += len;
+                    }
+                    c if c != 0.0 => {}
+                    _ => pc += skip,
+                },
+                Op::Jump { skip } => pc += skip,
+                Op::Integral { len } => {
+                    let body = &code[pc + 1..pc + 1 + len];
+                    let mut sum = 0.0;
+                    for (s, weight) in integral_points(t) {
+                        self.run(body, s, indexes);
+                        sum += weight * self.stack.pop().unwrap();
+                    }
+                    self.stack.push(sum);
+                    pc += len;
+                }
+                Op::Series { op, slot, len } => {
+                    let body = &code[pc + 1..pc + 1 + len];
+                    let (first, last) = self.pop2();
+                    pc += len;
+                    if first.is_nan() || last.is_nan() {
+                        self.stack.push(f64::NAN);
+                        pc += 1;
+                        continue;
+                    }
+                    let mut indexes = indexes[..slot].to_vec();
+                    indexes.push(0.0);
+                    let mut term = |k| {
+                        indexes[slot] = k;
+                        self.run(body, t, &indexes);
+                        self.stack.pop().unwrap()
+                    };
+                    let value = match op {
+                        SeriesOp::Sum => series_indexes(first, last).map(&mut term).sum(),
+                        SeriesOp::Prod => series_indexes(first, last).map(&mut term).product(),
+                    };
+                    self.stack.push(value);
+                }
+            }
+            pc += 1;
+        }
+    }
+}
+
+/// The compiled functions of a parametric equation
+#[derive(Clone, Debug)]
+pub struct CompiledParametric {
+    pub x: CompiledFunction,
+    pub y: CompiledFunction,
+    pub assigns: Vec<CompiledFunction>,
+}
@@ -0,0 +1,363 @@
+//! Symbolic differentiation of functions with respect to `t`, for the `d` operator
+
+use super::lang::{Call1, Call2, CmpOp, Function, OpType, SeriesOp};
+
+fn is_zero(f: &Function) -> bool {
+    matches!(f, Function::Const(c) if *c == 0.0)
+}
+
+/// Adds up terms, adding together the ones that are constants
+fn sum(terms: Vec<(Function, OpType)>) -> Function {
+    let mut constant = 0.0;
+    let mut terms = terms
+        .into_iter()
+        .filter(|(f, op)| match (f, op) {
+            (Function::Const(c), OpType::Normal) => {
+                constant += c;
+                false
+            }
+            (Function::Const(c), _) => {
+                constant -= c;
+                false
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+    if constant != 0.0 {
+        terms.push((Function::Const(constant), OpType::Normal));
+    }
+
+    if let Some((_, OpType::Inverse)) = terms.first() {
+        let (first, _) = terms.remove(0);
+        terms.insert(0, (neg(first), OpType::Normal));
+    }
+    match terms.len() {
+        0 => Function::Const(0.0),
+        1 => terms.pop().unwrap().0,
+        _ => Function::Add(terms),
+    }
+}
+
+/// Multiplies and divides factors, which can only use `OpType::Normal` and `OpType::Inverse`.
+/// Constant factors get multiplied together in front.
+fn product(factors: Vec<(Function, OpType)>) -> Function {
+    let mut coefficient = 1.0;
+    let mut factors = factors
+        .into_iter()
+        .flat_map(|(f, op)| match f {
+            // Products of products get flattened, like the parser does
+            Function::Mul(inner)
+                if op == OpType::Normal
+                    && inner
+                        .iter()
+                        .all(|(_, op)| matches!(op, OpType::Normal | OpType::Inverse)) =>
+            {
+                inner
+            }
+            f => vec![(f, op)],
+        })
+        .filter(|(f, op)| match (f, op) {
+            (Function::Const(c), OpType::Normal) => {
+                coefficient *= c;
+                false
+            }
+            (Function::Const(c), _) => {
+                coefficient /= c;
+                false
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+
+    if coefficient == 0.0 || factors.is_empty() {
+        return Function::Const(coefficient);
+    }
+    if coefficient != 1.0 || factors[0].1 == OpType::Inverse {
+        factors.insert(0, (Function::Const(coefficient), OpType::Normal));
+    }
+    if factors.len() == 1 {
+        factors.pop().unwrap().0
+    } else {
+        Function::Mul(factors)
+    }
+}
+
+fn neg(f: Function) -> Function {
+    match f {
+        f if is_zero(&f) => f,
+        Function::Neg(f) => *f,
+        f => Function::Neg(Box::new(f)),
+    }
+}
+
+fn call1(call: Call1, f: &Function) -> Function {
+    Function::Call1(call, Box::new(f.clone()))
+}
+
+fn pow(base: Function, exponent: f64) -> Function {
+    power(base, Function::Const(exponent))
+}
+
+fn power(base: Function, exponent: Function) -> Function {
+    match exponent {
+        Function::Const(c) if c == 0.0 => Function::Const(1.0),
+        Function::Const(c) if c == 1.0 => base,
+        exponent => Function::Exp(vec![base, exponent]),
+    }
+}
+
+/// `if(condition, a, b)`, or just 0 if both are 0
+fn branch(condition: Function, a: Function, b: Function) -> Function {
+    if is_zero(&a) && is_zero(&b) {
+        a
+    } else {
+        Function::If(Box::new([condition, a, b]))
+    }
+}
+
+/// The function the power tower `fs` stands for
+fn tower(fs: &[Function]) -> Function {
+    match fs {
+        [f] => f.clone(),
+        fs => Function::Exp(fs.to_vec()),
+    }
+}
+
+/// Derivative of a function of `f`, divided by the derivative of `f`, as factors
+fn call1_factors(call: Call1, f: &Function) -> Option<Vec<(Function, OpType)>> {
+    use Function::Const;
+    use OpType::{Inverse, Normal};
+
+    let one_minus_square = || sum(vec![(Const(1.0), Normal), (pow(f.clone(), 2.0), Inverse)]);
+    let factors = match call {
+        Call1::Sin => vec![(call1(Call1::Cos, f), Normal)],
+        Call1::Cos => vec![(neg(call1(Call1::Sin, f)), Normal)],
+        Call1::Tan => vec![(pow(call1(Call1::Cos, f), 2.0), Inverse)],
+        Call1::Asin => vec![(Function::Call1(Call1::Sqrt, Box::new(one_minus_square())), Inverse)],
+        Call1::Acos => vec![
+            (Const(-1.0), Normal),
+            (Function::Call1(Call1::Sqrt, Box::new(one_minus_square())), Inverse),
+        ],
+        Call1::Atan => {
+            vec![(sum(vec![(Const(1.0), Normal), (pow(f.clone(), 2.0), Normal)]), Inverse)]
+        }
+        Call1::Sinh => vec![(call1(Call1::Cosh, f), Normal)],
+        Call1::Cosh => vec![(call1(Call1::Sinh, f), Normal)],
+        Call1::Tanh => vec![(pow(call1(Call1::Cosh, f), 2.0), Inverse)],
+        Call1::Asinh | Call1::Acosh => {
+            let op = if call == Call1::Asinh { Normal } else { Inverse };
+            let inner = sum(vec![(pow(f.clone(), 2.0), Normal), (Const(1.0), op)]);
+            vec![(Function::Call1(Call1::Sqrt, Box::new(inner)), Inverse)]
+        }
+        Call1::Atanh => vec![(one_minus_square(), Inverse)],
+        Call1::Exp => vec![(call1(Call1::Exp, f), Normal)],
+        Call1::Ln => vec![(f.clone(), Inverse)],
+        Call1::Log2 => vec![(f.clone(), Inverse), (Const(2f64.ln()), Inverse)],
+        Call1::Log10 => vec![(f.clone(), Inverse), (Const(10f64.ln()), Inverse)],
+        Call1::Sqrt => vec![(Const(2.0), Inverse), (call1(Call1::Sqrt, f), Inverse)],
+        Call1::Cbrt => vec![(Const(3.0), Inverse), (pow(call1(Call1::Cbrt, f), 2.0), Inverse)],
+        Call1::Abs => vec![(call1(Call1::Sign, f), Normal)],
+        Call1::Fract => vec![],
+        // Steps are flat everywhere but where they jump
+        Call1::Sign | Call1::Floor | Call1::Ceil | Call1::Round => return None,
+    };
+    Some(factors)
+}
+
+/// Derivative of `base ^ exponent`
+fn pow_derivative(
+    base: &Function,
+    d_base: Function,
+    exponent: &Function,
+    d_exponent: Function,
+) -> Function {
+    use OpType::{Inverse, Normal};
+
+    if is_zero(&d_exponent) {
+        // The power rule also works for negative bases
+        let lowered = power(
+            base.clone(),
+            sum(vec![(exponent.clone(), Normal), (Function::Const(1.0), Inverse)]),
+        );
+        return product(vec![(exponent.clone(), Normal), (lowered, Normal), (d_base, Normal)]);
+    }
+    let value = Function::Exp(vec![base.clone(), exponent.clone()]);
+    let log_derivative = sum(vec![
+        (product(vec![(d_exponent, Normal), (call1(Call1::Ln, base), Normal)]), Normal),
+        (
+            product(vec![(exponent.clone(), Normal), (d_base, Normal), (base.clone(), Inverse)]),
+            Normal,
+        ),
+    ]);
+    product(vec![(value, Normal), (log_derivative, Normal)])
+}
+
+impl Function {
+    /// Derivative with respect to `t`. `var_derivative` gives the derivative of the variable
+    /// with some index. Steps like `floor` and comparisons count as flat, since they are
+    /// everywhere but where they jump.
+    pub fn derivative(&self, var_derivative: &mut impl FnMut(usize) -> Function) -> Function {
+        use Function::Const;
+        use OpType::{Inverse, Normal};
+
+        match self {
+            Self::Var(None) => Const(1.0),
+            Self::Var(Some(index)) => var_derivative(*index),
+            Self::Const(_) | Self::ImagUnit | Self::Index(_) | Self::Cmp(..) => Const(0.0),
+            Self::Integral(f) => (**f).clone(),
+            // The bounds only change in steps, so they count as flat too
+            Self::Series(op, slot, fs) => {
+                let [first, last, term] = &**fs;
+                let d_term = term.derivative(var_derivative);
+                if is_zero(&d_term) {
+                    return Const(0.0);
+                }
+                let series = |op, term| {
+                    Self::Series(op, *slot, Box::new([first.clone(), last.clone(), term]))
+                };
+                match op {
+                    SeriesOp::Sum => series(SeriesOp::Sum, d_term),
+                    // The derivative of a product is the product times the sum of the
+                    // terms' log derivatives
+                    SeriesOp::Prod => product(vec![
+                        (self.clone(), Normal),
+                        (
+                            series(
+                                SeriesOp::Sum,
+                                product(vec![(d_term, Normal), (term.clone(), Inverse)]),
+                            ),
+                            Normal,
+                        ),
+                    ]),
+                }
+            }
+            Self::Add(terms) => {
+                sum(terms.iter().map(|(f, op)| (f.derivative(var_derivative), *op)).collect())
+            }
+            Self::Mul(factors) => {
+                // Go through the factors in order, keeping the product so far and its derivative
+                let mut acc = Const(1.0);
+                let mut d_acc = Const(0.0);
+                for (f, op) in factors {
+                    let d_f = f.derivative(var_derivative);
+                    // Parts of the product rule
+                    let rule = |d_acc, acc: &Function, d_f: Function| {
+                        (
+                            product(vec![(d_acc, Normal), (f.clone(), Normal)]),
+                            product(vec![(acc.clone(), Normal), (d_f, Normal)]),
+                        )
+                    };
+                    d_acc = match op {
+                        Normal => {
+                            let (d_acc_f, acc_d_f) = rule(d_acc, &acc, d_f);
+                            sum(vec![(d_acc_f, Normal), (acc_d_f, Normal)])
+                        }
+                        Inverse => {
+                            let (d_acc_f, acc_d_f) = rule(d_acc, &acc, d_f);
+                            product(vec![
+                                (sum(vec![(d_acc_f, Normal), (acc_d_f, Inverse)]), Normal),
+                                (pow(f.clone(), 2.0), Inverse),
+                            ])
+                        }
+                        // Floor division is a step
+                        OpType::Third => Const(0.0),
+                        // a % b is a - b * (a // b)
+                        OpType::Fourth => {
+                            let quotient =
+                                Self::Mul(vec![(acc.clone(), Normal), (f.clone(), OpType::Third)]);
+                            sum(vec![
+                                (d_acc, Normal),
+                                (product(vec![(d_f, Normal), (quotient, Normal)]), Inverse),
+                            ])
+                        }
+                    };
+                    acc = match op {
+                        Normal | Inverse => product(vec![(acc, Normal), (f.clone(), *op)]),
+                        _ => Self::Mul(vec![(acc, Normal), (f.clone(), *op)]),
+                    };
+                }
+                d_acc
+            }
+            Self::Exp(fs) => {
+                // Towers go right to left, so start from the top
+                let mut d_exponent = fs.last().unwrap().derivative(var_derivative);
+                for i in (0..fs.len() - 1).rev() {
+                    let d_base = fs[i].derivative(var_derivative);
+                    d_exponent = pow_derivative(&fs[i], d_base, &tower(&fs[i + 1..]), d_exponent);
+                }
+                d_exponent
+            }
+            Self::Neg(f) => neg(f.derivative(var_derivative)),
+            Self::Call1(call, f) => match call1_factors(*call, f) {
+                Some(factors) => {
+                    let d_f = f.derivative(var_derivative);
+                    product(vec![(d_f, Normal)].into_iter().chain(factors).collect())
+                }
+                None => Const(0.0),
+            },
+            Self::Call2(call, fs) => {
+                let [a, b] = &**fs;
+                let (d_a, d_b) = (a.derivative(var_derivative), b.derivative(var_derivative));
+                match call {
+                    Call2::Min | Call2::Max => {
+                        let op = if *call == Call2::Min { CmpOp::Le } else { CmpOp::Ge };
+                        let cmp = Self::Cmp(op, Box::new([a.clone(), b.clone()]));
+                        branch(cmp, d_a, d_b)
+                    }
+                    // atan2(a, b) is the angle of (b, a)
+                    Call2::Atan2 => product(vec![
+                        (
+                            sum(vec![
+                                (product(vec![(b.clone(), Normal), (d_a, Normal)]), Normal),
+                                (product(vec![(a.clone(), Normal), (d_b, Normal)]), Inverse),
+                            ]),
+                            Normal,
+                        ),
+                        (
+                            sum(vec![(pow(a.clone(), 2.0), Normal), (pow(b.clone(), 2.0), Normal)]),
+                            Inverse,
+                        ),
+                    ]),
+                    // mod(a, b) is a - b * (a // b)
+                    Call2::Mod => sum(vec![
+                        (d_a, Normal),
+                        (
+                            product(vec![
+                                (d_b, Normal),
+                                (
+                                    Self::Mul(vec![
+                                        (a.clone(), Normal),
+                                        (b.clone(), OpType::Third),
+                                    ]),
+                                    Normal,
+                                ),
+                            ]),
+                            Inverse,
+                        ),
+                    ]),
+                }
+            }
+            Self::If(fs) => branch(
+                fs[0].clone(),
+                fs[1].derivative(var_derivative),
+                fs[2].derivative(var_derivative),
+            ),
+            // The random value stays the same, but the bounds can move
+            Self::Rand(index, bounds) => {
+                let (d_lo, d_hi) =
+                    (bounds[0].derivative(var_derivative), bounds[1].derivative(var_derivative));
+                let unit = Self::Rand(*index, Box::new([Const(0.0), Const(1.0)]));
+                sum(vec![
+                    (d_lo.clone(), Normal),
+                    (
+                        product(vec![
+                            (sum(vec![(d_hi, Normal), (d_lo, Inverse)]), Normal),
+                            (unit, Normal),
+                        ]),
+                        Normal,
+                    ),
+                ])
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! How much ink functions cost, when the rules give each player a limited supply of it
+
+use super::lang::{Function, Parametric};
+
+impl Function {
+    /// Ink it takes to write this function. Numbers, variables, and operators cost 1 each,
+    /// builtins cost 2, random values 3, and integrals, sums, and products 4,
+    /// since they do the most for their length.
+    pub fn ink_cost(&self) -> u32 {
+        let all = |fs: &mut dyn Iterator<Item = &Function>| fs.map(Function::ink_cost).sum::<u32>();
+        match self {
+            Self::Var(_) | Self::Const(_) | Self::ImagUnit | Self::Index(_) => 1,
+            // One for each operator between the terms
+            Self::Add(terms) | Self::Mul(terms) => {
+                terms.len().saturating_sub(1) as u32 + all(&mut terms.iter().map(|(f, _)| f))
+            }
+            Self::Exp(factors) => factors.len().saturating_sub(1) as u32 + all(&mut factors.iter()),
+            Self::Neg(inner) => 1 + inner.ink_cost(),
+            Self::Cmp(_, args) => 1 + all(&mut args.iter()),
+            Self::Call1(_, arg) => 2 + arg.ink_cost(),
+            Self::Call2(_, args) => 2 + all(&mut args.iter()),
+            Self::If(args) => 2 + all(&mut args.iter()),
+            Self::Rand(_, args) => 3 + all(&mut args.iter()),
+            Self::Integral(inner) => 4 + inner.ink_cost(),
+            Self::Series(_, _, args) => 4 + all(&mut args.iter()),
+        }
+    }
+}
+
+impl Parametric {
+    /// Ink it takes to write all the functions of the shot. Derivatives the parser binds
+    /// as variables are left out, since the player didn't write them.
+    pub fn ink_cost(&self) -> u32 {
+        let y = (!self.complex).then(|| &self.y);
+        let assigns = self
+            .assigns
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.derivative_assigns.contains(i))
+            .map(|(_, assign)| assign);
+        [&self.x].into_iter().chain(y).chain(assigns).map(Function::ink_cost).sum()
+    }
+}
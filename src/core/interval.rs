@@ -0,0 +1,405 @@
+//! Interval analysis of shots, to warn about places a function might be undefined
+//! before it gets fired. Ranges are worked out for t from 0 to 1, and can be wider than
+//! the real ranges, so the warnings are about what might happen rather than what will.
+
+use std::{
+    f64::consts::{FRAC_PI_2, PI, TAU},
+    fmt,
+};
+
+use super::lang::{Call1, Call2, Function, OpType, Parametric, SeriesOp, MAX_SERIES_TERMS};
+
+/// A range of values a function takes, including both ends
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl Interval {
+    pub const ALL: Self = Self { lo: f64::NEG_INFINITY, hi: f64::INFINITY };
+
+    /// Range between two bounds. Bounds that aren't numbers make it the whole number line.
+    pub fn new(lo: f64, hi: f64) -> Self {
+        if lo.is_nan() || hi.is_nan() {
+            Self::ALL
+        } else {
+            Self { lo, hi }
+        }
+    }
+
+    pub fn point(value: f64) -> Self {
+        Self::new(value, value)
+    }
+
+    /// Smallest range containing both ranges
+    pub fn hull(self, other: Self) -> Self {
+        Self::new(self.lo.min(other.lo), self.hi.max(other.hi))
+    }
+
+    pub fn contains(self, value: f64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    /// Whether the range contains `phase` plus some multiple of `period`
+    fn contains_periodic(self, phase: f64, period: f64) -> bool {
+        let k = ((self.lo - phase) / period).ceil();
+        phase + k * period <= self.hi
+    }
+
+    /// Applies a function that never decreases
+    fn map_increasing(self, f: impl Fn(f64) -> f64) -> Self {
+        Self::new(f(self.lo), f(self.hi))
+    }
+
+    /// Applies a function of two ranges that only goes one way in each argument,
+    /// so its extremes are at the corners
+    fn corners(self, other: Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        let values = [
+            f(self.lo, other.lo),
+            f(self.lo, other.hi),
+            f(self.hi, other.lo),
+            f(self.hi, other.hi),
+        ];
+        if values.iter().any(|v| v.is_nan()) {
+            return Self::ALL;
+        }
+        Self::new(
+            values.iter().copied().fold(f64::INFINITY, f64::min),
+            values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+}
+
+/// Something that might make a shot undefined somewhere along its path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DomainWarning {
+    DivideByZero,
+    /// A unary function might get an argument it isn't defined for
+    OutsideDomain(Call1),
+    /// A negative number might get raised to a power that isn't a whole number
+    NegativeBase,
+}
+
+impl fmt::Display for DomainWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DivideByZero => write!(f, "might divide by zero"),
+            Self::OutsideDomain(Call1::Sqrt) => {
+                write!(f, "might take the square root of a negative number")
+            }
+            Self::OutsideDomain(Call1::Ln | Call1::Log2 | Call1::Log10) => {
+                write!(f, "might take the log of a number that isn't positive")
+            }
+            Self::OutsideDomain(Call1::Tan) => write!(f, "might take tan where it's infinite"),
+            Self::OutsideDomain(call) => {
+                write!(f, "might take {} outside where it's defined", call.name())
+            }
+            Self::NegativeBase => {
+                write!(f, "might raise a negative number to a power that isn't a whole number")
+            }
+        }
+    }
+}
+
+fn sin_range(x: Interval) -> Interval {
+    if !x.lo.is_finite() || !x.hi.is_finite() || x.hi - x.lo >= TAU {
+        return Interval::new(-1.0, 1.0);
+    }
+    let mut range = Interval::point(x.lo.sin()).hull(Interval::point(x.hi.sin()));
+    if x.contains_periodic(FRAC_PI_2, TAU) {
+        range.hi = 1.0;
+    }
+    if x.contains_periodic(-FRAC_PI_2, TAU) {
+        range.lo = -1.0;
+    }
+    range
+}
+
+/// Range of a function that decreases up to 0 and increases after it, like `abs`
+fn even_range(x: Interval, f: fn(f64) -> f64) -> Interval {
+    let ends = Interval::point(f(x.lo)).hull(Interval::point(f(x.hi)));
+    if x.contains(0.0) {
+        Interval::new(f(0.0), ends.hi)
+    } else {
+        ends
+    }
+}
+
+/// Works out the ranges of functions and collects warnings along the way
+struct Analysis<'a> {
+    assigns: &'a [Function],
+    /// Ranges of the variables that have been looked at. `Some(None)` means the variable is
+    /// undefined everywhere.
+    ranges: Vec<Option<Option<Interval>>>,
+    warnings: Vec<DomainWarning>,
+}
+
+impl Analysis<'_> {
+    fn warn(&mut self, warning: DomainWarning) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// Range of `a / b`
+    fn divide(&mut self, a: Interval, b: Interval) -> Interval {
+        if b.contains(0.0) {
+            self.warn(DomainWarning::DivideByZero);
+            Interval::ALL
+        } else {
+            a.corners(b, |a, b| a / b)
+        }
+    }
+
+    /// Range of `base ^ exponent`
+    fn pow(&mut self, base: Interval, exponent: Interval) -> Interval {
+        if base.contains(0.0) && exponent.lo < 0.0 {
+            self.warn(DomainWarning::DivideByZero);
+            return Interval::ALL;
+        }
+        if base.lo >= 0.0 {
+            return base.corners(exponent, f64::powf);
+        }
+
+        let whole = exponent.lo == exponent.hi && exponent.lo.fract() == 0.0;
+        if whole {
+            let n = exponent.lo;
+            let ends = Interval::point(base.lo.powf(n)).hull(Interval::point(base.hi.powf(n)));
+            // Even powers are smallest at 0
+            if base.contains(0.0) {
+                ends.hull(Interval::point(0.0))
+            } else {
+                ends
+            }
+        } else {
+            self.warn(DomainWarning::NegativeBase);
+            if base.hi < 0.0 {
+                Interval::ALL
+            } else {
+                Interval::new(0.0, base.hi).corners(exponent, f64::powf)
+            }
+        }
+    }
+
+    /// Range of a unary function, with `domain` being where it's defined
+    fn restricted(
+        &mut self,
+        call: Call1,
+        x: Interval,
+        domain: Interval,
+        open: bool,
+    ) -> Option<Interval> {
+        let outside = if open {
+            x.lo <= domain.lo || x.hi >= domain.hi
+        } else {
+            x.lo < domain.lo || x.hi > domain.hi
+        };
+        if outside {
+            self.warn(DomainWarning::OutsideDomain(call));
+        }
+        let x = Interval::new(x.lo.max(domain.lo), x.hi.min(domain.hi));
+        (x.lo <= x.hi).then(|| x)
+    }
+
+    fn call1(&mut self, call: Call1, x: Interval) -> Option<Interval> {
+        let f = |x| call.call(x);
+        Some(match call {
+            Call1::Sin => sin_range(x),
+            Call1::Cos => sin_range(Interval::new(x.lo + FRAC_PI_2, x.hi + FRAC_PI_2)),
+            Call1::Tan => {
+                if x.contains_periodic(FRAC_PI_2, PI) {
+                    self.warn(DomainWarning::OutsideDomain(call));
+                    Interval::ALL
+                } else {
+                    x.map_increasing(f)
+                }
+            }
+            Call1::Asin | Call1::Atanh => {
+                let open = call == Call1::Atanh;
+                self.restricted(call, x, Interval::new(-1.0, 1.0), open)?.map_increasing(f)
+            }
+            Call1::Acos => {
+                let x = self.restricted(call, x, Interval::new(-1.0, 1.0), false)?;
+                Interval::new(f(x.hi), f(x.lo))
+            }
+            Call1::Acosh => self
+                .restricted(call, x, Interval::new(1.0, f64::INFINITY), false)?
+                .map_increasing(f),
+            Call1::Sqrt => self
+                .restricted(call, x, Interval::new(0.0, f64::INFINITY), false)?
+                .map_increasing(f),
+            Call1::Ln | Call1::Log2 | Call1::Log10 => {
+                self.restricted(call, x, Interval::new(0.0, f64::INFINITY), true)?.map_increasing(f)
+            }
+            Call1::Cosh => even_range(x, f64::cosh),
+            Call1::Abs => even_range(x, f64::abs),
+            Call1::Fract => Interval::new(0.0, 1.0),
+            Call1::Atan
+            | Call1::Sinh
+            | Call1::Tanh
+            | Call1::Asinh
+            | Call1::Exp
+            | Call1::Cbrt
+            | Call1::Sign
+            | Call1::Floor
+            | Call1::Ceil
+            | Call1::Round => x.map_increasing(f),
+        })
+    }
+
+    /// Range of a function, where `indexes` are the ranges of the enclosing index variables.
+    /// `None` means it isn't defined anywhere.
+    fn range(&mut self, function: &Function, indexes: &[Interval]) -> Option<Interval> {
+        Some(match function {
+            Function::Var(None) => Interval::new(0.0, 1.0),
+            Function::Var(Some(index)) => {
+                if let Some(range) = self.ranges[*index] {
+                    return range;
+                }
+                let assigns = self.assigns;
+                let range = self.range(&assigns[*index], indexes);
+                self.ranges[*index] = Some(range);
+                range?
+            }
+            Function::Const(c) => Interval::point(*c),
+            Function::ImagUnit => Interval::ALL,
+            Function::Index(slot) => indexes[*slot],
+            Function::Add(terms) => {
+                let mut acc = Interval::point(0.0);
+                for (term, op) in terms {
+                    let term = self.range(term, indexes)?;
+                    acc = match op {
+                        OpType::Normal => Interval::new(acc.lo + term.lo, acc.hi + term.hi),
+                        _ => Interval::new(acc.lo - term.hi, acc.hi - term.lo),
+                    };
+                }
+                acc
+            }
+            Function::Mul(factors) => {
+                let mut acc = Interval::point(1.0);
+                for (factor, op) in factors {
+                    let factor = self.range(factor, indexes)?;
+                    acc = match op {
+                        OpType::Normal => acc.corners(factor, |a, b| a * b),
+                        OpType::Inverse => self.divide(acc, factor),
+                        OpType::Third => {
+                            let quotient = self.divide(acc, factor);
+                            Interval::new(quotient.lo.floor(), quotient.hi.ceil())
+                        }
+                        OpType::Fourth => self.modulo(factor),
+                    };
+                }
+                acc
+            }
+            // Towers go right to left
+            Function::Exp(fs) => {
+                let mut acc = self.range(fs.last().unwrap(), indexes)?;
+                for f in fs.iter().rev().skip(1) {
+                    let base = self.range(f, indexes)?;
+                    acc = self.pow(base, acc);
+                }
+                acc
+            }
+            Function::Neg(f) => {
+                let x = self.range(f, indexes)?;
+                Interval::new(-x.hi, -x.lo)
+            }
+            Function::Call1(call, f) => {
+                let x = self.range(f, indexes)?;
+                self.call1(*call, x)?
+            }
+            Function::Call2(call, fs) => {
+                let a = self.range(&fs[0], indexes)?;
+                let b = self.range(&fs[1], indexes)?;
+                match call {
+                    Call2::Min => Interval::new(a.lo.min(b.lo), a.hi.min(b.hi)),
+                    Call2::Max => Interval::new(a.lo.max(b.lo), a.hi.max(b.hi)),
+                    Call2::Atan2 => Interval::new(-PI, PI),
+                    Call2::Mod => self.modulo(b),
+                }
+            }
+            Function::Cmp(_, fs) => {
+                self.range(&fs[0], indexes)?;
+                self.range(&fs[1], indexes)?;
+                Interval::new(0.0, 1.0)
+            }
+            // Branches that can't be taken don't get warnings
+            Function::If(fs) => {
+                let condition = self.range(&fs[0], indexes)?;
+                if condition == Interval::point(0.0) {
+                    self.range(&fs[2], indexes)?
+                } else if !condition.contains(0.0) {
+                    self.range(&fs[1], indexes)?
+                } else {
+                    match (self.range(&fs[1], indexes), self.range(&fs[2], indexes)) {
+                        (Some(a), Some(b)) => a.hull(b),
+                        (a, b) => a.or(b)?,
+                    }
+                }
+            }
+            Function::Rand(_, bounds) => {
+                let lo = self.range(&bounds[0], indexes)?;
+                let hi = self.range(&bounds[1], indexes)?;
+                lo.hull(hi)
+            }
+            // The integral from 0 to t of a function is at most t times its biggest value
+            Function::Integral(f) => {
+                let x = self.range(f, indexes)?;
+                Interval::new(x.lo.min(0.0), x.hi.max(0.0))
+            }
+            Function::Series(op, slot, fs) => {
+                let first = self.range(&fs[0], indexes)?;
+                let last = self.range(&fs[1], indexes)?;
+                let index = Interval::new(first.lo.round(), last.hi.round());
+                let identity = if *op == SeriesOp::Sum { 0.0 } else { 1.0 };
+                if index.lo > index.hi {
+                    return Some(Interval::point(identity));
+                }
+                let mut indexes = indexes[..*slot].to_vec();
+                indexes.push(index);
+                let term = self.range(&fs[2], &indexes)?;
+                // Bounds that aren't fixed can give different numbers of terms
+                let count = |first: f64, last: f64| {
+                    (last.round() - first.round() + 1.0).clamp(0.0, MAX_SERIES_TERMS as f64)
+                };
+                let (fewest, most) = (count(first.hi, last.lo), count(first.lo, last.hi));
+                match op {
+                    SeriesOp::Sum => Interval::new(
+                        (term.lo * fewest).min(term.lo * most),
+                        (term.hi * fewest).max(term.hi * most),
+                    ),
+                    SeriesOp::Prod if term.lo >= -1.0 && term.hi <= 1.0 => {
+                        Interval::new(term.lo.min(0.0).max(-1.0), 1.0)
+                    }
+                    SeriesOp::Prod if term.lo >= 0.0 => Interval::new(0.0, term.hi.powf(most)),
+                    SeriesOp::Prod => Interval::ALL,
+                }
+            }
+        })
+    }
+
+    /// Range of `a % b`, which is from 0 up to the size of `b`
+    fn modulo(&mut self, b: Interval) -> Interval {
+        if b.contains(0.0) {
+            self.warn(DomainWarning::DivideByZero);
+        }
+        Interval::new(0.0, b.lo.abs().max(b.hi.abs()))
+    }
+}
+
+/// Places where a shot might be undefined for some t from 0 to 1, in the order they come up.
+/// Complex shots don't get warnings, since they're defined in more places.
+pub fn domain_warnings(parametric: &Parametric) -> Vec<DomainWarning> {
+    if parametric.complex {
+        return vec![];
+    }
+    let mut analysis = Analysis {
+        assigns: &parametric.assigns,
+        ranges: vec![None; parametric.assigns.len()],
+        warnings: vec![],
+    };
+    analysis.range(&parametric.x, &[]);
+    analysis.range(&parametric.y, &[]);
+    analysis.warnings
+}
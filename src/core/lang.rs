@@ -0,0 +1,1086 @@
+//! The function language: parsing what players type into parametric curves, and evaluating them
+
+use bevy::math::Vec2;
+use fxhash::FxHashMap;
+use once_cell::sync::Lazy;
+use pest::{
+    error::{Error, ErrorVariant, InputLocation, LineColLocation},
+    iterators::{Pair, Pairs},
+    Parser,
+};
+use std::{fmt, iter, mem, ops::Range};
+
+use super::{
+    compile::{CompiledFunction, CompiledParametric},
+    complex::Complex,
+};
+
+#[derive(Parser)]
+#[grammar = "function.pest"]
+pub struct FunctionParser;
+
+macro_rules! def_str_lookup {
+    (
+        $(#[$attr:meta])*
+        pub enum $enum_name:ident {
+            $($var:ident ( $string:tt ) => $func:expr),* $(,)?
+        }
+        static $set:ident: $set_ty:ty;
+        const $arr:ident: $arr_ty:ty;
+    ) => {
+        $(#[$attr])*
+        pub enum $enum_name {
+            $($var),*
+        }
+
+        static $set: $set_ty = once_cell::sync::Lazy::new(|| [
+            $(($string, $enum_name::$var)),*
+        ].into_iter().collect());
+
+        const $arr: $arr_ty = [$($func),*];
+
+        impl $enum_name {
+            /// Name of the function in the language
+            pub fn name(self) -> &'static str {
+                [$($string),*][self as usize]
+            }
+        }
+    };
+}
+
+def_str_lookup! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Call1 {
+        Sin("sin") => f64::sin,
+        Cos("cos") => f64::cos,
+        Tan("tan") => f64::tan,
+        Asin("asin") => f64::asin,
+        Acos("acos") => f64::acos,
+        Atan("atan") => f64::atan,
+        Sinh("sinh") => f64::sinh,
+        Cosh("cosh") => f64::cosh,
+        Tanh("tanh") => f64::tanh,
+        Asinh("asinh") => f64::asinh,
+        Acosh("acosh") => f64::acosh,
+        Atanh("atanh") => f64::atanh,
+        Exp("exp") => f64::exp,
+        Ln("ln") => f64::ln,
+        Log2("log2") => f64::log2,
+        Log10("log10") => f64::log10,
+        Sqrt("sqrt") => f64::sqrt,
+        Cbrt("cbrt") => f64::cbrt,
+        Abs("abs") => f64::abs,
+        Sign("sign") => f64::signum,
+        Floor("floor") => f64::floor,
+        Ceil("ceil") => f64::ceil,
+        Round("round") => f64::round,
+        Fract("fract") => f64::fract,
+    }
+
+    static CALL_1_FN_MAP: Lazy<FxHashMap<&str, Call1>>;
+
+    const CALL_1_FNS: [fn(f64) -> f64; 24];
+}
+
+impl Call1 {
+    pub(crate) fn call(self, t: f64) -> f64 {
+        CALL_1_FNS[self as usize](t)
+    }
+
+    /// Calls the function on a complex number. Functions without a complex version
+    /// only work on real numbers.
+    fn call_complex(self, z: Complex) -> Complex {
+        match self {
+            Self::Sin => z.sin(),
+            Self::Cos => z.cos(),
+            Self::Tan => z.tan(),
+            Self::Sinh => z.sinh(),
+            Self::Cosh => z.cosh(),
+            Self::Tanh => z.tanh(),
+            Self::Exp => z.exp(),
+            Self::Ln if !z.is_real() || z.re < 0.0 => z.ln(),
+            Self::Sqrt if !z.is_real() || z.re < 0.0 => z.sqrt(),
+            Self::Abs => Complex::real(z.norm()),
+            _ if z.is_real() => Complex::real(self.call(z.re)),
+            _ => Complex::NAN,
+        }
+    }
+}
+
+def_str_lookup! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Call2 {
+        Min("min") => f64::min,
+        Max("max") => f64::max,
+        Atan2("atan2") => f64::atan2,
+        Mod("mod") => f64::rem_euclid,
+    }
+
+    static CALL_2_FN_MAP: Lazy<FxHashMap<&str, Call2>>;
+
+    const CALL_2_FNS: [fn(f64, f64) -> f64; 4];
+}
+
+impl Call2 {
+    pub(crate) fn call(self, t1: f64, t2: f64) -> f64 {
+        CALL_2_FNS[self as usize](t1, t2)
+    }
+}
+
+static CONSTS: Lazy<FxHashMap<&str, f64>> = Lazy::new(|| {
+    [("tau", std::f64::consts::TAU), ("pi", std::f64::consts::PI), ("e", std::f64::consts::E)]
+        .into_iter()
+        .collect()
+});
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OpType {
+    Normal,
+    Inverse,
+    Third,
+    Fourth,
+}
+
+/// Comparison operators. Comparisons evaluate to 1 when they hold and 0 when they don't.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CmpOp {
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+}
+
+impl CmpOp {
+    fn from_sign(sign: &str) -> Self {
+        match sign {
+            "<" => Self::Lt,
+            ">" => Self::Gt,
+            "<=" => Self::Le,
+            ">=" => Self::Ge,
+            "==" => Self::Eq,
+            _ => unreachable!(),
+        }
+    }
+
+    pub(crate) fn holds(self, a: f64, b: f64) -> bool {
+        match self {
+            Self::Lt => a < b,
+            Self::Gt => a > b,
+            Self::Le => a <= b,
+            Self::Ge => a >= b,
+            Self::Eq => a == b,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Function {
+    /// If the option is None, the variable is `t`
+    Var(Option<usize>),
+    Const(f64),
+    Add(Vec<(Function, OpType)>),
+    Mul(Vec<(Function, OpType)>),
+    Exp(Vec<Function>),
+    Neg(Box<Function>),
+    Call1(Call1, Box<Function>),
+    Call2(Call2, Box<[Function; 2]>),
+    Cmp(CmpOp, Box<[Function; 2]>),
+    /// Condition, then the value when it's nonzero, then the value when it's zero
+    If(Box<[Function; 3]>),
+    /// A random value between the bounds, the same for the whole shot.
+    /// The number tells apart the random values of a shot.
+    Rand(u32, Box<[Function; 2]>),
+    /// The imaginary unit `i`, which only complex functions have
+    ImagUnit,
+    /// Integral of the function from 0 to `t`, where `t` in the function is
+    /// the variable of integration
+    Integral(Box<Function>),
+    /// Sum or product of the last function over whole numbers from the first bound
+    /// to the second. The number is the slot of the index variable.
+    Series(SeriesOp, usize, Box<[Function; 3]>),
+    /// The index variable of an enclosing sum or product. Its slot is how many sums
+    /// and products it's inside.
+    Index(usize),
+}
+
+/// Whether a series adds or multiplies its terms
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SeriesOp {
+    Sum,
+    Prod,
+}
+
+/// Most terms a sum or product adds up, so a big bound can't freeze the game.
+/// Shots that nest them are limited by how long they take to evaluate instead.
+pub const MAX_SERIES_TERMS: usize = 1000;
+
+/// Values an index variable takes from `first` to `last`, rounded to whole numbers
+pub(crate) fn series_indexes(first: f64, last: f64) -> impl Iterator<Item = f64> {
+    let (first, last) = (first.round(), last.round());
+    let count = (last - first + 1.0).clamp(0.0, MAX_SERIES_TERMS as f64) as usize;
+    (0..count).map(move |i| first + i as f64)
+}
+
+/// Steps the integral of `int` takes from 0 to `t`. Simpson's rule needs an even number.
+/// Each step evaluates the whole function, so shots nesting integrals are limited by how long
+/// they take to evaluate.
+pub const INTEGRAL_STEPS: usize = 64;
+
+/// Parameters to evaluate a function at, and how much each value counts,
+/// to integrate it from 0 to `t` with Simpson's rule
+pub(crate) fn integral_points(t: f64) -> impl Iterator<Item = (f64, f64)> {
+    let step = t / INTEGRAL_STEPS as f64;
+    (0..=INTEGRAL_STEPS).map(move |i| {
+        let weight = match i {
+            0 | INTEGRAL_STEPS => 1.0,
+            i if i % 2 == 1 => 4.0,
+            _ => 2.0,
+        };
+        (i as f64 * step, weight * step / 3.0)
+    })
+}
+
+/// Uniformly random number in [0, 1) for the `index`th random value of a shot with some seed
+pub(crate) fn random_value(seed: u64, index: u32) -> f64 {
+    // SplitMix64, so each value only depends on the seed and the index
+    let mut x = seed ^ (index as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^= x >> 31;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl Function {
+    fn from_multi_op_sequence(
+        pair: Pair<Rule>,
+        variant: impl Fn(Vec<(Function, OpType)>) -> Self,
+        signs: &[(&str, OpType)],
+        var_map: &VarIndexMap,
+        state: &mut ParseState,
+    ) -> Result<Self, Error<Rule>> {
+        let mut inner = pair.into_inner();
+        let first = inner.next().unwrap();
+        if inner.peek().is_some() {
+            let pair_vec = inner.collect::<Vec<_>>();
+            Ok(variant(
+                iter::once(Self::from_pair(first, var_map, state).map(|f| (f, OpType::Normal)))
+                    .chain(pair_vec.chunks(2).map(|pairs| {
+                        let op_sign = &pairs[0];
+                        let expr = pairs[1].clone();
+                        Self::from_pair(expr, var_map, state).map(|f| {
+                            (
+                                f,
+                                signs
+                                    .iter()
+                                    .find_map(|(sign, op)| (*sign == op_sign.as_str()).then(|| *op))
+                                    .unwrap(),
+                            )
+                        })
+                    }))
+                    .collect::<Result<_, _>>()?,
+            ))
+        } else {
+            Self::from_pair(first, var_map, state)
+        }
+    }
+
+    fn from_op_sequence(
+        pair: Pair<Rule>,
+        variant: impl Fn(Vec<Function>) -> Self,
+        var_map: &VarIndexMap,
+        state: &mut ParseState,
+    ) -> Result<Self, Error<Rule>> {
+        let mut inner = pair.into_inner();
+        let first = inner.next().unwrap();
+        if inner.peek().is_some() {
+            Ok(variant(
+                iter::once(first)
+                    .chain(inner)
+                    .map(|p| Self::from_pair(p, var_map, state))
+                    .collect::<Result<_, _>>()?,
+            ))
+        } else {
+            Self::from_pair(first, var_map, state)
+        }
+    }
+
+    /// Parses the arguments of `sum` or `prod`: the index variable, its bounds, and the term
+    fn from_series(
+        op: SeriesOp,
+        mut pairs: Pairs<Rule>,
+        var_map: &VarIndexMap,
+        state: &mut ParseState,
+    ) -> Result<Self, Error<Rule>> {
+        let index = pairs.next().unwrap();
+        let var = index_var(index.clone()).ok_or_else(|| {
+            Error::new_from_span(
+                ErrorVariant::CustomError {
+                    message: "expected an index variable, like k".to_owned(),
+                },
+                index.as_span(),
+            )
+        })?;
+        let var = new_var_name(var, var_map)?;
+        let first = Self::from_pair(pairs.next().unwrap(), var_map, state)?;
+        let last = Self::from_pair(pairs.next().unwrap(), var_map, state)?;
+
+        // The index is only defined in the term
+        let slot = state.series_depth;
+        let mut var_map = var_map.clone();
+        var_map.insert(var, Binding::Index(slot));
+        state.series_depth += 1;
+        let term = Self::from_pair(pairs.next().unwrap(), &var_map, state);
+        state.series_depth -= 1;
+        Ok(Self::Series(op, slot, Box::new([first, last, term?])))
+    }
+
+    fn from_pair(
+        pair: Pair<Rule>,
+        var_map: &VarIndexMap,
+        state: &mut ParseState,
+    ) -> Result<Self, Error<Rule>> {
+        match pair.as_rule() {
+            Rule::expr => Self::from_pair(pair.into_inner().next().unwrap(), var_map, state),
+            Rule::let_expr => {
+                // `let` and `in` come through as pairs too
+                let mut pairs = pair.into_inner();
+                let var = new_var_name(pairs.nth(1).unwrap(), var_map)?;
+                let value = Self::from_pair(pairs.next().unwrap(), var_map, state)?;
+                // The name is only defined in the body
+                let mut var_map = var_map.clone();
+                var_map.insert(var, Binding::Var(Some(state.bind(value))));
+                Self::from_pair(pairs.nth(1).unwrap(), &var_map, state)
+            }
+            Rule::cmp => {
+                let mut pairs = pair.into_inner();
+                let lhs = Self::from_pair(pairs.next().unwrap(), var_map, state)?;
+                if let Some(sign) = pairs.next() {
+                    let rhs = Self::from_pair(pairs.next().unwrap(), var_map, state)?;
+                    Ok(Self::Cmp(CmpOp::from_sign(sign.as_str()), Box::new([lhs, rhs])))
+                } else {
+                    Ok(lhs)
+                }
+            }
+            Rule::add => Self::from_multi_op_sequence(
+                pair,
+                Self::Add,
+                &[("+", OpType::Normal), ("-", OpType::Inverse)],
+                var_map,
+                state,
+            ),
+            Rule::mul => Self::from_multi_op_sequence(
+                pair,
+                Self::Mul,
+                &[
+                    ("*", OpType::Normal),
+                    ("/", OpType::Inverse),
+                    ("//", OpType::Third),
+                    ("%", OpType::Fourth),
+                ],
+                var_map,
+                state,
+            ),
+            Rule::neg => {
+                let negate = pair.as_str().starts_with('-');
+                let expr = Self::from_pair(pair.into_inner().next().unwrap(), var_map, state)?;
+                if negate {
+                    Ok(Self::Neg(Box::new(expr)))
+                } else {
+                    Ok(expr)
+                }
+            }
+            Rule::exp => Self::from_op_sequence(pair, Self::Exp, var_map, state),
+            Rule::call_0 => {
+                let func = pair.into_inner().next().unwrap();
+                if func.as_str() == "rand" {
+                    Ok(Self::Rand(
+                        state.rand_index(),
+                        Box::new([Self::Const(0.0), Self::Const(1.0)]),
+                    ))
+                } else {
+                    Err(Error::new_from_span(
+                        ErrorVariant::CustomError {
+                            message: format!(
+                                "unknown function with 0 arguments: {}",
+                                func.as_str()
+                            ),
+                        },
+                        func.as_span(),
+                    ))
+                }
+            }
+            Rule::call_1 => {
+                let mut pairs = pair.into_inner();
+                let func = pairs.next().unwrap();
+                let expr = pairs.next().unwrap();
+                if func.as_str() == "d" {
+                    let expr = Self::from_pair(expr, var_map, state)?;
+                    Ok(expr.derivative(&mut |index| state.var_derivative(index)))
+                } else if func.as_str() == "int" {
+                    Ok(Self::Integral(Box::new(Self::from_pair(expr, var_map, state)?)))
+                } else if let Some(call) = CALL_1_FN_MAP.get(func.as_str()) {
+                    Ok(Self::Call1(*call, Box::new(Self::from_pair(expr, var_map, state)?)))
+                } else {
+                    Err(Error::new_from_span(
+                        ErrorVariant::CustomError {
+                            message: format!("unknown unary function: {}", func.as_str()),
+                        },
+                        func.as_span(),
+                    ))
+                }
+            }
+            Rule::call_2 => {
+                let mut pairs = pair.into_inner();
+                let func = pairs.next().unwrap();
+                let expr1 = pairs.next().unwrap();
+                let expr2 = pairs.next().unwrap();
+                if let Some(call) = CALL_2_FN_MAP.get(func.as_str()) {
+                    Ok(Self::Call2(
+                        *call,
+                        Box::new([
+                            Self::from_pair(expr1, var_map, state)?,
+                            Self::from_pair(expr2, var_map, state)?,
+                        ]),
+                    ))
+                } else {
+                    Err(Error::new_from_span(
+                        ErrorVariant::CustomError {
+                            message: format!("unknown binary function: {}", func.as_str()),
+                        },
+                        func.as_span(),
+                    ))
+                }
+            }
+            Rule::call_n => {
+                let mut pairs = pair.into_inner();
+                let func = pairs.next().unwrap();
+                let series_op = match func.as_str() {
+                    "sum" => Some(SeriesOp::Sum),
+                    "prod" => Some(SeriesOp::Prod),
+                    _ => None,
+                };
+                if let Some(op) = series_op.filter(|_| pairs.clone().count() == 4) {
+                    return Self::from_series(op, pairs, var_map, state);
+                }
+                let args = pairs
+                    .map(|p| Self::from_pair(p, var_map, state))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let call_2 = |call, a, b| Self::Call2(call, Box::new([a, b]));
+                // min and max take any number of arguments, and clamp is made of them
+                if func.as_str() == "clamp" && args.len() == 3 {
+                    let [x, lo, hi] = <[Self; 3]>::try_from(args).unwrap();
+                    Ok(call_2(Call2::Max, call_2(Call2::Min, x, hi), lo))
+                } else if func.as_str() == "rand" && args.len() == 2 {
+                    Ok(Self::Rand(
+                        state.rand_index(),
+                        Box::new(<[Self; 2]>::try_from(args).unwrap()),
+                    ))
+                } else if func.as_str() == "if" && args.len() == 3 {
+                    Ok(Self::If(Box::new(<[Self; 3]>::try_from(args).unwrap())))
+                } else if let Some(call) = CALL_2_FN_MAP
+                    .get(func.as_str())
+                    .filter(|call| args.len() == 2 || matches!(call, Call2::Min | Call2::Max))
+                {
+                    let mut args = args.into_iter();
+                    let first = args.next().unwrap();
+                    Ok(args.fold(first, |acc, arg| call_2(*call, acc, arg)))
+                } else {
+                    let message = if args.len() == 2 {
+                        format!("unknown binary function: {}", func.as_str())
+                    } else {
+                        format!("unknown function with {} arguments: {}", args.len(), func.as_str())
+                    };
+                    Err(Error::new_from_span(ErrorVariant::CustomError { message }, func.as_span()))
+                }
+            }
+            Rule::primary => Self::from_pair(pair.into_inner().next().unwrap(), var_map, state),
+            Rule::primitive => Self::from_pair(pair.into_inner().next().unwrap(), var_map, state),
+            Rule::var => {
+                if let Some(constant) = CONSTS.get(pair.as_str()) {
+                    Ok(Self::Const(*constant))
+                } else if let Some(binding) = var_map.get(pair.as_str()) {
+                    Ok(match binding {
+                        Binding::Var(index) => Self::Var(*index),
+                        Binding::Value(value) => Self::Const(*value),
+                        Binding::ImagUnit => Self::ImagUnit,
+                        Binding::Index(slot) => Self::Index(*slot),
+                    })
+                } else {
+                    Err(Error::new_from_span(
+                        ErrorVariant::CustomError {
+                            message: format!("unknown variable: {}", pair.as_str()),
+                        },
+                        pair.as_span(),
+                    ))
+                }
+            }
+            Rule::constant => Ok(Self::Const(str::parse(pair.as_str()).unwrap())),
+
+            _ => unreachable!(),
+        }
+    }
+
+    /// `indexes` holds the values of the index variables of the sums and products around
+    /// the function
+    pub(crate) fn eval(&self, t: f64, assigns: &[Function], seed: u64, indexes: &[f64]) -> f64 {
+        match self {
+            Self::Var(index) => {
+                index.map(|i| assigns[i].eval(t, assigns, seed, indexes)).unwrap_or(t)
+            }
+            Self::Const(c) => *c,
+            Self::Add(fs) => fs.iter().fold(0.0, |acc, (f, op)| match *op {
+                OpType::Normal => acc + f.eval(t, assigns, seed, indexes),
+                OpType::Inverse => acc - f.eval(t, assigns, seed, indexes),
+                _ => unreachable!(),
+            }),
+            Self::Mul(fs) => fs.iter().fold(1.0, |acc, (f, op)| match *op {
+                OpType::Normal => acc * f.eval(t, assigns, seed, indexes),
+                OpType::Inverse => acc / f.eval(t, assigns, seed, indexes),
+                OpType::Third => acc.div_euclid(f.eval(t, assigns, seed, indexes)),
+                OpType::Fourth => acc.rem_euclid(f.eval(t, assigns, seed, indexes)),
+            }),
+            Self::Exp(fs) => {
+                fs.iter().rev().fold(1.0, |acc, f| f.eval(t, assigns, seed, indexes).powf(acc))
+            }
+            Self::Neg(f) => -f.eval(t, assigns, seed, indexes),
+            Self::Call1(call, f) => call.call(f.eval(t, assigns, seed, indexes)),
+            Self::Call2(call, fs) => call
+                .call(fs[0].eval(t, assigns, seed, indexes), fs[1].eval(t, assigns, seed, indexes)),
+            Self::Cmp(op, fs) => {
+                if op.holds(
+                    fs[0].eval(t, assigns, seed, indexes),
+                    fs[1].eval(t, assigns, seed, indexes),
+                ) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            // Only the branch that gets taken is evaluated
+            Self::If(fs) => match fs[0].eval(t, assigns, seed, indexes) {
+                c if c.is_nan() => f64::NAN,
+                c if c != 0.0 => fs[1].eval(t, assigns, seed, indexes),
+                _ => fs[2].eval(t, assigns, seed, indexes),
+            },
+            Self::Rand(index, bounds) => {
+                let (lo, hi) = (
+                    bounds[0].eval(t, assigns, seed, indexes),
+                    bounds[1].eval(t, assigns, seed, indexes),
+                );
+                lo + (hi - lo) * random_value(seed, *index)
+            }
+            Self::ImagUnit => f64::NAN,
+            Self::Integral(f) => integral_points(t)
+                .map(|(s, weight)| weight * f.eval(s, assigns, seed, indexes))
+                .sum(),
+            Self::Series(op, slot, fs) => {
+                let (first, last) =
+                    (fs[0].eval(t, assigns, seed, indexes), fs[1].eval(t, assigns, seed, indexes));
+                if first.is_nan() || last.is_nan() {
+                    return f64::NAN;
+                }
+                let mut indexes = indexes[..*slot].to_vec();
+                indexes.push(0.0);
+                let mut term = |k| {
+                    indexes[*slot] = k;
+                    fs[2].eval(t, assigns, seed, &indexes)
+                };
+                match op {
+                    SeriesOp::Sum => series_indexes(first, last).map(&mut term).sum(),
+                    SeriesOp::Prod => series_indexes(first, last).map(&mut term).product(),
+                }
+            }
+            Self::Index(slot) => indexes[*slot],
+        }
+    }
+
+    /// Like `eval`, but over complex numbers. Operations without a complex version
+    /// only work on real numbers.
+    fn eval_complex(&self, t: f64, assigns: &[Function], seed: u64, indexes: &[f64]) -> Complex {
+        let real_only = |a: Complex, b: Complex, f: fn(f64, f64) -> f64| {
+            if a.is_real() && b.is_real() {
+                Complex::real(f(a.re, b.re))
+            } else {
+                Complex::NAN
+            }
+        };
+
+        match self {
+            Self::Var(index) => index
+                .map(|i| assigns[i].eval_complex(t, assigns, seed, indexes))
+                .unwrap_or(Complex::real(t)),
+            Self::Const(c) => Complex::real(*c),
+            Self::Add(fs) => fs.iter().fold(Complex::real(0.0), |acc, (f, op)| match *op {
+                OpType::Normal => acc + f.eval_complex(t, assigns, seed, indexes),
+                OpType::Inverse => acc - f.eval_complex(t, assigns, seed, indexes),
+                _ => unreachable!(),
+            }),
+            Self::Mul(fs) => fs.iter().fold(Complex::real(1.0), |acc, (f, op)| {
+                let z = f.eval_complex(t, assigns, seed, indexes);
+                match *op {
+                    OpType::Normal => acc * z,
+                    OpType::Inverse => acc / z,
+                    OpType::Third => real_only(acc, z, f64::div_euclid),
+                    OpType::Fourth => real_only(acc, z, f64::rem_euclid),
+                }
+            }),
+            Self::Exp(fs) => fs.iter().rev().fold(Complex::real(1.0), |acc, f| {
+                f.eval_complex(t, assigns, seed, indexes).pow(acc)
+            }),
+            Self::Neg(f) => -f.eval_complex(t, assigns, seed, indexes),
+            Self::Call1(call, f) => call.call_complex(f.eval_complex(t, assigns, seed, indexes)),
+            Self::Call2(call, fs) => {
+                let (a, b) = (
+                    fs[0].eval_complex(t, assigns, seed, indexes),
+                    fs[1].eval_complex(t, assigns, seed, indexes),
+                );
+                real_only(a, b, CALL_2_FNS[*call as usize])
+            }
+            // Complex numbers can only be compared for equality
+            Self::Cmp(op, fs) => {
+                let (a, b) = (
+                    fs[0].eval_complex(t, assigns, seed, indexes),
+                    fs[1].eval_complex(t, assigns, seed, indexes),
+                );
+                if a.is_real() && b.is_real() {
+                    Complex::real(if op.holds(a.re, b.re) { 1.0 } else { 0.0 })
+                } else if *op == CmpOp::Eq {
+                    Complex::real(if a == b { 1.0 } else { 0.0 })
+                } else {
+                    Complex::NAN
+                }
+            }
+            // Only the branch that gets taken is evaluated
+            Self::If(fs) => match fs[0].eval_complex(t, assigns, seed, indexes) {
+                c if c.re.is_nan() || c.im.is_nan() => Complex::NAN,
+                c if c != Complex::real(0.0) => fs[1].eval_complex(t, assigns, seed, indexes),
+                _ => fs[2].eval_complex(t, assigns, seed, indexes),
+            },
+            Self::Rand(index, bounds) => {
+                let (lo, hi) = (
+                    bounds[0].eval_complex(t, assigns, seed, indexes),
+                    bounds[1].eval_complex(t, assigns, seed, indexes),
+                );
+                lo + (hi - lo) * Complex::real(random_value(seed, *index))
+            }
+            Self::ImagUnit => Complex::I,
+            Self::Integral(f) => integral_points(t).fold(Complex::real(0.0), |acc, (s, weight)| {
+                acc + Complex::real(weight) * f.eval_complex(s, assigns, seed, indexes)
+            }),
+            // The bounds have to be real
+            Self::Series(op, slot, fs) => {
+                let (first, last) = (
+                    fs[0].eval_complex(t, assigns, seed, indexes),
+                    fs[1].eval_complex(t, assigns, seed, indexes),
+                );
+                if !first.is_real() || !last.is_real() || first.re.is_nan() || last.re.is_nan() {
+                    return Complex::NAN;
+                }
+                let mut indexes = indexes[..*slot].to_vec();
+                indexes.push(0.0);
+                let terms = series_indexes(first.re, last.re).map(|k| {
+                    indexes[*slot] = k;
+                    fs[2].eval_complex(t, assigns, seed, &indexes)
+                });
+                match op {
+                    SeriesOp::Sum => terms.fold(Complex::real(0.0), |acc, z| acc + z),
+                    SeriesOp::Prod => terms.fold(Complex::real(1.0), |acc, z| acc * z),
+                }
+            }
+            Self::Index(slot) => Complex::real(indexes[*slot]),
+        }
+    }
+}
+
+/// Maps variable indexes to functions
+type AssignVec = Vec<Function>;
+
+/// What a variable name stands for
+#[derive(Clone, Copy, Debug)]
+enum Binding {
+    /// A variable, with the same meaning as in `Function::Var`
+    Var(Option<usize>),
+    /// A value from the environment, which gets baked in when parsing
+    Value(f64),
+    /// `i`, in complex functions
+    ImagUnit,
+    /// The index variable of a sum or product, with the same meaning as in `Function::Index`
+    Index(usize),
+}
+
+/// Maps variable names to what they stand for
+type VarIndexMap = FxHashMap<String, Binding>;
+
+/// Named values a shot can use like constants, captured when it gets parsed,
+/// such as where the other players are
+pub type Env = [(String, f64)];
+
+/// What parsing a shot keeps track of, other than which names are defined
+struct ParseState {
+    /// Values of the where clause's lines parsed so far
+    assigns: AssignVec,
+    /// Number of lines in the where clause. Values bound while parsing get indexes after them,
+    /// so the indexes of the where clause's values still match its lines.
+    num_where: usize,
+    /// Values bound with `let`, and derivatives of variables
+    bound: AssignVec,
+    /// Indexes of the bound values that are derivatives, which the player didn't write
+    derivative_assigns: Vec<usize>,
+    /// Derivatives of the variables that have been differentiated, so each is only taken once
+    derivatives: FxHashMap<usize, Function>,
+    next_rand: u32,
+    /// Number of sums and products around what's being parsed
+    series_depth: usize,
+}
+
+impl ParseState {
+    /// Parses the where clause. `complex` is whether `i` is defined.
+    fn from_assigns(
+        pairs: Pairs<Rule>,
+        env: &Env,
+        complex: bool,
+    ) -> Result<(Self, VarIndexMap), Error<Rule>> {
+        let mut var_map = iter::once(("t".to_owned(), Binding::Var(None)))
+            .chain(complex.then(|| ("i".to_owned(), Binding::ImagUnit)))
+            .chain(env.iter().map(|(name, value)| (name.clone(), Binding::Value(*value))))
+            .collect::<FxHashMap<_, _>>();
+
+        let pairs = pairs.filter(|pair| pair.as_rule() != Rule::EOI).collect::<Vec<_>>();
+        let mut state = Self {
+            assigns: Vec::with_capacity(pairs.len()),
+            num_where: pairs.len(),
+            bound: vec![],
+            derivative_assigns: vec![],
+            derivatives: FxHashMap::default(),
+            next_rand: 0,
+            series_depth: 0,
+        };
+
+        for (i, pair) in pairs.into_iter().enumerate() {
+            let mut pairs = pair.into_inner();
+            let var = new_var_name(pairs.next().unwrap(), &var_map)?;
+            var_map.insert(var, Binding::Var(Some(i)));
+            let expr = Function::from_pair(pairs.next().unwrap(), &var_map, &mut state)?;
+            state.assigns.push(expr);
+        }
+
+        Ok((state, var_map))
+    }
+
+    /// Adds a value, returning its index among the assigns
+    fn bind(&mut self, value: Function) -> usize {
+        self.bound.push(value);
+        self.num_where + self.bound.len() - 1
+    }
+
+    /// The value of the variable with some index
+    fn value(&self, index: usize) -> &Function {
+        if index < self.num_where {
+            &self.assigns[index]
+        } else {
+            &self.bound[index - self.num_where]
+        }
+    }
+
+    /// Derivative of the variable with some index. Unless it's just a number or variable,
+    /// it gets bound as a new variable so it's only written out once.
+    fn var_derivative(&mut self, index: usize) -> Function {
+        if let Some(derivative) = self.derivatives.get(&index) {
+            return derivative.clone();
+        }
+        let derivative = self.value(index).clone().derivative(&mut |i| self.var_derivative(i));
+        let derivative = match derivative {
+            Function::Const(_) | Function::Var(_) => derivative,
+            _ => {
+                let bound = self.bind(derivative);
+                self.derivative_assigns.push(bound);
+                Function::Var(Some(bound))
+            }
+        };
+        self.derivatives.insert(index, derivative.clone());
+        derivative
+    }
+
+    /// Numbers a new random value. Random values are numbered in the order they're parsed,
+    /// and copies of one made while differentiating keep its number.
+    fn rand_index(&mut self) -> u32 {
+        self.next_rand += 1;
+        self.next_rand - 1
+    }
+
+    /// The where clause's values followed by the bound ones
+    fn into_assigns(self) -> AssignVec {
+        let mut assigns = self.assigns;
+        assigns.extend(self.bound);
+        assigns
+    }
+}
+
+/// The variable an expression consists of, if it's nothing but a variable
+fn index_var(expr: Pair<Rule>) -> Option<Pair<Rule>> {
+    let text = expr.as_str();
+    let mut pair = expr;
+    loop {
+        if pair.as_rule() == Rule::var {
+            return (pair.as_str() == text).then(|| pair);
+        }
+        pair = pair.into_inner().next()?;
+    }
+}
+
+/// Checks that a variable being defined doesn't already mean something, and returns its name
+fn new_var_name(var: Pair<Rule>, var_map: &VarIndexMap) -> Result<String, Error<Rule>> {
+    let message = if var_map.contains_key(var.as_str()) {
+        format!("'{}' is already defined", var.as_str())
+    } else if CONSTS.contains_key(var.as_str()) {
+        format!("cannot assign to constant '{}'", var.as_str())
+    } else {
+        return Ok(var.as_str().to_owned());
+    };
+    Err(Error::new_from_span(ErrorVariant::CustomError { message }, var.as_span()))
+}
+
+#[derive(Clone, Debug)]
+pub struct Parametric {
+    pub x: Function,
+    pub y: Function,
+    pub assigns: AssignVec,
+    pub source_x: Option<String>,
+    pub source_y: Option<String>,
+    pub source_assigns: Option<String>,
+    /// Seed of the random values in the shot. Kept with the shot so replays fly the same way.
+    pub seed: u64,
+    /// Whether `x` is a complex function whose real and imaginary parts are x and y.
+    /// `y` isn't used then.
+    pub complex: bool,
+    /// How many of the assigns come from lines of the where clause.
+    /// The rest are values bound with `let`, and derivatives.
+    pub where_assigns: usize,
+    /// Indexes of the assigns the parser bound for derivatives, which the player didn't write
+    pub derivative_assigns: Vec<usize>,
+    /// Bytecode for the functions, if they've been compiled.
+    /// Changing the functions afterwards doesn't change it.
+    compiled: Option<CompiledParametric>,
+}
+
+impl Parametric {
+    pub fn new(
+        x: Function,
+        y: Function,
+        assigns: AssignVec,
+        source_x: String,
+        source_y: String,
+        source_assigns: String,
+    ) -> Self {
+        let where_assigns = assigns.len();
+        Self {
+            x,
+            y,
+            assigns,
+            source_x: Some(source_x),
+            source_y: Some(source_y),
+            source_assigns: Some(source_assigns),
+            seed: 0,
+            complex: false,
+            where_assigns,
+            derivative_assigns: vec![],
+            compiled: None,
+        }
+    }
+
+    /// Sets the seed of the random values in the shot
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Treats the x and y functions as a radius and an angle, and turns them into x and y
+    pub fn polar_to_cartesian(mut self) -> Self {
+        let (r, theta) = (self.x, self.y);
+        let polar = |call, r: &Function, theta: &Function| {
+            Function::Mul(vec![
+                (r.clone(), OpType::Normal),
+                (Function::Call1(call, Box::new(theta.clone())), OpType::Normal),
+            ])
+        };
+        self.x = polar(Call1::Cos, &r, &theta);
+        self.y = polar(Call1::Sin, &r, &theta);
+        self
+    }
+
+    /// Simplifies the functions of the shot, so they're cheaper to evaluate
+    pub fn simplify(mut self) -> Self {
+        self.x = self.x.simplify();
+        self.y = self.y.simplify();
+        self.assigns = self.assigns.into_iter().map(Function::simplify).collect();
+        self
+    }
+
+    /// Compiles the functions of the shot to bytecode, so they're faster to evaluate.
+    /// Complex shots stay as they are.
+    pub fn compile(mut self) -> Self {
+        if !self.complex {
+            self.compiled = Some(CompiledParametric {
+                x: CompiledFunction::new(&self.x),
+                y: CompiledFunction::new(&self.y),
+                assigns: self.assigns.iter().map(CompiledFunction::new).collect(),
+            });
+        }
+        self
+    }
+
+    pub fn eval(&self, t: f64) -> Vec2 {
+        if let Some(compiled) = &self.compiled {
+            return Vec2::new(
+                compiled.x.eval(t, &compiled.assigns, self.seed) as f32,
+                compiled.y.eval(t, &compiled.assigns, self.seed) as f32,
+            );
+        }
+        if self.complex {
+            let z = self.x.eval_complex(t, &self.assigns, self.seed, &[]);
+            return Vec2::new(z.re as f32, z.im as f32);
+        }
+        Vec2::new(
+            self.x.eval(t, &self.assigns, self.seed, &[]) as f32,
+            self.y.eval(t, &self.assigns, self.seed, &[]) as f32,
+        )
+    }
+
+    /// Like `eval`, but `None` where the curve is undefined or infinite
+    pub fn try_eval(&self, t: f64) -> Option<Vec2> {
+        let pos = self.eval(t);
+        pos.is_finite().then(|| pos)
+    }
+}
+
+/// An error in one of the textboxes, along with which one
+#[derive(Debug)]
+pub struct ParseError {
+    error: Error<Rule>,
+    label: String,
+    include_line: bool,
+    textbox: usize,
+}
+
+impl ParseError {
+    fn new(error: Error<Rule>, label: String, include_line: bool, textbox: usize) -> Self {
+        Self { error, label, include_line, textbox }
+    }
+
+    /// Which textbox the error is in: 0 for x(t) or f(t), 1 for y(t), and 2 for the where clause
+    pub fn textbox(&self) -> usize {
+        self.textbox
+    }
+
+    /// Byte range of the textbox's text that the error is about.
+    /// It's empty when the error is at a single spot, like a missing closing parenthesis.
+    pub fn span(&self) -> Range<usize> {
+        match self.error.location {
+            InputLocation::Pos(pos) => pos..pos,
+            InputLocation::Span((start, end)) => start..end,
+        }
+    }
+
+    /// What went wrong, without where it went wrong
+    pub fn message(&self) -> &str {
+        match &self.error.variant {
+            ErrorVariant::CustomError { message } => message.as_str(),
+            ErrorVariant::ParsingError { .. } => "syntax",
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (line, column) = match self.error.line_col {
+            LineColLocation::Pos((l, c)) | LineColLocation::Span((l, c), _) => (l, c),
+        };
+        let line_message =
+            if self.include_line { format!("line {} ", line) } else { String::new() };
+        write!(f, "Error in {} ({}col {}): {}", self.label, line_message, column, self.message())
+    }
+}
+
+/// Parses the functions entered into the x, y and 'where' textboxes
+pub fn parse_parametric(
+    fx_str: &str,
+    fy_str: &str,
+    where_str: &str,
+) -> Result<Parametric, ParseError> {
+    parse_parametric_in(fx_str, fy_str, where_str, &[])
+}
+
+/// Like `parse_parametric`, but the functions can also use the values in `env`
+pub fn parse_parametric_in(
+    fx_str: &str,
+    fy_str: &str,
+    where_str: &str,
+    env: &Env,
+) -> Result<Parametric, ParseError> {
+    let (mut state, var_map) = FunctionParser::parse(Rule::assigns, where_str)
+        .and_then(|mut pairs| {
+            ParseState::from_assigns(pairs.next().unwrap().into_inner(), env, false)
+        })
+        .map_err(|error| ParseError::new(error, "'where'".into(), true, 2))?;
+
+    let mut funcs = Vec::with_capacity(2);
+
+    for (textbox, (axis, func)) in [("x", fx_str), ("y", fy_str)].into_iter().enumerate() {
+        let f = FunctionParser::parse(Rule::func, func)
+            .and_then(|mut pairs| {
+                let expr = pairs.next().unwrap().into_inner().next().unwrap();
+                Function::from_pair(expr, &var_map, &mut state)
+            })
+            .map_err(|error| ParseError::new(error, format!("{}(t)", axis), false, textbox))?;
+        funcs.push(f);
+    }
+
+    let fy = funcs.pop().unwrap();
+    let fx = funcs.pop().unwrap();
+    let where_assigns = state.num_where;
+    let derivative_assigns = mem::take(&mut state.derivative_assigns);
+    let mut parametric = Parametric::new(
+        fx,
+        fy,
+        state.into_assigns(),
+        fx_str.to_owned(),
+        fy_str.to_owned(),
+        where_str.to_owned(),
+    );
+    parametric.where_assigns = where_assigns;
+    parametric.derivative_assigns = derivative_assigns;
+    Ok(parametric)
+}
+
+/// Parses a complex function f(t), entered into the x textbox, and its 'where' textbox.
+/// The functions can use `i` and the values in `env`.
+pub fn parse_complex_in(f_str: &str, where_str: &str, env: &Env) -> Result<Parametric, ParseError> {
+    let (mut state, var_map) = FunctionParser::parse(Rule::assigns, where_str)
+        .and_then(|mut pairs| {
+            ParseState::from_assigns(pairs.next().unwrap().into_inner(), env, true)
+        })
+        .map_err(|error| ParseError::new(error, "'where'".into(), true, 2))?;
+
+    let f = FunctionParser::parse(Rule::func, f_str)
+        .and_then(|mut pairs| {
+            let expr = pairs.next().unwrap().into_inner().next().unwrap();
+            Function::from_pair(expr, &var_map, &mut state)
+        })
+        .map_err(|error| ParseError::new(error, "f(t)".into(), false, 0))?;
+
+    let where_assigns = state.num_where;
+    let derivative_assigns = mem::take(&mut state.derivative_assigns);
+    let mut parametric = Parametric::new(
+        f,
+        Function::Const(0.0),
+        state.into_assigns(),
+        f_str.to_owned(),
+        String::new(),
+        where_str.to_owned(),
+    );
+    parametric.complex = true;
+    parametric.where_assigns = where_assigns;
+    parametric.derivative_assigns = derivative_assigns;
+    Ok(parametric)
+}
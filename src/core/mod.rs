@@ -0,0 +1,11 @@
+//! Game logic that doesn't depend on rendering or the ECS, so bots, servers, and analysis tools
+//! can use it as a library. The Bevy front end in the rest of the crate is built on top of it.
+
+pub mod compile;
+pub mod complex;
+pub mod derivative;
+pub mod ink;
+pub mod interval;
+pub mod lang;
+pub mod rules;
+pub mod simplify;
@@ -0,0 +1,204 @@
+use bevy::math::Vec2;
+
+/// What happens when the flight time of a projectile is up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnExpire {
+    Despawn,
+    /// Blows up where it is, destroying every other projectile in the blast
+    Explode,
+    /// Leaves a mine where it is
+    DropMine,
+    /// Leaves a ball where it is, which any rocket can collect
+    DropBall,
+    /// Flies back along its path, then despawns
+    Return,
+}
+
+/// Rules of a match that aren't tied to the field
+#[derive(Clone, Debug)]
+pub struct GameRules {
+    /// Maximum number of rockets a player can fire in one turn
+    pub max_rockets: u32,
+    /// Maximum ammo a player can hold, where each rocket costs 1 ammo.
+    /// `None` means unlimited ammo.
+    pub ammo: Option<u32>,
+    /// Ammo regained at the start of each round
+    pub ammo_per_round: u32,
+    /// Ink each player has for the whole match, where every shot costs as much ink as its
+    /// functions take to write. `None` means unlimited ink.
+    pub ink: Option<u32>,
+    /// Minimum time between two rockets sent by the same player, in seconds
+    pub fire_cooldown: f32,
+    /// Whether to cover the screen between turns so the next player can't see
+    /// what the previous one entered, for hotseat games with secret inputs
+    pub privacy_screen: bool,
+    /// Distance a rocket can travel before running out of fuel and ending its flight early.
+    /// `None` means unlimited fuel.
+    pub fuel: Option<f32>,
+    /// Whether every rocket also gets fired by all the other players, flipped around
+    /// so it heads the opposite way from their positions
+    pub mirror: bool,
+    /// Whether the players with the fewest balls get knocked out after each normal round but the last
+    pub elimination: bool,
+    /// Number of turns the debris left behind when two rockets blow each other up lasts.
+    /// `None` means no debris.
+    pub debris_turns: Option<u32>,
+    /// Number of bounces off obstacles and field edges a ricochet pickup gives the next rocket.
+    /// `None` means no ricochet pickups.
+    pub ricochet_bounces: Option<u32>,
+    /// Longest the Add Rocket or Fire button can be held to charge a rocket, in seconds.
+    /// Charged rockets blow up bigger, but the player has to wait longer before sending another one.
+    /// `None` means rockets can't be charged.
+    pub max_charge: Option<f32>,
+    /// Whether each rocket sent uses up energy, which comes back over time. Running out overheats
+    /// the player's launcher, and they can't send rockets until it cools down.
+    pub energy: bool,
+    /// Whether EMP pickups spawn. A player with an EMP can press their number key
+    /// while rockets fly to destroy everyone else's rockets.
+    pub emp: bool,
+    /// Whether time dilation pickups spawn. A player with a time dilation can press their
+    /// slow or fast key while rockets fly to change how fast their own rockets go for a moment.
+    pub time_dilation: bool,
+    /// Whether rockets earn style points for each ball they collect, based on how fancy their
+    /// functions are. Style points are shown at the end but don't decide the winner.
+    pub style_score: bool,
+    /// Whether the entered functions give a rocket's acceleration instead of its position.
+    /// Rockets start at rest and feel gravity and drag, like artillery shells.
+    pub physics: bool,
+    /// Whether shots with coefficients to tune get their path previewed before they're fired
+    pub previews: bool,
+    /// What rockets do when their flight time is up
+    pub on_expire: OnExpire,
+    /// How much rocket sprites get scaled. This only changes how rockets look.
+    pub rocket_scale: f32,
+    /// Radius of a rocket's hitbox, in field units, no matter how big its sprite is drawn
+    pub rocket_radius: f32,
+    /// Half the width and height of the arena. Field layouts are stretched to fit it,
+    /// and the camera fits its height to the window.
+    pub arena_half_size: Vec2,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            max_rockets: 3,
+            ammo: None,
+            ammo_per_round: 1,
+            ink: None,
+            fire_cooldown: 0.5,
+            privacy_screen: false,
+            fuel: None,
+            mirror: false,
+            elimination: false,
+            debris_turns: None,
+            ricochet_bounces: None,
+            max_charge: None,
+            energy: false,
+            emp: false,
+            time_dilation: false,
+            style_score: false,
+            physics: false,
+            previews: true,
+            on_expire: OnExpire::Despawn,
+            rocket_scale: 0.3,
+            rocket_radius: 0.15,
+            arena_half_size: Vec2::splat(4.0),
+        }
+    }
+}
+
+/// Named rules that can be picked before a match, read from a data file such as:
+///
+/// ```text
+/// name = Precision
+/// description = Tiny hitboxes and no previews
+/// rocket_radius = 0.05
+/// previews = false
+/// ```
+///
+/// Each other key is a field of `GameRules`, and fields that aren't given keep their defaults.
+/// Optional fields can be set to `none`, and `arena_half_size` takes a width and a height.
+#[derive(Clone, Debug)]
+pub struct RulePreset {
+    pub name: String,
+    pub description: String,
+    pub rules: GameRules,
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("`{}` isn't a valid value", value))
+}
+
+fn parse_optional<T: std::str::FromStr>(value: &str) -> Result<Option<T>, String> {
+    if value == "none" {
+        Ok(None)
+    } else {
+        parse_value(value).map(Some)
+    }
+}
+
+fn parse_on_expire(value: &str) -> Result<OnExpire, String> {
+    Ok(match value {
+        "despawn" => OnExpire::Despawn,
+        "explode" => OnExpire::Explode,
+        "drop_mine" => OnExpire::DropMine,
+        "drop_ball" => OnExpire::DropBall,
+        "return" => OnExpire::Return,
+        _ => return Err(format!("`{}` isn't something rockets can do when they expire", value)),
+    })
+}
+
+/// Parses a rule preset. Blank lines and lines starting with `#` are skipped.
+pub fn parse_preset(text: &str) -> Result<RulePreset, String> {
+    let mut preset =
+        RulePreset { name: String::new(), description: String::new(), rules: GameRules::default() };
+    for (i, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) =
+            line.split_once('=').ok_or_else(|| format!("line {}: expected `=`", i))?;
+        let (key, value) = (key.trim(), value.trim());
+        let rules = &mut preset.rules;
+        let result = match key {
+            "name" => {
+                preset.name = value.to_owned();
+                Ok(())
+            }
+            "description" => {
+                preset.description = value.to_owned();
+                Ok(())
+            }
+            "max_rockets" => parse_value(value).map(|v| rules.max_rockets = v),
+            "ammo" => parse_optional(value).map(|v| rules.ammo = v),
+            "ammo_per_round" => parse_value(value).map(|v| rules.ammo_per_round = v),
+            "ink" => parse_optional(value).map(|v| rules.ink = v),
+            "fire_cooldown" => parse_value(value).map(|v| rules.fire_cooldown = v),
+            "privacy_screen" => parse_value(value).map(|v| rules.privacy_screen = v),
+            "fuel" => parse_optional(value).map(|v| rules.fuel = v),
+            "mirror" => parse_value(value).map(|v| rules.mirror = v),
+            "elimination" => parse_value(value).map(|v| rules.elimination = v),
+            "debris_turns" => parse_optional(value).map(|v| rules.debris_turns = v),
+            "ricochet_bounces" => parse_optional(value).map(|v| rules.ricochet_bounces = v),
+            "max_charge" => parse_optional(value).map(|v| rules.max_charge = v),
+            "energy" => parse_value(value).map(|v| rules.energy = v),
+            "emp" => parse_value(value).map(|v| rules.emp = v),
+            "time_dilation" => parse_value(value).map(|v| rules.time_dilation = v),
+            "style_score" => parse_value(value).map(|v| rules.style_score = v),
+            "physics" => parse_value(value).map(|v| rules.physics = v),
+            "previews" => parse_value(value).map(|v| rules.previews = v),
+            "on_expire" => parse_on_expire(value).map(|v| rules.on_expire = v),
+            "rocket_scale" => parse_value(value).map(|v| rules.rocket_scale = v),
+            "rocket_radius" => parse_value(value).map(|v| rules.rocket_radius = v),
+            "arena_half_size" => match value.split_whitespace().collect::<Vec<_>>()[..] {
+                [x, y] => parse_value(x)
+                    .and_then(|x| Ok(Vec2::new(x, parse_value(y)?)))
+                    .map(|v| rules.arena_half_size = v),
+                _ => Err("expected a width and a height".to_owned()),
+            },
+            _ => Err(format!("unknown key `{}`", key)),
+        };
+        result.map_err(|err| format!("line {}: {}", i, err))?;
+    }
+    Ok(preset)
+}
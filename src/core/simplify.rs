@@ -0,0 +1,188 @@
+//! Simplification of functions, so shots are cheaper to evaluate every frame
+
+use super::lang::{Function, OpType};
+
+fn is_const(f: &Function) -> bool {
+    matches!(f, Function::Const(_))
+}
+
+/// Whether a product only multiplies and divides, so its factors can be moved around
+fn reorderable(factors: &[(Function, OpType)]) -> bool {
+    factors.iter().all(|(_, op)| matches!(op, OpType::Normal | OpType::Inverse))
+}
+
+fn flip(op: OpType) -> OpType {
+    match op {
+        OpType::Normal => OpType::Inverse,
+        OpType::Inverse => OpType::Normal,
+        _ => unreachable!(),
+    }
+}
+
+/// Adds up terms that have already been simplified, adding together the ones that are constants.
+/// The constant goes last, unless the first other term is subtracted.
+fn fold_sum(terms: Vec<(Function, OpType)>) -> Function {
+    let mut constant = 0.0;
+    let mut terms = terms
+        .into_iter()
+        .filter(|(f, op)| match f {
+            Function::Const(c) => {
+                constant += if *op == OpType::Normal { *c } else { -*c };
+                false
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+
+    match terms.first() {
+        None => return Function::Const(constant),
+        Some((_, OpType::Inverse)) if constant != 0.0 => {
+            terms.insert(0, (Function::Const(constant), OpType::Normal))
+        }
+        Some((_, OpType::Inverse)) => {
+            let (first, _) = terms.remove(0);
+            terms.insert(0, (Function::Neg(Box::new(first)), OpType::Normal));
+        }
+        Some(_) if constant != 0.0 => terms.push((Function::Const(constant), OpType::Normal)),
+        Some(_) => {}
+    }
+    if terms.len() == 1 {
+        terms.pop().unwrap().0
+    } else {
+        Function::Add(terms)
+    }
+}
+
+/// Multiplies and divides factors that have already been simplified, multiplying together
+/// the ones that are constants in front. Unlike in derivatives, a factor of 0 stays a product,
+/// since the other factors could still be undefined.
+fn fold_product(factors: Vec<(Function, OpType)>) -> Function {
+    let mut coefficient = 1.0;
+    let mut factors = factors
+        .into_iter()
+        .filter(|(f, op)| match f {
+            Function::Const(c) => {
+                if *op == OpType::Normal {
+                    coefficient *= c;
+                } else {
+                    coefficient /= c;
+                }
+                false
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+
+    if factors.is_empty() {
+        return Function::Const(coefficient);
+    }
+    if coefficient != 1.0 || factors[0].1 == OpType::Inverse {
+        factors.insert(0, (Function::Const(coefficient), OpType::Normal));
+    }
+    if factors.len() == 1 {
+        factors.pop().unwrap().0
+    } else {
+        Function::Mul(factors)
+    }
+}
+
+impl Function {
+    /// Folds constants and flattens nested sums and products, without changing the value
+    /// other than by rounding. Variables stay variables, and random values stay random.
+    pub fn simplify(self) -> Self {
+        use OpType::{Inverse, Normal};
+
+        let simplified = match self {
+            Self::Add(terms) => {
+                // a - (b - c) is a - b + c, and a + -b is a - b
+                let mut flat = vec![];
+                for (term, op) in terms {
+                    match (term.simplify(), op) {
+                        (Self::Add(inner), op) => {
+                            flat.extend(inner.into_iter().map(|(f, inner_op)| {
+                                (f, if op == Normal { inner_op } else { flip(inner_op) })
+                            }))
+                        }
+                        (Self::Neg(f), op) => flat.push((*f, flip(op))),
+                        (f, op) => flat.push((f, op)),
+                    }
+                }
+                return fold_sum(flat);
+            }
+            Self::Mul(factors) => {
+                let mut flat = vec![];
+                for (factor, op) in factors {
+                    match (factor.simplify(), op) {
+                        (Self::Mul(inner), Normal | Inverse) if reorderable(&inner) => {
+                            flat.extend(inner.into_iter().map(|(f, inner_op)| {
+                                (f, if op == Normal { inner_op } else { flip(inner_op) })
+                            }))
+                        }
+                        (f, op) => flat.push((f, op)),
+                    }
+                }
+                // Factors can only be moved around up to the first floor division or modulo
+                let split = flat
+                    .iter()
+                    .position(|(_, op)| matches!(op, OpType::Third | OpType::Fourth))
+                    .unwrap_or(flat.len());
+                let rest = flat.split_off(split);
+                let head = fold_product(flat);
+                if rest.is_empty() {
+                    return head;
+                }
+                let mut factors = match head {
+                    Self::Mul(inner) => inner,
+                    head => vec![(head, Normal)],
+                };
+                factors.extend(rest);
+                Self::Mul(factors)
+            }
+            Self::Exp(fs) => {
+                let mut fs = fs.into_iter().map(Self::simplify).collect::<Vec<_>>();
+                // Towers go right to left, so constants at the top can be worked out
+                while let [.., Self::Const(base), Self::Const(exponent)] = fs[..] {
+                    fs.truncate(fs.len() - 2);
+                    fs.push(Self::Const(base.powf(exponent)));
+                }
+                if fs.len() == 1 {
+                    return fs.pop().unwrap();
+                }
+                Self::Exp(fs)
+            }
+            Self::Neg(f) => match f.simplify() {
+                Self::Const(c) => return Self::Const(-c),
+                Self::Neg(f) => return *f,
+                f => Self::Neg(Box::new(f)),
+            },
+            Self::Call1(call, f) => Self::Call1(call, Box::new(f.simplify())),
+            Self::Call2(call, fs) => Self::Call2(call, Box::new(fs.map(Self::simplify))),
+            Self::Cmp(op, fs) => Self::Cmp(op, Box::new(fs.map(Self::simplify))),
+            Self::If(fs) => {
+                let [condition, then, otherwise] = fs.map(Self::simplify);
+                match condition {
+                    Self::Const(c) if c.is_nan() => return Self::Const(f64::NAN),
+                    Self::Const(c) if c != 0.0 => return then,
+                    Self::Const(_) => return otherwise,
+                    condition => Self::If(Box::new([condition, then, otherwise])),
+                }
+            }
+            Self::Rand(index, bounds) => Self::Rand(index, Box::new(bounds.map(Self::simplify))),
+            Self::Integral(f) => Self::Integral(Box::new(f.simplify())),
+            Self::Series(op, slot, fs) => Self::Series(op, slot, Box::new(fs.map(Self::simplify))),
+            f @ (Self::Var(_) | Self::Const(_) | Self::ImagUnit | Self::Index(_)) => f,
+        };
+
+        // Calls and comparisons of constants are constants
+        let constant_args = match &simplified {
+            Self::Call1(_, f) => is_const(f),
+            Self::Call2(_, fs) | Self::Cmp(_, fs) => fs.iter().all(is_const),
+            _ => false,
+        };
+        if constant_args {
+            Self::Const(simplified.eval(0.0, &[], 0, &[]))
+        } else {
+            simplified
+        }
+    }
+}
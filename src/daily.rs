@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+
+use crate::{
+    asset, projectile::Projectile, storage, Field, Game, Player, RelativeTextSize, RenderLayer,
+    WinnerBox,
+};
+
+/// Number of times the daily challenge can be played per day
+pub const MAX_DAILY_ATTEMPTS: u32 = 3;
+
+/// What kind of game is being played
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    Normal,
+    /// Single-player game with the same layout for everyone on a given day
+    Daily {
+        /// Days since 1970-01-01, in UTC
+        day: i64,
+    },
+    /// Bots playing each other while the menu sits idle
+    Demo,
+    /// Single-player game with targets to aim at, which can be placed anywhere
+    Practice,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+/// Days since 1970-01-01, in UTC
+#[cfg(not(target_family = "wasm"))]
+pub fn today() -> i64 {
+    let since_epoch =
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    (since_epoch.as_secs() / 86400) as i64
+}
+
+/// Days since 1970-01-01, in UTC
+#[cfg(target_family = "wasm")]
+pub fn today() -> i64 {
+    (js_sys::Date::now() / 86_400_000.0).floor() as i64
+}
+
+/// Formats a day as YYYY-MM-DD
+pub fn date_string(day: i64) -> String {
+    // Howard Hinnant's civil_from_days
+    let z = day + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// Seed for the field of a given day
+pub fn seed(day: i64) -> u64 {
+    // Spread consecutive days out
+    (day as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+/// Attempts and best score on the daily challenge of some day
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DailyRecord {
+    pub attempts: u32,
+    pub best: Option<u32>,
+}
+
+impl DailyRecord {
+    fn key(day: i64) -> String {
+        format!("daily-{}", date_string(day))
+    }
+
+    pub fn load(day: i64) -> Self {
+        storage::load(&Self::key(day))
+            .and_then(|s| {
+                let mut parts = s.split_whitespace();
+                let attempts = parts.next()?.parse().ok()?;
+                let best = parts.next().and_then(|b| b.parse().ok());
+                Some(Self { attempts, best })
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, day: i64) {
+        let value = match self.best {
+            Some(best) => format!("{} {}", self.attempts, best),
+            None => self.attempts.to_string(),
+        };
+        storage::save(&Self::key(day), &value);
+    }
+
+    pub fn attempts_left(&self) -> u32 {
+        MAX_DAILY_ATTEMPTS.saturating_sub(self.attempts)
+    }
+}
+
+/// Labels the text of the daily challenge button
+#[derive(Component)]
+pub struct DailyButtonText;
+
+pub fn update_daily_button_text(mut texts: Query<&mut Text, With<DailyButtonText>>) {
+    let record = DailyRecord::load(today());
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = if record.attempts_left() == 0 {
+            "Daily Challenge (done for today)".to_owned()
+        } else {
+            format!("Daily Challenge ({} left)", record.attempts_left())
+        };
+    }
+}
+
+/// Records the result of a daily challenge once all rockets are gone,
+/// and copies a result string to the clipboard
+pub fn finish_daily(
+    mode: Res<GameMode>,
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    projectiles: Query<&Projectile>,
+    winner_box: Query<&WinnerBox>,
+    field: Query<Entity, With<Field>>,
+    fonts: Res<Assets<Font>>,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut commands: Commands,
+) {
+    let day = if let GameMode::Daily { day } = *mode { day } else { return };
+    if projectiles.iter().next().is_some()
+        || winner_box.iter().next().is_some()
+        || !game.is_on_final_round()
+    {
+        return;
+    }
+
+    let score = players[0].num_balls;
+    let mut record = DailyRecord::load(day);
+    let new_best = record.best.map_or(true, |best| score > best);
+    if new_best {
+        record.best = Some(score);
+    }
+    record.save(day);
+
+    let result = format!(
+        "Graph War daily {}: {} balls (attempt {}/{})",
+        date_string(day),
+        score,
+        record.attempts,
+        MAX_DAILY_ATTEMPTS
+    );
+    log::info!("{}", result);
+    egui_ctx.ctx_mut().output().copied_text = result;
+
+    commands.entity(field.single()).with_children(|node| {
+        node.spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.0, 0.0, 0.0, 0.5),
+                custom_size: Some(Vec2::new(1.6, 0.5) * game.scale),
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, RenderLayer::WinnerBox.z()),
+            ..Default::default()
+        })
+        .insert(WinnerBox);
+
+        let best_text = if new_best {
+            "New best!".to_owned()
+        } else {
+            format!("Best: {}", record.best.unwrap_or(0))
+        };
+        node.spawn_bundle(Text2dBundle {
+            text: Text::with_section(
+                format!("{} balls\n{}\n(result copied)", score, best_text),
+                TextStyle {
+                    color: Color::WHITE,
+                    font: fonts.get_handle(asset::Font),
+                    font_size: 0.0,
+                },
+                TextAlignment {
+                    horizontal: HorizontalAlign::Center,
+                    vertical: VerticalAlign::Center,
+                },
+            ),
+            transform: Transform::from_xyz(0.0, 0.0, RenderLayer::Winner.z()),
+            ..Default::default()
+        })
+        .insert(RelativeTextSize(0.4));
+    });
+}
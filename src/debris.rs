@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+
+use crate::{
+    collision::ProjectileCollision, projectile::Projectile, rules::GameRules, spawn_item,
+    time::AdvanceRound, Field, RenderLayer, ITEM_DEBRIS,
+};
+
+/// An obstacle left behind by a large explosion. Rockets that hit it get destroyed.
+#[derive(Clone, Copy, Debug, Component)]
+pub struct Debris {
+    /// Number of round starts the debris survives
+    rounds_left: u32,
+}
+
+/// Leaves debris where two rockets blew each other up, if the rules say so
+pub fn spawn_debris(
+    mut commands: Commands,
+    mut collisions: EventReader<ProjectileCollision>,
+    rules: Res<GameRules>,
+    projectiles: Query<&Projectile>,
+    transforms: Query<&Transform>,
+    images: Res<Assets<Image>>,
+    field: Query<Entity, With<Field>>,
+) {
+    let turns = if let Some(turns) = rules.debris_turns { turns } else { return };
+
+    for collision in collisions.iter() {
+        // Only rocket-rocket explosions are large enough
+        if projectiles.get(collision.other).is_err() {
+            continue;
+        }
+        let (pos0, pos1) = if let (Ok(pos0), Ok(pos1)) =
+            (transforms.get(collision.projectile), transforms.get(collision.other))
+        {
+            (pos0.translation, pos1.translation)
+        } else {
+            continue;
+        };
+
+        let position = ((pos0 + pos1) / 2.0).truncate().extend(RenderLayer::Mine.z());
+        commands.entity(field.single()).with_children(|node| {
+            spawn_item(node, &images, position, &ITEM_DEBRIS, 0)
+                .insert(Debris { rounds_left: turns });
+        });
+    }
+}
+
+/// Clears away debris that has been around long enough. Every player gets one turn per round.
+pub fn age_debris(
+    mut commands: Commands,
+    mut advance_round_events: EventReader<AdvanceRound>,
+    mut debris: Query<(Entity, &mut Debris)>,
+) {
+    if advance_round_events.iter().next().is_none() {
+        return;
+    }
+
+    for (entity, mut debris) in debris.iter_mut() {
+        if debris.rounds_left == 0 {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            debris.rounds_left -= 1;
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::{input::mouse::MouseMotion, math::Vec3Swizzles, prelude::*};
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+
+use crate::{
+    analysis::{sample_candidates, Board, NUM_CANDIDATES},
+    daily::GameMode,
+    graph::{SendAction, SendFunctions},
+    time::{AdvanceRound, GameTime},
+    ui::{
+        entry_box_index, ButtonsEnabled, FunctionEntryBox, FunctionX, FunctionY, MatchSetup,
+        Textbox,
+    },
+    Ball, Game, Mine, Owner, PlayState, PlayerLabel,
+};
+
+/// How long the menu has to sit idle before a demo match starts, in seconds
+const IDLE_TIME: f32 = 30.0;
+/// Number of bots in a demo match
+const DEMO_PLAYERS: u32 = 2;
+/// How long bots wait before taking a turn or moving on to the next round, in seconds
+const DEMO_DELAY: f32 = 1.5;
+
+/// Timers for the attract mode, where bots play each other while the menu sits idle
+#[derive(Debug)]
+pub struct Demo {
+    idle: Timer,
+    delay: Timer,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self {
+            idle: Timer::from_seconds(IDLE_TIME, false),
+            delay: Timer::from_seconds(DEMO_DELAY, false),
+        }
+    }
+}
+
+fn any_input(
+    keys: &Input<KeyCode>,
+    mouse: &Input<MouseButton>,
+    motion: &mut EventReader<MouseMotion>,
+) -> bool {
+    // Every event has to be read, so this doesn't short-circuit
+    let moved = motion.iter().count() > 0;
+    moved || keys.get_just_pressed().next().is_some() || mouse.get_just_pressed().next().is_some()
+}
+
+pub fn reset_idle(mut demo: ResMut<Demo>) {
+    demo.idle.reset();
+}
+
+/// Starts a demo match once the menu has sat idle for a while
+pub fn start_demo(
+    mut demo: ResMut<Demo>,
+    time: Res<GameTime>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut mode: ResMut<GameMode>,
+    mut setup: MatchSetup,
+) {
+    if any_input(&keys, &mouse, &mut motion) {
+        demo.idle.reset();
+        return;
+    }
+    if demo.idle.tick(time.delta()).just_finished() {
+        demo.delay.reset();
+        *mode = GameMode::Demo;
+        setup.start(DEMO_PLAYERS, GameMode::Demo);
+        log::info!("Started a demo match");
+    }
+}
+
+/// Goes back to the menu on any input during a demo match
+pub fn stop_demo(
+    mode: Res<GameMode>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut play_state: ResMut<State<PlayState>>,
+) {
+    let input = any_input(&keys, &mouse, &mut motion);
+    if *mode == GameMode::Demo && *play_state.current() != PlayState::Menu && input {
+        play_state.set(PlayState::Menu).ok();
+    }
+}
+
+/// Has a bot take the current turn of a demo match, with the best shot the shot analysis finds
+pub fn play_demo_turn(
+    mut demo: ResMut<Demo>,
+    mode: Res<GameMode>,
+    time: Res<GameTime>,
+    game: Res<Game>,
+    player_comps: Query<(&Owner, &Transform), With<PlayerLabel>>,
+    balls: Query<(&Transform, Option<&Owner>), With<Ball>>,
+    mines: Query<&Transform, With<Mine>>,
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+    buttons_enabled: Res<ButtonsEnabled>,
+    mut fire_events: EventWriter<SendFunctions>,
+) {
+    if *mode != GameMode::Demo || !buttons_enabled.0 {
+        return;
+    }
+    if !demo.delay.tick(time.delta()).finished() {
+        return;
+    }
+    demo.delay.reset();
+
+    let player = game.player_turn();
+    if let Some((_, transform)) = player_comps.iter().find(|(o, _)| o.0 == player) {
+        let board = Board {
+            start: transform.translation.xy(),
+            targets: balls
+                .iter()
+                .filter(|(_, owner)| owner.map_or(true, |owner| owner.0 != player))
+                .map(|(transform, _)| transform.translation.xy())
+                .collect(),
+            mines: mines.iter().map(|transform| transform.translation.xy()).collect(),
+        };
+        let mut rng = Pcg64::seed_from_u64(((game.round_index as u64) << 32) | player as u64);
+        if let Some(best) = sample_candidates(&board, NUM_CANDIDATES, &mut rng).into_iter().next() {
+            let functions = [best.x, best.y, String::new()];
+            for (mut textbox, x, y) in entry_boxes.iter_mut() {
+                textbox.text = functions[entry_box_index(x, y)].clone();
+            }
+        }
+    }
+
+    // With empty textboxes, this just ends the turn
+    fire_events.send(SendFunctions { player_index: player, action: SendAction::Fire, charge: 0.0 });
+}
+
+/// Moves a demo match on to the next round once the rockets are done,
+/// and back to the menu once it's over
+pub fn advance_demo_round(
+    mut demo: ResMut<Demo>,
+    mode: Res<GameMode>,
+    time: Res<GameTime>,
+    game: Res<Game>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    mut advance_round_events: EventWriter<AdvanceRound>,
+    mut play_state: ResMut<State<PlayState>>,
+) {
+    if *mode != GameMode::Demo || !buttons_enabled.0 {
+        return;
+    }
+    if !demo.delay.tick(time.delta()).finished() {
+        return;
+    }
+    demo.delay.reset();
+
+    if game.is_on_final_round() {
+        play_state.set(PlayState::Menu).ok();
+    } else {
+        advance_round_events.send(AdvanceRound);
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::{
+    math::Vec3Swizzles,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{projectile::Projectile, Field, Game, RenderLayer};
+
+/// Number of cells along each side of the field in the shot density overlay
+const DENSITY_CELLS: usize = 48;
+/// Opacity of the cells rockets have visited the most
+const MAX_ALPHA: f32 = 0.6;
+
+/// Where rockets have flown over the whole match, for spectators and streams.
+/// F4 shows it as a low-resolution overlay on the field.
+#[derive(Debug)]
+pub struct ShotDensity {
+    pub shown: bool,
+    /// Frames a rocket spent in each cell, row by row from the bottom
+    visits: Vec<u32>,
+    /// Whether the overlay is out of date
+    dirty: bool,
+}
+
+impl Default for ShotDensity {
+    fn default() -> Self {
+        Self { shown: false, visits: vec![0; DENSITY_CELLS * DENSITY_CELLS], dirty: true }
+    }
+}
+
+impl ShotDensity {
+    /// RGBA pixels of the overlay, row by row from the top. The most visited cell is the most opaque.
+    fn pixels(&self) -> Vec<u8> {
+        let max = self.visits.iter().copied().max().unwrap_or(0).max(1) as f32;
+        self.visits
+            .chunks(DENSITY_CELLS)
+            .rev()
+            .flatten()
+            .flat_map(|visits| {
+                let alpha = MAX_ALPHA * (*visits as f32 / max).sqrt();
+                [255, 102, 0, (alpha * 255.0) as u8]
+            })
+            .collect()
+    }
+}
+
+/// Labels the shot density overlay
+#[derive(Component)]
+pub struct DensityOverlay;
+
+pub fn clear_density(mut density: ResMut<ShotDensity>) {
+    density.visits.fill(0);
+    density.dirty = true;
+}
+
+/// Counts the cells rockets are in
+pub fn record_density(
+    mut density: ResMut<ShotDensity>,
+    game: Res<Game>,
+    rockets: Query<&Transform, With<Projectile>>,
+) {
+    let half_size = game.half_size();
+    for transform in rockets.iter() {
+        let cell = ((transform.translation.xy() + half_size) / (2.0 * half_size)
+            * DENSITY_CELLS as f32)
+            .floor();
+        if cell.cmpge(Vec2::ZERO).all() && cell.cmplt(Vec2::splat(DENSITY_CELLS as f32)).all() {
+            density.visits[cell.y as usize * DENSITY_CELLS + cell.x as usize] += 1;
+            density.dirty = true;
+        }
+    }
+}
+
+/// Shows or hides the shot density overlay with F4, and keeps it up to date while it's shown
+pub fn show_density(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut density: ResMut<ShotDensity>,
+    game: Res<Game>,
+    mut images: ResMut<Assets<Image>>,
+    mut overlays: Query<(&mut Visibility, &mut Sprite, &Handle<Image>), With<DensityOverlay>>,
+    field: Query<Entity, With<Field>>,
+) {
+    if keys.just_pressed(KeyCode::F4) {
+        density.shown = !density.shown;
+    }
+
+    if let Ok((mut visibility, mut sprite, handle)) = overlays.get_single_mut() {
+        visibility.is_visible = density.shown;
+        sprite.custom_size = Some(2.0 * game.half_size());
+        if density.shown && density.dirty {
+            if let Some(image) = images.get_mut(handle) {
+                image.data = density.pixels();
+            }
+            density.dirty = false;
+        }
+    } else if density.shown {
+        let field = if let Ok(field) = field.get_single() { field } else { return };
+        let size = Extent3d {
+            width: DENSITY_CELLS as u32,
+            height: DENSITY_CELLS as u32,
+            depth_or_array_layers: 1,
+        };
+        let image = images.add(Image::new(
+            size,
+            TextureDimension::D2,
+            density.pixels(),
+            TextureFormat::Rgba8UnormSrgb,
+        ));
+        commands.entity(field).with_children(|node| {
+            node.spawn_bundle(SpriteBundle {
+                sprite: Sprite { custom_size: Some(2.0 * game.half_size()), ..Default::default() },
+                texture: image,
+                transform: Transform::from_translation(Vec3::Z * RenderLayer::Heat.z()),
+                ..Default::default()
+            })
+            .insert(DensityOverlay);
+        });
+        density.dirty = false;
+    }
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+
+use crate::{
+    projectile::Projectile,
+    status::{StatusEffects, StatusKind},
+    time::GameTime,
+    Owner, PlayerLabel,
+};
+
+/// Number of time dilation pickups spawned in a normal round, if the rules have them
+pub const NUM_DILATION_PICKUPS: usize = 1;
+/// Number of round starts an unused time dilation survives
+pub const DILATION_ROUNDS: u32 = 3;
+/// How long a time dilation lasts, in seconds
+const DILATION_TIME: f32 = 1.5;
+/// How fast rockets fly while slowed down by a time dilation
+const SLOW_RATE: f32 = 0.4;
+/// How fast rockets fly while sped up by a time dilation
+const FAST_RATE: f32 = 2.0;
+
+/// Labels a time dilation pickup. The player whose rocket collects it gets a
+/// `StatusKind::Dilation` stack, which they can use while rockets fly
+/// to slow down or speed up their own rockets for a moment.
+#[derive(Component)]
+pub struct DilationPickup;
+
+/// Scales how fast a projectile moves along its path until the timer runs out
+#[derive(Component)]
+pub struct Dilation {
+    pub rate: f32,
+    timer: Timer,
+}
+
+/// Keys players press to slow down their rockets, by player index
+const SLOW_KEYS: [KeyCode; 4] = [KeyCode::Q, KeyCode::W, KeyCode::E, KeyCode::R];
+/// Keys players press to speed up their rockets, by player index
+const FAST_KEYS: [KeyCode; 4] = [KeyCode::A, KeyCode::S, KeyCode::D, KeyCode::F];
+
+/// Dilates time for a player's rockets when a player who has a time dilation presses one of their keys
+pub fn use_dilations(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut players: Query<(&Owner, &mut StatusEffects), With<PlayerLabel>>,
+    projectiles: Query<(Entity, &Owner), With<Projectile>>,
+) {
+    for (owner, mut effects) in players.iter_mut() {
+        let index = owner.0 as usize;
+        let rate = match (SLOW_KEYS.get(index), FAST_KEYS.get(index)) {
+            (Some(slow), _) if keys.just_pressed(*slow) => SLOW_RATE,
+            (_, Some(fast)) if keys.just_pressed(*fast) => FAST_RATE,
+            _ => continue,
+        };
+        let own_rockets =
+            projectiles.iter().filter(|(_, o)| o.0 == owner.0).map(|(e, _)| e).collect::<Vec<_>>();
+        if own_rockets.is_empty() || !effects.consume(StatusKind::Dilation) {
+            continue;
+        }
+
+        for projectile in own_rockets {
+            commands
+                .entity(projectile)
+                .insert(Dilation { rate, timer: Timer::from_seconds(DILATION_TIME, false) });
+        }
+        log::info!("P{} dilated time for their rockets by {}x", owner.0 + 1, rate);
+    }
+}
+
+/// Ends time dilations once they run out
+pub fn tick_dilations(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut dilations: Query<(Entity, &mut Dilation)>,
+) {
+    for (entity, mut dilation) in dilations.iter_mut() {
+        if dilation.timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Dilation>();
+        }
+    }
+}
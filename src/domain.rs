@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+
+use crate::{core::interval::domain_warnings, ui::TypedShot};
+
+/// Labels the text that warns about places the current functions might be undefined
+#[derive(Component)]
+pub struct DomainWarningText;
+
+/// Warns the current player before they fire about places their functions might be undefined,
+/// like dividing by something that crosses zero
+pub fn update_domain_warnings(
+    shot: Res<TypedShot>,
+    mut text: Query<&mut Text, With<DomainWarningText>>,
+) {
+    if !shot.is_changed() {
+        return;
+    }
+
+    let warnings = match &shot.parsed {
+        Some(Ok(parametric)) => domain_warnings(parametric),
+        _ => vec![],
+    };
+    text.single_mut().sections[0].value = warnings
+        .iter()
+        .map(|warning| format!("(!) Shot {} for some t", warning))
+        .collect::<Vec<_>>()
+        .join("\n");
+}
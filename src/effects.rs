@@ -1,38 +1,96 @@
 use bevy::prelude::*;
 
-use crate::{asset, collision::RocketCollision, z, Field};
+use crate::{
+    asset,
+    breakdown::{FlightNote, NoteKind},
+    charge::{blast_radius, Charge},
+    collision::ProjectileCollision,
+    time::GameTime,
+    DespawnOnExit, Field, PlayState, RenderLayer,
+};
 
+/// How long the puff of a fizzled rocket lasts, in seconds
+const PUFF_TIME: f32 = 0.4;
+/// Size the puff of a fizzled rocket grows to
+const PUFF_SIZE: f32 = 0.6;
+
+/// The little puff left where a rocket fizzled out
 #[derive(Component)]
-pub struct Effect;
+pub struct Puff(Timer);
 
 pub fn spawn_boom(
     mut commands: Commands,
     field: Query<Entity, With<Field>>,
-    mut rocket_collisions: EventReader<RocketCollision>,
+    mut rocket_collisions: EventReader<ProjectileCollision>,
     transforms: Query<&Transform>,
+    charges: Query<&Charge>,
     images: Res<Assets<Image>>,
 ) {
     let rotation = Quat::from_rotation_z(std::f32::consts::TAU / 16.0);
 
     commands.entity(field.single()).with_children(|node| {
         for collision in rocket_collisions.iter() {
-            let pos0 = transforms.get(collision.rocket).unwrap();
+            let pos0 = transforms.get(collision.projectile).unwrap();
             let pos1 = transforms.get(collision.other).unwrap();
             let mut position = (pos0.translation + pos1.translation) / 2.0;
-            position.z = z::BOOM;
+            position.z = RenderLayer::Boom.z();
+            let charge = [collision.projectile, collision.other]
+                .into_iter()
+                .filter_map(|entity| charges.get(entity).ok())
+                .fold(0.0, |max, charge| charge.0.max(max));
+            let size = 2.0 * blast_radius(charge);
             node.spawn_bundle(SpriteBundle {
-                sprite: Sprite { custom_size: Some([0.5; 2].into()), ..Default::default() },
+                sprite: Sprite { custom_size: Some(Vec2::splat(size)), ..Default::default() },
                 texture: images.get_handle(asset::Boom),
                 transform: Transform::from_rotation(rotation).with_translation(position),
                 ..Default::default()
             })
-            .insert(Effect);
+            .insert(DespawnOnExit(PlayState::Fire));
         }
     });
 }
 
-pub fn remove_effects(mut commands: Commands, effects: Query<Entity, With<Effect>>) {
-    for entity in effects.iter() {
-        commands.entity(entity).despawn_recursive();
+/// Leaves a puff where rockets fizzle out
+pub fn spawn_puffs(
+    mut commands: Commands,
+    field: Query<Entity, With<Field>>,
+    mut notes: EventReader<FlightNote>,
+    images: Res<Assets<Image>>,
+) {
+    for note in notes.iter().filter(|note| note.kind == NoteKind::Fizzled) {
+        commands.entity(field.single()).with_children(|node| {
+            node.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.6, 0.6, 0.6, 0.8),
+                    custom_size: Some(Vec2::ONE),
+                    ..Default::default()
+                },
+                texture: images.get_handle(asset::Boom),
+                transform: Transform::from_translation(note.position.extend(RenderLayer::Boom.z()))
+                    .with_scale(Vec3::new(0.0, 0.0, 1.0)),
+                ..Default::default()
+            })
+            .insert(Puff(Timer::from_seconds(PUFF_TIME, false)))
+            .insert(DespawnOnExit(PlayState::Fire));
+        });
+    }
+}
+
+/// Grows puffs while fading them out
+pub fn fade_puffs(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut puffs: Query<(Entity, &mut Puff, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut puff, mut transform, mut sprite) in puffs.iter_mut() {
+        if puff.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let progress = puff.0.percent();
+        let size = PUFF_SIZE * (0.5 + 0.5 * progress);
+        transform.scale = Vec3::new(size, size, 1.0);
+        sprite.color.set_a(0.8 * (1.0 - progress));
     }
 }
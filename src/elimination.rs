@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+
+use crate::{
+    projector::ProjectorCamera,
+    rules::GameRules,
+    time::{AdvanceRound, AdvanceTurn},
+    ui::{PlayerFunctionDisplay, TextboxesEditable, UiCamera},
+    Game, Player,
+};
+
+/// Knocks out the players with the fewest balls after each normal round but the last,
+/// if the rules say so. Nobody gets knocked out if everyone left is tied.
+/// This runs before the round index gets advanced.
+pub fn eliminate_players(
+    mut advance_round_events: EventReader<AdvanceRound>,
+    game: Res<Game>,
+    rules: Res<GameRules>,
+    mut players: ResMut<Vec<Player>>,
+    mut displays: Query<(&mut Style, &PlayerFunctionDisplay)>,
+) {
+    if advance_round_events.iter().next().is_none() || !rules.elimination {
+        return;
+    }
+    if game.round_index == 0 || game.round_index >= game.num_rounds {
+        return;
+    }
+
+    let mut alive = players.iter().filter(|p| !p.eliminated);
+    let min_balls = alive.clone().map(|p| p.num_balls).min().unwrap_or(0);
+    if alive.all(|p| p.num_balls == min_balls) {
+        return;
+    }
+
+    for (i, player) in players.iter_mut().enumerate() {
+        if !player.eliminated && player.num_balls == min_balls {
+            log::info!("P{} was eliminated", i + 1);
+            player.eliminated = true;
+        }
+    }
+
+    for (mut style, display) in displays.iter_mut() {
+        if players[display.player_index as usize].eliminated {
+            style.display = Display::None;
+        }
+    }
+}
+
+/// Ends the turn of an eliminated player right away
+pub fn skip_eliminated_turns(
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    mut textboxes_editable: ResMut<TextboxesEditable>,
+    mut advance_turn_events: EventWriter<AdvanceTurn>,
+    mut skipped: Local<Option<(u32, u32)>>,
+) {
+    let turn = (game.round_index, game.order_index);
+    if !players[game.player_turn() as usize].eliminated || *skipped == Some(turn) {
+        return;
+    }
+
+    *skipped = Some(turn);
+    textboxes_editable.0 = false;
+    advance_turn_events.send(AdvanceTurn);
+}
+
+/// Lets eliminated players look around while rockets fly.
+/// The arrow keys pan and +/- zoom.
+pub fn spectator_camera(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    mut camera: Query<
+        &mut Transform,
+        (With<OrthographicProjection>, Without<UiCamera>, Without<ProjectorCamera>),
+    >,
+) {
+    if !players.iter().any(|p| p.eliminated) {
+        return;
+    }
+    let mut transform =
+        if let Ok(transform) = camera.get_single_mut() { transform } else { return };
+
+    let mut pan = Vec2::ZERO;
+    for (key, dir) in [
+        (KeyCode::Left, -Vec2::X),
+        (KeyCode::Right, Vec2::X),
+        (KeyCode::Down, -Vec2::Y),
+        (KeyCode::Up, Vec2::Y),
+    ] {
+        if keys.pressed(key) {
+            pan += dir;
+        }
+    }
+    let speed = game.scale * transform.scale.x;
+    transform.translation += (pan * speed * time.delta_seconds()).extend(0.0);
+
+    let mut zoom = 1.0;
+    if keys.pressed(KeyCode::Equals) {
+        zoom /= 1.0 + time.delta_seconds();
+    }
+    if keys.pressed(KeyCode::Minus) {
+        zoom *= 1.0 + time.delta_seconds();
+    }
+    let scale = (transform.scale.x * zoom).clamp(0.25, 2.0);
+    transform.scale = Vec3::new(scale, scale, 1.0);
+}
+
+/// Puts the camera back after spectating
+pub fn reset_spectator_camera(
+    mut camera: Query<
+        &mut Transform,
+        (With<OrthographicProjection>, Without<UiCamera>, Without<ProjectorCamera>),
+    >,
+) {
+    if let Ok(mut transform) = camera.get_single_mut() {
+        transform.translation.x = 0.0;
+        transform.translation.y = 0.0;
+        transform.scale = Vec3::ONE;
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioSource};
+
+use crate::{
+    asset,
+    projectile::{Projectile, ProjectileDestroyed},
+    status::{StatusEffects, StatusKind},
+    time::GameTime,
+    DespawnOnExit, Field, Game, Owner, PlayState, PlayerLabel, RenderLayer,
+};
+
+/// Number of EMP pickups spawned in a normal round, if the rules have them
+pub const NUM_EMP_PICKUPS: usize = 1;
+/// Number of round starts an unused EMP survives
+pub const EMP_ROUNDS: u32 = 3;
+/// Number of round starts the slow on players whose rockets an EMP destroyed survives
+const SLOW_ROUNDS: u32 = 2;
+/// How long the pulse of an EMP takes to cover the field, in seconds
+const PULSE_TIME: f32 = 0.5;
+
+/// Labels an EMP pickup. The player whose rocket collects it gets a `StatusKind::Emp` stack,
+/// which they can use while rockets fly to destroy everyone else's rockets.
+#[derive(Component)]
+pub struct EmpPickup;
+
+/// The expanding pulse of an EMP
+#[derive(Component)]
+pub struct Pulse(Timer);
+
+/// Keys players press to use an EMP, by player index
+const EMP_KEYS: [KeyCode; 4] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+
+/// Sets off an EMP when a player who has one presses their number key.
+/// The players whose rockets it destroys get slowed.
+pub fn use_emps(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut players: Query<(&Owner, &Transform, &mut StatusEffects), With<PlayerLabel>>,
+    projectiles: Query<(Entity, &Owner), With<Projectile>>,
+    mut destroyed_events: EventWriter<ProjectileDestroyed>,
+    field: Query<Entity, With<Field>>,
+    images: Res<Assets<Image>>,
+    audio: Res<Audio>,
+    sounds: Res<Assets<AudioSource>>,
+) {
+    let mut slowed = vec![];
+    for (owner, transform, mut effects) in players.iter_mut() {
+        let key = if let Some(key) = EMP_KEYS.get(owner.0 as usize) { *key } else { continue };
+        if !keys.just_pressed(key) || !effects.consume(StatusKind::Emp) {
+            continue;
+        }
+
+        for (projectile, projectile_owner) in projectiles.iter() {
+            if projectile_owner.0 != owner.0 {
+                destroyed_events.send(ProjectileDestroyed { projectile });
+                slowed.push(projectile_owner.0);
+            }
+        }
+
+        let mut position = transform.translation;
+        position.z = RenderLayer::Boom.z();
+        commands.entity(field.single()).with_children(|node| {
+            node.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.5, 0.8, 1.0, 0.6),
+                    custom_size: Some(Vec2::ONE),
+                    ..Default::default()
+                },
+                texture: images.get_handle(asset::Boom),
+                transform: Transform::from_translation(position)
+                    .with_scale(Vec3::new(0.0, 0.0, 1.0)),
+                ..Default::default()
+            })
+            .insert(Pulse(Timer::from_seconds(PULSE_TIME, false)))
+            .insert(DespawnOnExit(PlayState::Fire));
+        });
+        audio.play(sounds.get_handle(asset::Explosion));
+        log::info!("P{} set off an EMP", owner.0 + 1);
+    }
+
+    for (owner, _, mut effects) in players.iter_mut() {
+        if slowed.contains(&owner.0) {
+            effects.add(StatusKind::Slow, SLOW_ROUNDS);
+        }
+    }
+}
+
+/// Grows pulses until they cover the field, fading them out as they go
+pub fn expand_pulses(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    game: Res<Game>,
+    mut pulses: Query<(Entity, &mut Pulse, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut pulse, mut transform, mut sprite) in pulses.iter_mut() {
+        if pulse.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let progress = pulse.0.percent();
+        // Big enough to cover the field from a corner
+        let size = progress * 4.0 * game.half_size().max_element();
+        transform.scale = Vec3::new(size, size, 1.0);
+        sprite.color.set_a(0.6 * (1.0 - progress));
+    }
+}
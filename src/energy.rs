@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+use crate::{rules::GameRules, Game, Player};
+
+/// Energy of a full launcher
+pub const MAX_ENERGY: f32 = 1.0;
+/// Energy each rocket uses up
+pub const SHOT_COST: f32 = 0.3;
+/// Energy regained per second
+pub const REGEN_RATE: f32 = 0.2;
+/// How long an overheated launcher can't send rockets, in seconds
+pub const OVERHEAT_TIME: f64 = 2.0;
+
+/// A player's launcher energy, if the energy rule is on.
+/// Times are in seconds since startup, like `Player::next_fire_time`.
+#[derive(Clone, Copy, Debug)]
+pub struct Energy {
+    /// Energy at `updated_at`
+    level: f32,
+    updated_at: f64,
+    /// Time when the launcher cools down after running out of energy
+    overheated_until: f64,
+}
+
+impl Default for Energy {
+    fn default() -> Self {
+        Self { level: MAX_ENERGY, updated_at: 0.0, overheated_until: 0.0 }
+    }
+}
+
+impl Energy {
+    /// Energy at time `now`. It doesn't come back while the launcher is overheated.
+    pub fn level(&self, now: f64) -> f32 {
+        let regen_time = (now - self.updated_at.max(self.overheated_until)).max(0.0);
+        (self.level + regen_time as f32 * REGEN_RATE).min(MAX_ENERGY)
+    }
+
+    /// How long until the launcher cools down, if it's overheated at time `now`
+    pub fn overheat_wait(&self, now: f64) -> Option<f64> {
+        (now < self.overheated_until).then(|| self.overheated_until - now)
+    }
+
+    /// Uses up the energy of a rocket. Any rocket can be sent while the launcher isn't overheated,
+    /// but one that takes more energy than is left overheats it.
+    pub fn spend(&mut self, now: f64) {
+        self.level = self.level(now) - SHOT_COST;
+        self.updated_at = now;
+        if self.level < 0.0 {
+            self.level = 0.0;
+            self.overheated_until = now + OVERHEAT_TIME;
+        }
+    }
+}
+
+/// Labels the node that holds the energy bar
+#[derive(Component)]
+pub struct EnergyBar;
+
+/// Labels the part of the energy bar that shows how much energy is left
+#[derive(Component)]
+pub struct EnergyFill;
+
+const ENERGY_COLOR: Color = Color::rgb(0.2, 0.6, 1.0);
+const OVERHEAT_COLOR: Color = Color::rgb(0.9, 0.3, 0.1);
+
+/// Shows the current player's energy, or hides the bar if the energy rule is off
+pub fn update_energy_bar(
+    players: Res<Vec<Player>>,
+    game: Res<Game>,
+    rules: Res<GameRules>,
+    time: Res<Time>,
+    mut bar: Query<&mut Style, (With<EnergyBar>, Without<EnergyFill>)>,
+    mut fill: Query<(&mut Style, &mut UiColor), With<EnergyFill>>,
+) {
+    let display = if rules.energy { Display::Flex } else { Display::None };
+    let mut bar_style = bar.single_mut();
+    if bar_style.display != display {
+        bar_style.display = display;
+    }
+    if !rules.energy {
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+    let energy = &players[game.player_turn() as usize].energy;
+    let (mut style, mut color) = fill.single_mut();
+    style.size.width = Val::Percent(energy.level(now) / MAX_ENERGY * 100.0);
+    *color =
+        UiColor(if energy.overheat_wait(now).is_some() { OVERHEAT_COLOR } else { ENERGY_COLOR });
+}
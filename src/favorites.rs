@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+
+use crate::{
+    stats::NUM_PROFILES,
+    storage,
+    ui::{ButtonsEnabled, FunctionEntryBox, FunctionStatus, FunctionX, FunctionY, Textbox},
+    Game,
+};
+
+/// Most favorites a player slot keeps. Saving past this forgets the oldest one.
+const MAX_FAVORITES: usize = 20;
+/// Saves the current x(t) and y(t) as a favorite, or with Shift, forgets them
+const SAVE_KEY: KeyCode = KeyCode::F5;
+/// Fills in the next favorite, or with Shift, the previous one
+const RECALL_KEY: KeyCode = KeyCode::F6;
+
+/// Favorite x(t) and y(t) pairs of each player slot, oldest first, which are remembered
+/// between sessions
+#[derive(Debug)]
+pub struct FavoriteFunctions(pub Vec<Vec<[String; 2]>>);
+
+impl Default for FavoriteFunctions {
+    fn default() -> Self {
+        Self((0..NUM_PROFILES).map(Self::load).collect())
+    }
+}
+
+impl FavoriteFunctions {
+    fn key(index: usize) -> String {
+        format!("favorites-p{}", index + 1)
+    }
+
+    /// Loads the favorites of a player slot, one per line with x(t) and y(t) separated by a tab.
+    /// Broken lines are skipped.
+    fn load(index: usize) -> Vec<[String; 2]> {
+        storage::load(&Self::key(index))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(x, y)| [x.to_owned(), y.to_owned()])
+            .collect()
+    }
+
+    fn save(&self, index: usize) {
+        let value =
+            self.0[index].iter().map(|[x, y]| format!("{}\t{}\n", x, y)).collect::<String>();
+        storage::save(&Self::key(index), &value);
+    }
+}
+
+/// Saves, forgets, and fills in the current player's favorites with their hotkeys
+pub fn use_favorites(
+    keys: Res<Input<KeyCode>>,
+    game: Res<Game>,
+    mut favorites: ResMut<FavoriteFunctions>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+    mut status: Query<&mut Text, With<FunctionStatus>>,
+) {
+    let saving = keys.just_pressed(SAVE_KEY);
+    let recalling = keys.just_pressed(RECALL_KEY);
+    if !buttons_enabled.0 || !(saving || recalling) {
+        return;
+    }
+    let shift = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+
+    let mut current: [String; 2] = Default::default();
+    for (textbox, x, y) in entry_boxes.iter() {
+        if x.is_some() {
+            current[0] = textbox.text.trim().to_owned();
+        } else if y.is_some() {
+            current[1] = textbox.text.trim().to_owned();
+        }
+    }
+
+    let player = game.player_turn() as usize;
+    let list = &mut favorites.0[player];
+    let position = list.iter().position(|favorite| *favorite == current);
+    let message = if saving {
+        if current.iter().all(String::is_empty) {
+            return;
+        }
+        if shift {
+            let position = if let Some(position) = position { position } else { return };
+            list.remove(position);
+            format!("Forgot favorite ({} saved)", list.len())
+        } else {
+            // Saving a favorite again makes it the newest
+            if let Some(position) = position {
+                list.remove(position);
+            }
+            list.push(current);
+            if list.len() > MAX_FAVORITES {
+                list.remove(0);
+            }
+            format!("Saved favorite ({} saved)", list.len())
+        }
+    } else {
+        if list.is_empty() {
+            return;
+        }
+        let len = list.len();
+        let next = match (position, shift) {
+            (Some(position), false) => (position + 1) % len,
+            (Some(position), true) => (position + len - 1) % len,
+            (None, false) => 0,
+            (None, true) => len - 1,
+        };
+        for (mut textbox, x, y) in entry_boxes.iter_mut() {
+            if x.is_some() {
+                textbox.text = list[next][0].clone();
+            } else if y.is_some() {
+                textbox.text = list[next][1].clone();
+            }
+        }
+        format!("Favorite {}/{}", next + 1, len)
+    };
+    if saving {
+        favorites.save(player);
+    }
+
+    let mut status = status.single_mut();
+    status.sections[0].value = format!("{}\n", message);
+    status.sections[0].style.color = Color::BLACK;
+}
@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    asset,
+    core::lang::{parse_parametric, Parametric},
+    spawn_item,
+    time::GameTime,
+    Ball, Field, Game, Mine, RenderLayer, ITEM_BALL, ITEM_MINE,
+};
+
+/// What a hazard does
+#[derive(Clone, Copy, Debug)]
+pub enum HazardKind {
+    /// A mine that moves along the hazard's path
+    Mine,
+    /// Drops a ball where it is every `interval` seconds, up to `max_balls` per round
+    Spawner { interval: f32, max_balls: u32 },
+}
+
+/// A hazard as written in a field config.
+/// The path uses the same language as rockets, with t going from 0 to 1 every `period` seconds,
+/// in units where the field goes from -1 to 1.
+#[derive(Clone, Debug)]
+pub struct HazardConfig {
+    pub kind: HazardKind,
+    pub x: &'static str,
+    pub y: &'static str,
+    pub assigns: &'static str,
+    pub period: f32,
+}
+
+#[derive(Component)]
+pub struct Hazard {
+    kind: HazardKind,
+    parametric: Parametric,
+    period: f32,
+    time: f32,
+    spawn_timer: Timer,
+    balls_spawned: u32,
+}
+
+impl Hazard {
+    /// Position on the field at the hazard's current time
+    fn position(&self, half_size: Vec2) -> Vec2 {
+        self.parametric.eval((self.time / self.period).fract() as f64) * half_size
+    }
+}
+
+/// Spawns the hazards of a field. Hazards whose path doesn't parse are skipped with a warning.
+pub fn spawn_hazards(
+    node: &mut ChildBuilder,
+    images: &Res<Assets<Image>>,
+    configs: &[HazardConfig],
+    half_size: Vec2,
+) {
+    for config in configs {
+        let parametric = match parse_parametric(config.x, config.y, config.assigns) {
+            Ok(parametric) => parametric,
+            Err(error) => {
+                log::warn!("Skipping hazard with invalid path: {}", error);
+                continue;
+            }
+        };
+
+        let spawn_timer = match config.kind {
+            HazardKind::Spawner { interval, .. } => Timer::from_seconds(interval, true),
+            HazardKind::Mine => Timer::default(),
+        };
+        let hazard = Hazard {
+            kind: config.kind,
+            parametric,
+            period: config.period,
+            time: 0.0,
+            spawn_timer,
+            balls_spawned: 0,
+        };
+        let start = hazard.position(half_size);
+
+        match config.kind {
+            HazardKind::Mine => {
+                spawn_item(node, images, start.extend(RenderLayer::Mine.z()), &ITEM_MINE, 0)
+                    .insert(RigidBodyTypeComponent::from(RigidBodyType::KinematicPositionBased))
+                    .insert(Mine)
+                    .insert(hazard);
+            }
+            HazardKind::Spawner { .. } => {
+                node.spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1.0, 1.0, 1.0, 0.4),
+                        custom_size: Some(Vec2::ONE),
+                        ..Default::default()
+                    },
+                    texture: images.get_handle(asset::Ball),
+                    transform: Transform::from_translation(start.extend(RenderLayer::Pickup.z()))
+                        .with_scale(Vec3::from([0.6; 3])),
+                    ..Default::default()
+                })
+                .insert(hazard);
+            }
+        }
+    }
+}
+
+pub fn move_hazards(
+    mut commands: Commands,
+    mut hazards: Query<(&mut Hazard, &mut Transform, Option<&mut RigidBodyPositionComponent>)>,
+    time: Res<GameTime>,
+    game: Res<Game>,
+    images: Res<Assets<Image>>,
+    field: Query<Entity, With<Field>>,
+) {
+    for (mut hazard, mut transform, body_position) in hazards.iter_mut() {
+        hazard.time += time.delta_seconds();
+        let pos = hazard.position(game.half_size());
+        if !pos.is_finite() {
+            continue;
+        }
+
+        transform.translation = pos.extend(transform.translation.z);
+        if let Some(mut body_position) = body_position {
+            body_position.0.next_position = Isometry::new(pos.into(), 0.0);
+        }
+
+        if let HazardKind::Spawner { max_balls, .. } = hazard.kind {
+            hazard.spawn_timer.tick(time.delta());
+            if hazard.spawn_timer.just_finished() && hazard.balls_spawned < max_balls {
+                hazard.balls_spawned += 1;
+                commands.entity(field.single()).with_children(|node| {
+                    spawn_item(node, &images, pos.extend(RenderLayer::Pickup.z()), &ITEM_BALL, 0)
+                        .insert(Ball);
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::{core::lang::Parametric, projectile::MotionModel, time::GameTime, Game, RenderLayer};
+
+/// Maximum distance a player can travel in one hop
+pub const MAX_HOP_LENGTH: f32 = 1.5;
+/// Time a hop takes, in seconds
+pub const HOP_TIME: f32 = 0.8;
+
+/// A player hopping along a short path
+#[derive(Component)]
+pub struct Hop {
+    /// Cut off at the maximum hop length
+    motion: MotionModel,
+    timer: Timer,
+}
+
+impl Hop {
+    pub fn new(parametric: Parametric, start: Vec2) -> Self {
+        let mut motion = MotionModel::parametric(parametric, start);
+        motion.truncate_to_length(MAX_HOP_LENGTH);
+        Self { motion, timer: Timer::new(Duration::from_secs_f32(HOP_TIME), false) }
+    }
+}
+
+pub fn move_hops(
+    mut commands: Commands,
+    mut hops: Query<(Entity, &mut Hop, &mut Transform)>,
+    time: Res<GameTime>,
+    game: Res<Game>,
+) {
+    for (entity, mut hop, mut transform) in hops.iter_mut() {
+        hop.timer.tick(time.delta());
+
+        let pos = hop.motion.position(hop.timer.percent());
+        if pos.is_finite() {
+            // Players stay on the field
+            let pos = pos.clamp(-game.half_size(), game.half_size());
+            transform.translation = pos.extend(RenderLayer::Player.z());
+        }
+
+        if hop.timer.finished() {
+            commands.entity(entity).remove::<Hop>();
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+
+use crate::{rules::GameRules, ui::TypedShot, Game, Player};
+
+/// Labels the text that shows how much ink the current player has left,
+/// and how much their current functions would cost
+#[derive(Component)]
+pub struct InkText;
+
+/// Shows the current player's ink against what the functions they're typing cost,
+/// or nothing if ink is unlimited
+pub fn update_ink_text(
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    rules: Res<GameRules>,
+    shot: Res<TypedShot>,
+    mut text: Query<&mut Text, With<InkText>>,
+    mut shown_left: Local<Option<u32>>,
+) {
+    let max_ink = if let Some(max_ink) = rules.ink {
+        max_ink
+    } else {
+        return;
+    };
+    // Queuing a shot uses up ink without changing the textboxes
+    let left = players[game.player_turn() as usize].ink;
+    if !shot.is_changed() && *shown_left == Some(left) {
+        return;
+    }
+    *shown_left = Some(left);
+
+    let mut text = text.single_mut();
+    let section = &mut text.sections[0];
+    section.value = format!("Ink: {}/{}", left, max_ink);
+    section.style.color = Color::BLACK;
+    if let Some(Ok(parametric)) = &shot.parsed {
+        let cost = parametric.ink_cost();
+        section.value += &format!(" (this shot: {})", cost);
+        if cost > left {
+            section.style.color = Color::MAROON;
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Intersections of sampled paths with each other and with circles.
+//! Parameters along a path go from 0 at its first sample to 1 at its last,
+//! spread evenly over the segments between samples.
+
+use bevy::prelude::*;
+
+/// Point on the segments between the samples of a path at some parameter
+pub fn point_on_path(path: &[Vec2], param: f32) -> Vec2 {
+    let segments = path.len() - 1;
+    let scaled = param * segments as f32;
+    let index = (scaled as usize).min(segments - 1);
+    path[index].lerp(path[index + 1], scaled - index as f32)
+}
+
+/// The part of a path up to some parameter
+pub fn truncate(path: &[Vec2], param: f32) -> Vec<Vec2> {
+    if path.len() < 2 || param >= 1.0 {
+        return path.to_vec();
+    }
+    let scaled = param.max(0.0) * (path.len() - 1) as f32;
+    let mut result = path[..=scaled as usize].to_vec();
+    result.push(point_on_path(path, param));
+    result
+}
+
+/// Parameters along segments `a0 a1` and `b0 b1`, from 0 to 1, where they cross
+pub fn segment_intersection(a0: Vec2, a1: Vec2, b0: Vec2, b1: Vec2) -> Option<(f32, f32)> {
+    let (da, db) = (a1 - a0, b1 - b0);
+    let denom = da.perp_dot(db);
+    if denom == 0.0 {
+        return None;
+    }
+    let offset = b0 - a0;
+    let s = offset.perp_dot(db) / denom;
+    let t = offset.perp_dot(da) / denom;
+    ((0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t)).then(|| (s, t))
+}
+
+/// Parameters along two paths where they cross, in order along the first path
+pub fn curve_intersections(a: &[Vec2], b: &[Vec2]) -> Vec<(f32, f32)> {
+    let (segments_a, segments_b) = (a.len().saturating_sub(1), b.len().saturating_sub(1));
+    let mut result = vec![];
+    for (i, sa) in a.windows(2).enumerate() {
+        for (j, sb) in b.windows(2).enumerate() {
+            if let Some((s, t)) = segment_intersection(sa[0], sa[1], sb[0], sb[1]) {
+                result
+                    .push(((i as f32 + s) / segments_a as f32, (j as f32 + t) / segments_b as f32));
+            }
+        }
+    }
+    result
+}
+
+/// Parameter along segment `a b`, from 0 to 1, where it first gets within `radius` of `center`
+fn segment_circle_entry(a: Vec2, b: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    let (dir, offset) = (b - a, a - center);
+    if offset.length_squared() <= radius * radius {
+        return Some(0.0);
+    }
+    // Solve |offset + dir * s| = radius for the smaller s
+    let qa = dir.length_squared();
+    let qb = 2.0 * offset.dot(dir);
+    let qc = offset.length_squared() - radius * radius;
+    let discriminant = qb * qb - 4.0 * qa * qc;
+    if qa == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let s = (-qb - discriminant.sqrt()) / (2.0 * qa);
+    (0.0..=1.0).contains(&s).then(|| s)
+}
+
+/// Parameter along a path where it first gets within `radius` of `center`
+pub fn circle_entry(path: &[Vec2], center: Vec2, radius: f32) -> Option<f32> {
+    if path.len() == 1 {
+        return (path[0].distance(center) <= radius).then(|| 0.0);
+    }
+    let segments = path.len().saturating_sub(1) as f32;
+    path.windows(2).enumerate().find_map(|(i, segment)| {
+        segment_circle_entry(segment[0], segment[1], center, radius)
+            .map(|s| (i as f32 + s) / segments)
+    })
+}
+
+/// Closest a path gets to a point, counting the segments between samples
+pub fn path_distance(path: &[Vec2], point: Vec2) -> f32 {
+    if path.len() == 1 {
+        return path[0].distance(point);
+    }
+    path.windows(2)
+        .map(|segment| {
+            let dir = segment[1] - segment[0];
+            let s = if dir == Vec2::ZERO {
+                0.0
+            } else {
+                ((point - segment[0]).dot(dir) / dir.length_squared()).clamp(0.0, 1.0)
+            };
+            (segment[0] + dir * s).distance(point)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
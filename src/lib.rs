@@ -4,14 +4,66 @@
 #[macro_use]
 extern crate pest_derive;
 
+pub mod analysis;
 pub mod asset;
+pub mod assist;
+pub mod bot;
+pub mod breakdown;
+pub mod charge;
 pub mod collision;
+pub mod complexity;
+pub mod coordinates;
+pub mod core;
+pub mod daily;
+pub mod debris;
+pub mod demo;
+pub mod density;
+pub mod dilation;
+pub mod domain;
 pub mod effects;
+pub mod elimination;
+pub mod emp;
+pub mod energy;
+pub mod favorites;
 pub mod graph;
+pub mod hazard;
+pub mod hop;
+pub mod ink;
+pub mod intersect;
+pub mod library;
+pub mod loadout;
+pub mod lock;
+pub mod matchlog;
+pub mod mesh;
+pub mod mods;
+pub mod observer;
+pub mod parse_error;
+pub mod practice;
+pub mod preset;
+pub mod projectile;
+pub mod projector;
+pub mod quality;
+pub mod radial;
 pub mod random;
+pub mod recap;
+pub mod ricochet;
+pub mod rule_presets;
+pub mod share;
+pub mod sketch;
+pub mod snapshot;
+pub mod stats;
+pub mod status;
+pub mod storage;
+pub mod style;
 pub mod time;
+pub mod tuning;
 pub mod ui;
+pub mod validation;
+pub mod warning;
 
+pub use self::core::rules;
+
+use crate::core::lang::Parametric;
 use bevy::{
     ecs::{schedule::ShouldRun, system::EntityCommands},
     math::{Mat2, Vec3Swizzles},
@@ -21,15 +73,31 @@ use bevy::{
 use bevy_egui::EguiPlugin;
 use bevy_kira_audio::AudioPlugin;
 use bevy_rapier2d::{physics::PhysicsSystems, prelude::*};
-use graph::{Graph, Parametric, Rocket};
+use graph::Graph;
 use once_cell::sync::Lazy;
+use projectile::Projectile;
 use rand::SeedableRng;
 use rand::{distributions::Uniform, prelude::Distribution};
 use rand_pcg::Pcg64;
 #[cfg(target_family = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use crate::{collision::CollisionGroups, random::RectRegion, time::AdvanceRound};
+use crate::{
+    collision::CollisionGroups,
+    coordinates::CoordinateMode,
+    dilation::{DilationPickup, NUM_DILATION_PICKUPS},
+    emp::{EmpPickup, NUM_EMP_PICKUPS},
+    energy::Energy,
+    hazard::{Hazard, HazardConfig, HazardKind},
+    loadout::Loadout,
+    preset::NUM_PRESETS,
+    projectile::LastShotEnd,
+    random::RectRegion,
+    ricochet::{Ricochet, NUM_RICOCHET_PICKUPS},
+    rules::GameRules,
+    status::{StatusEffects, StatusIcons, StatusKind},
+    time::AdvanceRound,
+};
 
 #[cfg(target_family = "wasm")]
 #[macro_export]
@@ -73,8 +141,34 @@ pub struct PlayerLabel;
 #[derive(Clone, Debug, Default)]
 pub struct Player {
     pub num_balls: u32,
-    /// Parametric is stored here until rocket gets fired
-    pub parametric: Option<Parametric>,
+    /// Parametrics are stored here until the rockets get fired
+    pub parametrics: Vec<Parametric>,
+    /// Charge of each queued rocket, from 0 to 1
+    pub charges: Vec<f32>,
+    /// Only used if ammo is limited
+    pub ammo: u32,
+    /// Only used if ink is limited
+    pub ink: u32,
+    /// Time since startup at which the player can send another rocket, in seconds
+    pub next_fire_time: f64,
+    /// Only used if the energy rule is on
+    pub energy: Energy,
+    /// Eliminated players don't get turns anymore
+    pub eliminated: bool,
+    pub loadout: Loadout,
+    /// Rockets the player can send over the limit, from their loadout
+    pub extra_rockets: u32,
+    /// Only used if the style score rule is on
+    pub style_points: u32,
+    /// x(t), y(t), and the where clause of the last rocket the player sent
+    pub last_functions: Option<[String; 3]>,
+    /// Functions saved to the player's preset slots, in the same order as `last_functions`
+    pub presets: [Option<[String; 3]>; NUM_PRESETS],
+    /// Target locks the player has left this match
+    pub target_locks: u32,
+    /// How the player's function textboxes are read. It's kept here rather than on the player's
+    /// entity since that gets respawned every round.
+    pub coordinate_mode: CoordinateMode,
 }
 
 #[derive(Component)]
@@ -96,15 +190,36 @@ pub enum PlayState {
     Fire,
 }
 
-#[derive(Debug)]
+/// Despawns an entity when the game leaves some state. This is a safety net, so things that only
+/// make sense in that state never outlive it, however the state gets left.
+#[derive(Clone, Copy, Debug, Component)]
+pub struct DespawnOnExit(pub PlayState);
+
+/// Despawns the entities tagged to go when `state` is left
+fn despawn_on_exit(state: PlayState) -> impl FnMut(Commands, Query<(Entity, &DespawnOnExit)>) {
+    move |mut commands, entities| {
+        for (entity, tag) in entities.iter() {
+            if tag.0 == state {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Game {
     pub order_index: u32,
     pub player_order: Vec<u32>,
     pub inverse_order: Vec<u32>,
+    /// Half the height of the field
     pub scale: f32,
+    /// Width of the field divided by its height
+    pub aspect: f32,
     /// this is 1-indexed to simplify advance_round
     pub round_index: u32,
     pub num_rounds: u32,
+    /// Whether the normal rounds are followed by a destruction round
+    pub has_destruction_round: bool,
 }
 
 impl Default for Game {
@@ -114,19 +229,27 @@ impl Default for Game {
             player_order: vec![],
             inverse_order: vec![],
             scale: 4.0,
+            aspect: 1.0,
             round_index: 0,
             num_rounds: 0,
+            has_destruction_round: true,
         }
     }
 }
 
 impl Game {
+    /// Half the width and height of the field
+    pub fn half_size(&self) -> Vec2 {
+        Vec2::new(self.scale * self.aspect, self.scale)
+    }
+
     pub fn set_num_players(&mut self, num_players: u32) {
         self.order_index = 0;
         self.player_order = (0..num_players).collect();
         self.inverse_order = self.player_order.clone();
         self.round_index = 0;
         self.num_rounds = 1; //18 / num_players.pow(2) * num_players;
+        self.has_destruction_round = true;
     }
 
     pub fn num_players(&self) -> u32 {
@@ -155,10 +278,24 @@ impl Game {
     pub fn is_on_destruction_round(&self) -> bool {
         self.round_index == self.num_rounds + 1
     }
+
+    /// Whether the game ends after this round
+    pub fn is_on_final_round(&self) -> bool {
+        if self.has_destruction_round {
+            self.is_on_destruction_round()
+        } else {
+            self.is_on_last_normal_round()
+        }
+    }
 }
 
 pub const ASPECT_RATIO: f32 = 16.0 / 9.0;
 
+/// Number of balls spawned in a normal round
+pub const NUM_BALLS: usize = 85;
+/// Number of mines spawned in a normal round
+pub const NUM_MINES: usize = 15;
+
 #[derive(Clone, Copy, Debug, SystemLabel, PartialEq, Eq, Hash)]
 enum Label {
     Setup,
@@ -168,8 +305,13 @@ enum Label {
     CollectItems,
     AdvanceTurn,
     MovePlayers,
-    MoveRockets,
+    MoveProjectiles,
     SeedRng,
+    EliminatePlayers,
+    FireRockets,
+    GameTime,
+    AdvanceTimers,
+    TypedShot,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, StageLabel)]
@@ -191,7 +333,31 @@ pub fn run() {
         .insert_resource(Pcg64::new(0, 0))
         .insert_resource(vec![] as Vec<Player>)
         .insert_resource(Game::default())
+        .insert_resource(rules::GameRules::default())
+        .insert_resource(daily::GameMode::default())
+        .insert_resource(analysis::AnalysisMode::default())
+        .insert_resource(charge::Charging::default())
+        .insert_resource(breakdown::Breakdown::default())
+        .insert_resource(loadout::load_player_configs())
         .insert_resource(ui::TextboxesEditable(true))
+        .insert_resource(ui::SubmitRequested::default())
+        .insert_resource(ui::FocusedEntryBox::default())
+        .insert_resource(radial::RadialMenu::default())
+        .insert_resource(recap::RecapLog::default())
+        .insert_resource(snapshot::Snapshots::default())
+        .insert_resource(sketch::Sketch::default())
+        .insert_resource(tuning::Preview::default())
+        .insert_resource(ui::TypedShot::default())
+        .insert_resource(stats::Stats::default())
+        .insert_resource(favorites::FavoriteFunctions::default())
+        .insert_resource(demo::Demo::default())
+        .insert_resource(assist::Assist::default())
+        .insert_resource(density::ShotDensity::default())
+        .insert_resource(quality::RenderQuality::default())
+        .insert_resource(practice::TargetApproaches::default())
+        .insert_resource(matchlog::MatchLog::from_env())
+        .insert_resource(observer::Observer::from_env())
+        .insert_resource(time::GameTime::new())
         .insert_resource(ui::ButtonsEnabled(true))
         .insert_resource(PrevWindowSize([0.0, 0.0]))
         .insert_resource(vec![] as Vec<HandleUntyped>)
@@ -202,27 +368,74 @@ pub fn run() {
         //.register_inspectable::<ui::EguiId>()
         .add_plugin(EguiPlugin)
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+        .add_plugin(projector::ProjectorPlugin)
         .add_event::<graph::SendFunctions>()
+        .add_event::<parse_error::ParseErrorEvent>()
         .add_event::<time::AdvanceTurn>()
         .add_event::<time::AdvanceRound>()
-        .add_event::<collision::RocketCollision>()
-        .add_event::<graph::RocketTimeUp>()
+        .add_event::<collision::ProjectileCollision>()
+        .add_event::<projectile::ProjectileExpired>()
+        .add_event::<projectile::ProjectileDestroyed>()
+        .add_event::<breakdown::FlightNote>()
         .add_stage_before(
             CoreStage::PreUpdate,
             Stage::AdvanceTimers,
             SystemStage::single_threaded(),
         )
+        .add_startup_system_to_stage(StartupStage::PreStartup, mods::load_mods)
+        .add_startup_system_to_stage(StartupStage::PreStartup, rule_presets::load_rule_presets)
+        .add_startup_system_to_stage(StartupStage::PreStartup, library::load_function_library)
         .add_startup_system(seed_rng.label(Label::SeedRng))
         .add_startup_system(asset::load_assets.label(Label::SeedRng))
         .add_startup_system(ui::setup_egui.label(Label::Setup).after(Label::SeedRng))
         .add_startup_system(ui::load_ui.label(Label::Setup).after(Label::SeedRng))
-        .add_system_to_stage(Stage::AdvanceTimers, time::advance_timers)
+        .add_system_to_stage(
+            Stage::AdvanceTimers,
+            time::update_game_time.label(Label::GameTime).before(Label::AdvanceTimers),
+        )
+        .add_system_to_stage(Stage::AdvanceTimers, time::advance_timers.label(Label::AdvanceTimers))
+        .add_system_to_stage(
+            Stage::AdvanceTimers,
+            time::throttle_when_unfocused.after(Label::GameTime),
+        )
         .add_system_to_stage(CoreStage::PreUpdate, ui::update_buttons)
         .add_system_to_stage(CoreStage::PreUpdate, collision::update_prev_positions)
+        .add_system_to_stage(CoreStage::PreUpdate, collision::update_prev_params)
         .add_system(resize.with_run_criteria(resized))
         .add_system(ui::update_textboxes)
-        .add_system_set(SystemSet::on_enter(PlayState::Menu).with_system(ui::show_menu))
-        .add_system_set(SystemSet::on_update(PlayState::Menu).with_system(ui::update_play_button))
+        .add_system(status::tick_status_effects)
+        .add_system(debris::age_debris)
+        .add_system(hazard::move_hazards)
+        .add_system(status::update_status_icons)
+        .add_system(ricochet::update_bounce_counters)
+        .add_system(breakdown::toggle_breakdown)
+        .add_system(density::show_density)
+        .add_system(quality::track_frame_time)
+        .add_system(observer::observe_scores)
+        .add_system_set(
+            SystemSet::on_exit(PlayState::Menu).with_system(despawn_on_exit(PlayState::Menu)),
+        )
+        .add_system_set(
+            SystemSet::on_exit(PlayState::Load).with_system(despawn_on_exit(PlayState::Load)),
+        )
+        .add_system_set(
+            SystemSet::on_enter(PlayState::Menu)
+                .with_system(ui::show_menu)
+                .with_system(recap::clear_recap)
+                .with_system(snapshot::clear_snapshots)
+                .with_system(density::clear_density)
+                .with_system(demo::reset_idle)
+                .with_system(daily::update_daily_button_text),
+        )
+        .add_system_set(
+            SystemSet::on_update(PlayState::Menu)
+                .with_system(ui::update_play_button)
+                .with_system(stats::show_profiles)
+                .with_system(mods::show_mod_browser)
+                .with_system(rule_presets::show_rule_presets)
+                .with_system(demo::start_demo)
+                .with_system(loadout::update_loadout_buttons),
+        )
         .add_system_set(
             SystemSet::on_enter(PlayState::Load)
                 .with_system(load_field.label(Label::LoadField))
@@ -231,56 +444,162 @@ pub fn run() {
         .add_system_set(
             SystemSet::on_enter(PlayState::Enter)
                 .with_system(move_players.label(Label::MovePlayers))
-                .with_system(init_enter_functions.after(Label::MovePlayers)),
+                .with_system(init_enter_functions.after(Label::MovePlayers))
+                .with_system(practice::spawn_targets.after(Label::MovePlayers))
+                .with_system(snapshot::request_snapshot),
         )
         .add_system_set(
             SystemSet::on_update(PlayState::Enter)
                 .before(PhysicsSystems::StepWorld)
                 .with_system(ui::update_done_button.label(Label::DoneButton))
+                .with_system(ui::keyboard_fire.label(Label::DoneButton))
+                .with_system(preset::use_presets.before(Label::DoneButton))
+                .with_system(favorites::use_favorites.before(Label::DoneButton))
+                .with_system(library::show_function_library.before(Label::DoneButton))
+                .with_system(share::import_share_codes.before(Label::DoneButton))
+                .with_system(share::update_copy_code_button)
+                .with_system(coordinates::toggle_coordinate_mode.before(Label::DoneButton))
+                .with_system(coordinates::update_coordinate_labels)
+                .with_system(snapshot::take_snapshot)
+                .with_system(sketch::draw_sketch.before(Label::DoneButton))
+                .with_system(radial::use_radial_menu.before(Label::DoneButton))
+                .with_system(lock::use_target_lock.before(Label::DoneButton))
+                .with_system(tuning::show_sliders.after(Label::TypedShot).before(Label::DoneButton))
+                .with_system(tuning::update_preview.after(Label::DoneButton))
+                .with_system(demo::play_demo_turn.before(Label::DoneButton))
+                .with_system(assist::show_assist.before(Label::DoneButton))
+                .with_system(practice::place_target.before(Label::DoneButton))
+                .with_system(snapshot::undo_round.before(Label::DoneButton))
+                .with_system(preset::update_preset_tabs)
+                .with_system(recap::show_recap)
+                .with_system(charge::update_charge_sound)
+                .with_system(ui::update_ammo_text)
+                .with_system(ui::update_typed_shot.label(Label::TypedShot))
+                .with_system(ink::update_ink_text.after(Label::TypedShot))
+                .with_system(energy::update_energy_bar)
+                .with_system(complexity::update_complexity_meter.after(Label::TypedShot))
+                .with_system(domain::update_domain_warnings.after(Label::TypedShot))
+                .with_system(parse_error::show_parse_errors)
+                .with_system(hop::move_hops)
+                .with_system(elimination::skip_eliminated_turns)
+                .with_system(analysis::update_analysis)
                 .with_system(graph::send_functions.after(Label::DoneButton)),
         )
+        .add_system_set(
+            SystemSet::on_exit(PlayState::Enter)
+                .with_system(despawn_on_exit(PlayState::Enter))
+                .with_system(analysis::clear_analysis)
+                .with_system(tuning::clear_preview),
+        )
         .add_system_set(
             SystemSet::on_enter(PlayState::Fire)
                 .after(Label::AdvanceTurn)
-                .with_system(graph::fire_rockets),
+                .with_system(stats::record_shots.before(Label::FireRockets))
+                .with_system(matchlog::log_round_start.before(Label::FireRockets))
+                .with_system(observer::observe_round_start.before(Label::FireRockets))
+                .with_system(projectile::fire_rockets.label(Label::FireRockets)),
+        )
+        .add_system_set(
+            SystemSet::on_update(PlayState::Fire)
+                .with_system(show_winner)
+                .with_system(daily::finish_daily)
+                .with_system(stats::record_match)
+                .with_system(warning::add_intercept_warnings)
+                .with_system(warning::update_intercept_warnings),
         )
-        .add_system_set(SystemSet::on_update(PlayState::Fire).with_system(show_winner))
         .add_system_set(
             SystemSet::on_update(PlayState::Fire)
                 .before(PhysicsSystems::StepWorld)
-                .with_system(graph::move_rockets.label(Label::MoveRockets))
+                .with_system(projectile::move_projectiles.label(Label::MoveProjectiles))
+                .with_system(projectile::expire_projectiles.after(Label::MoveProjectiles))
+                .with_system(density::record_density.after(Label::MoveProjectiles))
+                .with_system(practice::track_targets.after(Label::MoveProjectiles))
+                .with_system(emp::use_emps)
+                .with_system(emp::expand_pulses)
+                .with_system(effects::fade_puffs)
+                .with_system(dilation::use_dilations.before(Label::MoveProjectiles))
+                .with_system(dilation::tick_dilations.after(Label::MoveProjectiles))
                 .with_system(ui::update_next_round_button.label(Label::AdvanceRoundButton))
-                .with_system(ui::advance_round.after(Label::AdvanceRoundButton)),
+                .with_system(demo::advance_demo_round.label(Label::AdvanceRoundButton))
+                .with_system(
+                    elimination::eliminate_players
+                        .label(Label::EliminatePlayers)
+                        .after(Label::AdvanceRoundButton),
+                )
+                .with_system(ui::advance_round.after(Label::EliminatePlayers))
+                .with_system(elimination::spectator_camera),
         )
         .add_system_set(
             SystemSet::on_update(PlayState::Fire)
                 .after(PhysicsSystems::StepWorld)
                 .with_system(collision::collect_balls.label(Label::CollectItems))
                 .with_system(effects::spawn_boom.after(Label::CollectItems))
-                .with_system(graph::stop_rocket_sounds.after(Label::CollectItems))
+                .with_system(effects::spawn_puffs.after(Label::CollectItems))
+                .with_system(debris::spawn_debris.after(Label::CollectItems))
+                .with_system(projectile::stop_projectile_sounds.after(Label::CollectItems))
+                .with_system(projectile::destroy_projectiles.after(Label::CollectItems))
+                .with_system(breakdown::annotate_flights.after(Label::CollectItems))
+                .with_system(recap::record_flights.after(Label::CollectItems))
+                .with_system(stats::record_rockets.after(Label::CollectItems))
+                .with_system(matchlog::log_ticks.after(Label::CollectItems))
+                .with_system(observer::observe_notes.after(Label::CollectItems))
                 .with_system(graph::graph_functions.after(Label::CollectItems))
                 .with_system(update_scores.after(Label::CollectItems)),
         )
-        .add_system_set(SystemSet::on_exit(PlayState::Fire).with_system(effects::remove_effects))
+        .add_system_set(
+            SystemSet::on_exit(PlayState::Fire)
+                .with_system(despawn_on_exit(PlayState::Fire))
+                .with_system(elimination::reset_spectator_camera)
+                .with_system(recap::finish_recap_flights)
+                .with_system(stats::clear_hit_rockets)
+                .with_system(matchlog::flush_log)
+                .with_system(practice::clear_approaches)
+                .with_system(projectile::despawn_projectiles),
+        )
         .add_system(ui::advance_turn.label(Label::AdvanceTurn).after(Label::CollectItems))
+        .add_system(ui::show_privacy_screen.after(Label::AdvanceTurn))
+        .add_system(ui::update_privacy_button)
+        .add_system(demo::stop_demo)
         .add_system_to_stage(CoreStage::PostUpdate, ui::assign_egui_ids)
         .add_system_to_stage(CoreStage::PostUpdate, ui::give_back_egui_ids)
         .run();
 }
 
-/// Z-indexes
-pub mod z {
-    pub const GRID: f32 = 0.0;
-    pub const GRID_TEXT: f32 = 1.0;
-    pub const GRAPH: f32 = 1.5;
-    pub const BOOM: f32 = 1.7;
-    pub const PLAYER: f32 = 2.0;
-    pub const BALL: f32 = 2.0;
-    pub const MINE: f32 = 3.0;
-    pub const ROCKET: f32 = 4.0;
-    pub const SCORE: f32 = 5.0;
-    pub const WINNER_BOX: f32 = 6.0;
-    pub const WINNER: f32 = 6.0;
+/// Layers of the field, from back to front. Each layer owns the z range from its base
+/// up to the next layer's base, so things that stack within a layer, like the parts of a sprite,
+/// go up from the base in steps of `RenderLayer::STEP`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderLayer {
+    Grid,
+    GridText,
+    /// The analysis heat map
+    Heat,
+    /// Practice targets
+    Target,
+    /// Trails and previews of shots
+    Graph,
+    /// Explosions and other short effects
+    Boom,
+    Player,
+    /// Balls and other things rockets can pick up
+    Pickup,
+    Mine,
+    Rocket,
+    /// Text over the field, like scores and status icons
+    Score,
+    /// Box behind the text at the end of a match
+    WinnerBox,
+    Winner,
+}
+
+impl RenderLayer {
+    /// Gap between things stacked within one layer. A layer has room for 100 of them.
+    pub const STEP: f32 = 0.01;
+
+    /// Base z of the layer
+    pub const fn z(self) -> f32 {
+        self as u32 as f32
+    }
 }
 
 /// Configuration for a field, containing player positions
@@ -288,19 +607,28 @@ pub mod z {
 struct FieldConfig {
     positions: Vec<Vec2>,
     item_region: RectRegion,
+    hazards: Vec<HazardConfig>,
 }
 
 #[rustfmt::skip]
 static FIELD_CONFIGS: Lazy<[FieldConfig; 5]> = Lazy::new(|| [
     FieldConfig {
         positions: vec![],
-        item_region: RectRegion::new(&[])
+        item_region: RectRegion::new(&[]),
+        hazards: vec![],
     },
 
-    // 1 player is invalid for now at least
+    // Only used for the daily challenge
     FieldConfig {
-        positions: vec![],
-        item_region: RectRegion::new(&[])
+        positions: vec![
+            [-0.75,  0.0 ].into(),
+        ],
+        item_region: RectRegion::new(&[
+            Rect { left: -0.875, right: -0.5,   bottom: -0.875, top: -0.25,  },
+            Rect { left: -0.875, right: -0.5,   bottom:  0.25,  top:  0.875, },
+            Rect { left: -0.5,   right:  0.875, bottom: -0.875, top:  0.875, },
+        ]),
+        hazards: vec![],
     },
 
     FieldConfig {
@@ -312,7 +640,16 @@ static FIELD_CONFIGS: Lazy<[FieldConfig; 5]> = Lazy::new(|| [
             Rect { left: -0.875, right:  0.5,   bottom: -0.875, top: -0.5,   },
             Rect { left: -0.5,   right:  0.875, bottom:  0.5,   top:  0.875, },
             Rect { left: -0.875, right:  0.875, bottom: -0.5,   top:  0.5,   },
-        ])
+        ]),
+        hazards: vec![
+            HazardConfig {
+                kind: HazardKind::Mine,
+                x: "0.3 * cos(tau * t)",
+                y: "0.3 * sin(tau * t)",
+                assigns: "",
+                period: 8.0,
+            },
+        ],
     },
 
     FieldConfig {
@@ -324,7 +661,8 @@ static FIELD_CONFIGS: Lazy<[FieldConfig; 5]> = Lazy::new(|| [
         item_region: RectRegion::new(&[
             Rect { left: -0.875, right: -0.5,   bottom: -0.5,   top:  0.5,   },
             Rect { left: -0.5,   right:  0.5,   bottom: -0.875, top:  0.875, },
-        ])
+        ]),
+        hazards: vec![],
     },
 
     FieldConfig {
@@ -340,11 +678,25 @@ static FIELD_CONFIGS: Lazy<[FieldConfig; 5]> = Lazy::new(|| [
             Rect { left: -0.5,   right:  0.5,   bottom: -0.875, top: -0.5,   },
             Rect { left: -0.5,   right:  0.5,   bottom:  0.5,   top:  0.875, },
             Rect { left: -0.5,   right:  0.5,   bottom: -0.5,   top:  0.5,   },
-        ])
+        ]),
+        hazards: vec![
+            HazardConfig {
+                kind: HazardKind::Spawner { interval: 2.0, max_balls: 5 },
+                x: "r * sin(tau * t)",
+                y: "r * sin(2 * tau * t)",
+                assigns: "r = 0.35",
+                period: 12.0,
+            },
+        ],
     },
 ]);
 
 fn seed_rng(mut pcg: ResMut<Pcg64>) {
+    reseed_rng(&mut pcg);
+}
+
+/// Seeds the RNG randomly
+pub(crate) fn reseed_rng(pcg: &mut Pcg64) {
     let mut rng = rand::thread_rng();
     let mut seed = [0u8; 32];
     seed[0..16].copy_from_slice(&Uniform::from(0..=u128::MAX).sample(&mut rng).to_le_bytes());
@@ -356,12 +708,14 @@ fn seed_rng(mut pcg: ResMut<Pcg64>) {
 pub fn load_field(
     mut commands: Commands,
     game: ResMut<Game>,
+    players: Res<Vec<Player>>,
     mut advance_round_events: EventWriter<AdvanceRound>,
     fonts: Res<Assets<Font>>,
     images: Res<Assets<Image>>,
 ) {
     const AXIS_THICKNESS: f32 = 0.04;
     const GRID_THICKNESS: f32 = 0.02;
+    const WALL_THICKNESS: f32 = 0.08;
     let cell_size = 1.0;
     let scale = game.scale;
 
@@ -371,26 +725,73 @@ pub fn load_field(
         camera.orthographic_projection.scale = scale;
         node.spawn_bundle(camera);
 
-        let axis = Sprite {
-            color: Color::rgb(0.0, 0.0, 0.0),
-            custom_size: Some(Vec2::new(2.0 * scale, AXIS_THICKNESS)),
-            ..Default::default()
-        };
+        let half_size = game.half_size();
         let rot_90 =
             Transform::from_matrix(Mat4::from_mat3(Mat3::from_mat2(Mat2::from_cols_array(&[
                 0.0, 1.0, -1.0, 0.0,
             ]))));
 
         // Axes
-        node.spawn_bundle(SpriteBundle { sprite: axis.clone(), ..Default::default() });
-        node.spawn_bundle(SpriteBundle { sprite: axis, transform: rot_90, ..Default::default() });
+        let axis = |length: f32| Sprite {
+            color: Color::rgb(0.0, 0.0, 0.0),
+            custom_size: Some(Vec2::new(length, AXIS_THICKNESS)),
+            ..Default::default()
+        };
+        node.spawn_bundle(SpriteBundle { sprite: axis(2.0 * half_size.x), ..Default::default() });
+        node.spawn_bundle(SpriteBundle {
+            sprite: axis(2.0 * half_size.y),
+            transform: rot_90,
+            ..Default::default()
+        });
+
+        // Walls around the field
+        let walls = [
+            (Vec2::new(0.0, half_size.y), Vec2::new(2.0 * half_size.x, WALL_THICKNESS)),
+            (Vec2::new(0.0, -half_size.y), Vec2::new(2.0 * half_size.x, WALL_THICKNESS)),
+            (Vec2::new(half_size.x, 0.0), Vec2::new(WALL_THICKNESS, 2.0 * half_size.y)),
+            (Vec2::new(-half_size.x, 0.0), Vec2::new(WALL_THICKNESS, 2.0 * half_size.y)),
+        ];
+        for (pos, size) in walls {
+            node.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.3, 0.3, 0.3),
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(pos.extend(RenderLayer::Grid.z())),
+                ..Default::default()
+            });
+        }
 
         // Grid
-        let grid_line = Sprite {
+        let grid_line = |length: f32| Sprite {
             color: Color::rgba(0.0, 0.0, 0.0, 0.25),
-            custom_size: Some(Vec2::new(2.0 * scale, GRID_THICKNESS)),
+            custom_size: Some(Vec2::new(length, GRID_THICKNESS)),
             ..Default::default()
         };
+        let grid_dists =
+            |max: f32| (1..).map(move |i| i as f32 * cell_size).take_while(move |d| *d < max);
+
+        // Lines along the x axis, then lines along the y axis
+        let line_sets = [
+            (half_size.y, 2.0 * half_size.x, Transform::identity()),
+            (half_size.x, 2.0 * half_size.y, rot_90),
+        ];
+        for (max_dist, length, rotation) in line_sets {
+            for dist in grid_dists(max_dist) {
+                for dir in [1.0, -1.0] {
+                    node.spawn_bundle(SpriteBundle {
+                        sprite: grid_line(length),
+                        transform: rotation.mul_transform(Transform::from_xyz(
+                            0.0,
+                            dist * dir,
+                            RenderLayer::Grid.z(),
+                        )),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
 
         let label_style =
             TextStyle { font: fonts.get_handle(asset::Font), color: Color::BLACK, font_size: 0.0 };
@@ -399,42 +800,30 @@ pub fn load_field(
         let label_alignment_y =
             TextAlignment { vertical: VerticalAlign::Center, horizontal: HorizontalAlign::Right };
 
-        for dist in (1..(scale / cell_size) as i32).map(|i| i as f32 * cell_size) {
-            let transforms = [
-                Transform::from_xyz(0.0, dist, z::GRID),
-                Transform::from_xyz(0.0, -dist, z::GRID),
-                rot_90.mul_transform(Transform::from_xyz(0.0, dist, z::GRID)),
-                rot_90.mul_transform(Transform::from_xyz(0.0, -dist, z::GRID)),
-            ];
-            for transform in transforms {
-                node.spawn_bundle(SpriteBundle {
-                    sprite: grid_line.clone(),
-                    transform,
-                    ..Default::default()
-                });
-            }
-
-            for dir in [1.0, -1.0] {
-                let dist = dist * dir;
-                let pairs = [
-                    (Transform::from_xyz(dist, -0.05, z::GRID_TEXT), label_alignment_x),
-                    (Transform::from_xyz(-0.05, dist, z::GRID_TEXT), label_alignment_y),
-                ];
-                for (transform, alignment) in pairs {
-                    node.spawn_bundle(Text2dBundle {
-                        text: Text::with_section(
-                            format!("{}", dist),
-                            label_style.clone(),
-                            alignment,
-                        ),
-                        transform,
-                        ..Default::default()
-                    })
-                    .insert(RelativeTextSize(0.2));
-                }
-            }
+        let labels = grid_dists(half_size.x)
+            .flat_map(|dist| [dist, -dist])
+            .map(|dist| {
+                (
+                    dist,
+                    Transform::from_xyz(dist, -0.05, RenderLayer::GridText.z()),
+                    label_alignment_x,
+                )
+            })
+            .chain(grid_dists(half_size.y).flat_map(|dist| [dist, -dist]).map(|dist| {
+                (
+                    dist,
+                    Transform::from_xyz(-0.05, dist, RenderLayer::GridText.z()),
+                    label_alignment_y,
+                )
+            }));
+        for (dist, transform, alignment) in labels {
+            node.spawn_bundle(Text2dBundle {
+                text: Text::with_section(format!("{}", dist), label_style.clone(), alignment),
+                transform,
+                ..Default::default()
+            })
+            .insert(RelativeTextSize(0.2));
         }
-
         let score_style =
             TextStyle { font: fonts.get_handle(asset::Font), color: Color::BLACK, font_size: 0.0 };
         let score_alignment =
@@ -442,21 +831,45 @@ pub fn load_field(
 
         let positions = &FIELD_CONFIGS[game.num_players() as usize].positions;
         for (i, pos) in positions.iter().enumerate() {
+            let mut effects = StatusEffects::default();
+            if players[i].loadout == Loadout::Shield {
+                // Lasts until it gets used
+                effects.add(StatusKind::Shield, u32::MAX);
+            }
+
             // Player icon
             node.spawn_bundle(SpriteBundle {
                 sprite: Sprite { custom_size: Some(Vec2::ONE), ..Default::default() },
                 texture: images.get_handle(asset::Player(i as u32)),
-                transform: Transform::from_translation((*pos * scale).extend(z::PLAYER))
-                    .with_scale(Vec3::from([0.4; 3])),
+                transform: Transform::from_translation(
+                    (*pos * half_size).extend(RenderLayer::Player.z()),
+                )
+                .with_scale(Vec3::from([0.4; 3])),
+                ..Default::default()
+            })
+            .insert(Owner(i as u32))
+            .insert(PlayerLabel)
+            .insert(LastShotEnd::default())
+            .insert(effects);
+
+            // Status effects
+            node.spawn_bundle(Text2dBundle {
+                text: Text::with_section("", score_style.clone(), score_alignment),
+                transform: Transform::from_translation(
+                    (*pos * half_size - Vec2::new(0.0, 0.35)).extend(RenderLayer::Score.z()),
+                ),
                 ..Default::default()
             })
+            .insert(RelativeTextSize(0.2))
             .insert(Owner(i as u32))
-            .insert(PlayerLabel);
+            .insert(StatusIcons);
 
             // Score
             node.spawn_bundle(Text2dBundle {
                 text: Text::with_section("0", score_style.clone(), score_alignment),
-                transform: Transform::from_translation((*pos * scale * 3.4 / 3.0).extend(z::SCORE)),
+                transform: Transform::from_translation(
+                    (*pos * half_size * 3.4 / 3.0).extend(RenderLayer::Score.z()),
+                ),
                 ..Default::default()
             })
             .insert(RelativeTextSize(0.4))
@@ -475,13 +888,13 @@ fn move_players(
 ) {
     let positions = &FIELD_CONFIGS[game.num_players() as usize].positions;
     for (owner, mut transform) in player_comps.iter_mut() {
-        transform.translation =
-            (positions[game.order_index(owner.0) as usize] * game.scale).extend(z::PLAYER);
+        transform.translation = (positions[game.order_index(owner.0) as usize] * game.half_size())
+            .extend(RenderLayer::Player.z());
     }
     for (owner, mut transform) in scores.iter_mut() {
-        transform.translation = (positions[game.order_index(owner.0) as usize] * game.scale * 3.4
-            / 3.0)
-            .extend(z::PLAYER);
+        transform.translation =
+            (positions[game.order_index(owner.0) as usize] * game.half_size() * 3.4 / 3.0)
+                .extend(RenderLayer::Player.z());
     }
 }
 
@@ -520,6 +933,34 @@ const ITEM_MINE: ItemParams = ItemParams {
     interaction_layers: CollisionGroups::MINE,
 };
 
+const ITEM_DEBRIS: ItemParams = ItemParams {
+    color: Color::rgb(0.3, 0.3, 0.3),
+    texture: TexFn::Asset(asset::Boom),
+    scale_multiplier: 1.5,
+    interaction_layers: CollisionGroups::OBSTACLE,
+};
+
+const ITEM_RICOCHET: ItemParams = ItemParams {
+    color: Color::rgb(0.3, 0.8, 1.0),
+    texture: TexFn::Asset(asset::Ball),
+    scale_multiplier: 1.375,
+    interaction_layers: CollisionGroups::PICKUP,
+};
+
+const ITEM_EMP: ItemParams = ItemParams {
+    color: Color::rgb(1.0, 0.9, 0.2),
+    texture: TexFn::Asset(asset::Ball),
+    scale_multiplier: 1.375,
+    interaction_layers: CollisionGroups::PICKUP,
+};
+
+const ITEM_DILATION: ItemParams = ItemParams {
+    color: Color::rgb(0.7, 0.3, 1.0),
+    texture: TexFn::Asset(asset::Ball),
+    scale_multiplier: 1.375,
+    interaction_layers: CollisionGroups::PICKUP,
+};
+
 const ITEM_PLAYER_BALL: ItemParams = ItemParams {
     color: Color::rgb(0.8, 0.8, 0.8),
     texture: TexFn::AssetU32(asset::Player),
@@ -527,13 +968,71 @@ const ITEM_PLAYER_BALL: ItemParams = ItemParams {
     interaction_layers: CollisionGroups::BALL,
 };
 
+/// Spawns a ball, mine, or some other item
+fn spawn_item<'a, 'w, 's, 'b>(
+    node: &'b mut ChildBuilder<'w, 's, 'a>,
+    images: &Res<Assets<Image>>,
+    point: Vec3,
+    item_params: &ItemParams,
+    param: u32,
+) -> EntityCommands<'w, 's, 'b> {
+    let scale = 0.3;
+
+    let mut entity_commands = node.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: item_params.color,
+            custom_size: Some(Vec2::ONE),
+            ..Default::default()
+        },
+        texture: images.get_handle(item_params.texture.call(param)),
+        transform: Transform::from_translation(point)
+            .with_scale(Vec3::from([scale * item_params.scale_multiplier; 3])),
+        ..Default::default()
+    });
+    entity_commands
+        .insert_bundle(RigidBodyBundle {
+            body_type: RigidBodyType::Static.into(),
+            position: point.xy().extend(0.0).into(),
+            ..Default::default()
+        })
+        .with_children(|body| {
+            body.spawn_bundle(ColliderBundle {
+                shape: ColliderShape::ball(scale / 2.0).into(),
+                collider_type: ColliderType::Sensor.into(),
+                position: Vec2::ZERO.into(),
+                flags: ColliderFlags {
+                    collision_groups: InteractionGroups::new(
+                        item_params.interaction_layers.bits(),
+                        CollisionGroups::ROCKET_CAST.bits(),
+                    ),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            });
+        });
+    entity_commands
+}
+
 fn init_enter_functions(
     mut commands: Commands,
     mut rng: ResMut<Pcg64>,
     images: Res<Assets<Image>>,
     game: Res<Game>,
+    rules: Res<GameRules>,
     players: Res<Vec<Player>>,
-    items: Query<Entity, Or<(With<Ball>, With<Mine>, With<Graph>)>>,
+    items: Query<
+        Entity,
+        Or<(
+            With<Ball>,
+            With<Mine>,
+            With<Ricochet>,
+            With<EmpPickup>,
+            With<DilationPickup>,
+            With<Graph>,
+            With<Hazard>,
+        )>,
+    >,
     field: Query<Entity, With<Field>>,
 ) {
     for entity in items.iter() {
@@ -541,72 +1040,71 @@ fn init_enter_functions(
     }
 
     let item_region = &FIELD_CONFIGS[game.num_players() as usize].item_region;
-    let item_distribution = item_region.scaled(game.scale);
-
-    fn spawn_item<'a, 'w, 's, 'b>(
-        node: &'b mut ChildBuilder<'w, 's, 'a>,
-        images: &Res<Assets<Image>>,
-        point: Vec3,
-        item_params: &ItemParams,
-        param: u32,
-    ) -> EntityCommands<'w, 's, 'b> {
-        let scale = 0.3;
-
-        let mut entity_commands = node.spawn_bundle(SpriteBundle {
-            sprite: Sprite {
-                color: item_params.color,
-                custom_size: Some(Vec2::ONE),
-                ..Default::default()
-            },
-            texture: images.get_handle(item_params.texture.call(param)),
-            transform: Transform::from_translation(point)
-                .with_scale(Vec3::from([scale * item_params.scale_multiplier; 3])),
-            ..Default::default()
-        });
-        entity_commands
-            .insert_bundle(RigidBodyBundle {
-                body_type: RigidBodyType::Static.into(),
-                position: point.xy().extend(0.0).into(),
-                ..Default::default()
-            })
-            .with_children(|body| {
-                body.spawn_bundle(ColliderBundle {
-                    shape: ColliderShape::ball(scale / 2.0).into(),
-                    collider_type: ColliderType::Sensor.into(),
-                    position: Vec2::ZERO.into(),
-                    flags: ColliderFlags {
-                        collision_groups: InteractionGroups::new(
-                            item_params.interaction_layers.bits(),
-                            CollisionGroups::ROCKET_CAST.bits(),
-                        ),
-                        ..Default::default()
-                    }
-                    .into(),
-                    ..Default::default()
-                });
-            });
-        entity_commands
-    }
+    let item_distribution = item_region.scaled(game.half_size());
 
     commands.entity(field.single()).with_children(|node| {
         if game.is_on_destruction_round() {
-            for (i, player) in players.iter().enumerate() {
+            for (i, player) in players.iter().enumerate().filter(|(_, p)| !p.eliminated) {
                 let points = (&item_distribution).sample_iter(&mut *rng);
                 for point in points.take(player.num_balls as usize) {
-                    spawn_item(node, &images, point.extend(z::BALL), &ITEM_PLAYER_BALL, i as u32)
-                        .insert(Owner(i as u32))
-                        .insert(Ball);
+                    spawn_item(
+                        node,
+                        &images,
+                        point.extend(RenderLayer::Pickup.z()),
+                        &ITEM_PLAYER_BALL,
+                        i as u32,
+                    )
+                    .insert(Owner(i as u32))
+                    .insert(Ball);
                 }
             }
         } else {
             let points = (&item_distribution).sample_iter(&mut *rng);
-            for point in points.take(85) {
-                spawn_item(node, &images, point.extend(z::BALL), &ITEM_BALL, 0).insert(Ball);
+            for point in points.take(NUM_BALLS) {
+                spawn_item(node, &images, point.extend(RenderLayer::Pickup.z()), &ITEM_BALL, 0)
+                    .insert(Ball);
             }
             let points = (&item_distribution).sample_iter(&mut *rng);
-            for point in points.take(15) {
-                spawn_item(node, &images, point.extend(z::MINE), &ITEM_MINE, 0).insert(Mine);
+            for point in points.take(NUM_MINES) {
+                spawn_item(node, &images, point.extend(RenderLayer::Mine.z()), &ITEM_MINE, 0)
+                    .insert(Mine);
             }
+            if rules.ricochet_bounces.is_some() {
+                let points = (&item_distribution).sample_iter(&mut *rng);
+                for point in points.take(NUM_RICOCHET_PICKUPS) {
+                    spawn_item(
+                        node,
+                        &images,
+                        point.extend(RenderLayer::Pickup.z()),
+                        &ITEM_RICOCHET,
+                        0,
+                    )
+                    .insert(Ricochet);
+                }
+            }
+            if rules.emp {
+                let points = (&item_distribution).sample_iter(&mut *rng);
+                for point in points.take(NUM_EMP_PICKUPS) {
+                    spawn_item(node, &images, point.extend(RenderLayer::Pickup.z()), &ITEM_EMP, 0)
+                        .insert(EmpPickup);
+                }
+            }
+            if rules.time_dilation {
+                let points = (&item_distribution).sample_iter(&mut *rng);
+                for point in points.take(NUM_DILATION_PICKUPS) {
+                    spawn_item(
+                        node,
+                        &images,
+                        point.extend(RenderLayer::Pickup.z()),
+                        &ITEM_DILATION,
+                        0,
+                    )
+                    .insert(DilationPickup);
+                }
+            }
+
+            let hazards = &FIELD_CONFIGS[game.num_players() as usize].hazards;
+            hazard::spawn_hazards(node, &images, hazards, game.half_size());
         }
     });
 }
@@ -619,14 +1117,15 @@ fn update_scores(mut scores: Query<(&mut Text, &Owner), With<Score>>, players: R
 
 fn show_winner(
     fonts: Res<Assets<Font>>,
-    rockets: Query<&Rocket>,
+    projectiles: Query<&Projectile>,
     game: Res<Game>,
+    rules: Res<GameRules>,
     players: Res<Vec<Player>>,
     winner_box: Query<&WinnerBox>,
     field: Query<Entity, With<Field>>,
     mut commands: Commands,
 ) {
-    if rockets.iter().next().is_some()
+    if projectiles.iter().next().is_some()
         || winner_box.iter().next().is_some()
         || !game.is_on_destruction_round()
     {
@@ -637,22 +1136,32 @@ fn show_winner(
         node.spawn_bundle(SpriteBundle {
             sprite: Sprite {
                 color: Color::rgba(0.0, 0.0, 0.0, 0.5),
-                custom_size: Some(Vec2::new(1.0, 0.35) * game.scale),
+                custom_size: Some(
+                    Vec2::new(1.0, if rules.style_score { 0.5 } else { 0.35 }) * game.scale,
+                ),
                 ..Default::default()
             },
-            transform: Transform::from_xyz(0.0, 0.0, z::WINNER_BOX),
+            transform: Transform::from_xyz(0.0, 0.0, RenderLayer::WinnerBox.z()),
             ..Default::default()
         })
         .insert(WinnerBox);
 
-        let max_score = players.iter().map(|p| p.num_balls).max().unwrap();
-        let mut winner_text = players
-            .iter()
-            .enumerate()
+        let alive = players.iter().enumerate().filter(|(_, p)| !p.eliminated);
+        let max_score = alive.clone().map(|(_, p)| p.num_balls).max().unwrap();
+        let mut winner_text = alive
             .filter_map(|(i, p)| (p.num_balls == max_score).then(|| format!("P{}, ", i + 1)))
             .collect::<String>();
         winner_text = format!("Winners:\n{}", winner_text);
         winner_text.truncate(winner_text.len() - 2); // Remove final ", "
+        if rules.style_score {
+            let style_text = players
+                .iter()
+                .enumerate()
+                .map(|(i, p)| format!("P{} {}", i + 1, p.style_points))
+                .collect::<Vec<_>>()
+                .join(", ");
+            winner_text = format!("{}\nStyle: {}", winner_text, style_text);
+        }
 
         node.spawn_bundle(Text2dBundle {
             text: Text::with_section(
@@ -667,7 +1176,7 @@ fn show_winner(
                     vertical: VerticalAlign::Center,
                 },
             ),
-            transform: Transform::from_xyz(0.0, 0.0, z::WINNER),
+            transform: Transform::from_xyz(0.0, 0.0, RenderLayer::Winner.z()),
             ..Default::default()
         })
         .insert(RelativeTextSize(0.5));
@@ -694,9 +1203,13 @@ fn resized(
 struct PrevWindowSize([f32; 2]);
 
 fn resize(
+    game: Res<Game>,
     mut query: Query<(&mut Text, &mut Transform, &RelativeTextSize, Without<Node>)>,
     mut graph_node: Query<(&mut Style, With<ui::GraphNode>)>,
-    mut camera: Query<&mut OrthographicProjection, Without<ui::UiCamera>>,
+    mut camera: Query<
+        &mut OrthographicProjection,
+        (Without<ui::UiCamera>, Without<projector::ProjectorCamera>),
+    >,
     windows: Res<Windows>,
     mut prev_height: ResMut<PrevWindowSize>,
 ) {
@@ -708,14 +1221,15 @@ fn resize(
     //windows.get_primary_mut().unwrap().set_scale_factor_override(Some(height as f64 / 720.0));
 
     for (mut style, _) in graph_node.iter_mut() {
-        style.flex_basis = Val::Px(height);
+        style.flex_basis = Val::Px(height * game.aspect);
     }
 
     let mut camera = if let Ok(camera) = camera.get_single_mut() { camera } else { return };
 
     let scale = camera.scale;
-    camera.left = 1.0 - 2.0 * aspect_ratio;
-    camera.right = 1.0;
+    // The field is on the right side of the window, with its height filling the window
+    camera.left = game.aspect - 2.0 * aspect_ratio;
+    camera.right = game.aspect;
     camera.top = 1.0;
     camera.bottom = -1.0;
 
@@ -0,0 +1,134 @@
+//! A library of named curves that can be picked from a dropdown above the textboxes,
+//! which fills in their functions. Natively, the library is read from `assets/library.txt`,
+//! in the format `parse_library` reads, so new curves don't need code.
+//! On the web, the library that ships with the game is built in.
+
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use egui::Align2;
+
+use crate::ui::{
+    entry_box_index, ButtonsEnabled, FunctionEntryBox, FunctionX, FunctionY, Textbox,
+    TextboxesEditable,
+};
+
+/// File with the library, inside the asset folder
+pub const LIBRARY_FILE: &str = "library.txt";
+
+/// A named curve, with the text of x(t), y(t), and the assignments
+#[derive(Clone, Debug)]
+pub struct FunctionPreset {
+    pub name: String,
+    pub functions: [String; 3],
+}
+
+/// The curves in the library
+#[derive(Debug, Default)]
+pub struct FunctionLibrary(pub Vec<FunctionPreset>);
+
+/// Labels the spot above the textboxes where the library dropdown goes
+#[derive(Component)]
+pub struct LibrarySpot;
+
+/// Parses a library. Each line is `key = value`, and each curve starts with its `name`,
+/// followed by `x`, `y`, and any number of `where` lines, one per assignment.
+/// Empty lines and lines starting with `#` are skipped.
+pub fn parse_library(text: &str) -> Result<Vec<FunctionPreset>, String> {
+    let mut presets: Vec<FunctionPreset> = vec![];
+    for (i, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) =
+            line.split_once('=').ok_or_else(|| format!("line {}: expected `=`", i))?;
+        let (key, value) = (key.trim(), value.trim());
+        if key == "name" {
+            presets.push(FunctionPreset { name: value.to_owned(), functions: Default::default() });
+            continue;
+        }
+
+        let preset = presets.last_mut().ok_or_else(|| format!("line {}: expected `name`", i))?;
+        match key {
+            "x" => preset.functions[0] = value.to_owned(),
+            "y" => preset.functions[1] = value.to_owned(),
+            "where" => {
+                if !preset.functions[2].is_empty() {
+                    preset.functions[2].push('\n');
+                }
+                preset.functions[2].push_str(value);
+            }
+            _ => return Err(format!("line {}: unknown key `{}`", i, key)),
+        }
+    }
+    Ok(presets)
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn read_library() -> Option<String> {
+    std::fs::read_to_string(std::path::Path::new("assets").join(LIBRARY_FILE)).ok()
+}
+
+#[cfg(target_family = "wasm")]
+fn read_library() -> Option<String> {
+    Some(include_str!("../assets/library.txt").to_owned())
+}
+
+/// Reads the library, which is empty if it's missing or broken
+pub fn load_function_library(mut commands: Commands) {
+    let presets = match read_library().as_deref().map(parse_library) {
+        Some(Ok(presets)) => presets,
+        Some(Err(err)) => {
+            log::warn!("Skipped function library: {}", err);
+            vec![]
+        }
+        None => vec![],
+    };
+    commands.insert_resource(FunctionLibrary(presets));
+}
+
+/// Shows the library dropdown, and fills in the textboxes with the curve picked from it
+pub fn show_function_library(
+    mut egui_ctx: ResMut<EguiContext>,
+    library: Res<FunctionLibrary>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    textboxes_editable: Res<TextboxesEditable>,
+    spot: Query<(&Node, &GlobalTransform), With<LibrarySpot>>,
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+) {
+    if library.0.is_empty() || !buttons_enabled.0 || !textboxes_editable.0 {
+        return;
+    }
+    let (size, transform) = spot.single();
+    if size.size.x == 0.0 && size.size.y == 0.0 {
+        return;
+    }
+
+    let left = transform.translation.x - size.size.x / 2.0;
+    let bottom = transform.translation.y - size.size.y / 2.0;
+    let mut picked = None;
+    egui::Area::new("function library").anchor(Align2::LEFT_BOTTOM, [left, -bottom]).show(
+        egui_ctx.ctx_mut(),
+        |ui| {
+            egui::ComboBox::from_id_source("function library")
+                .selected_text("Library")
+                .width(size.size.x)
+                .show_ui(ui, |ui| {
+                    for (index, preset) in library.0.iter().enumerate() {
+                        if ui.selectable_label(false, &preset.name).clicked() {
+                            picked = Some(index);
+                        }
+                    }
+                });
+        },
+    );
+
+    if let Some(picked) = picked {
+        let functions = &library.0[picked].functions;
+        for (mut textbox, x, y) in entry_boxes.iter_mut() {
+            textbox.text = functions[entry_box_index(x, y)].clone();
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use crate::{projectile::ROCKET_TIME, storage};
+
+/// Flight time added to every rocket of a player with the long flight loadout, in seconds
+pub const LONG_FLIGHT_BONUS: f32 = 1.0;
+
+/// Something a player starts a match with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Loadout {
+    None,
+    /// A shield stack that lasts until it gets used
+    Shield,
+    /// One extra rocket over the limit, once per match
+    MultiShot,
+    /// Rockets fly longer, following their curve further
+    LongFlight,
+}
+
+impl Default for Loadout {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl Loadout {
+    const ALL: [Self; 4] = [Self::None, Self::Shield, Self::MultiShot, Self::LongFlight];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Shield => "Shield",
+            Self::MultiShot => "Multi-shot",
+            Self::LongFlight => "+1s flight",
+        }
+    }
+
+    /// The loadout after this one, for cycling through them
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|l| *l == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Factor the time range of a rocket's curve and its flight time get multiplied by
+    pub fn flight_factor(self) -> f32 {
+        match self {
+            Self::LongFlight => (ROCKET_TIME + LONG_FLIGHT_BONUS) / ROCKET_TIME,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Settings a player picks at match setup, which are remembered between sessions.
+/// This is meant to be contained in a vec, one for each player slot.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlayerConfig {
+    pub loadout: Loadout,
+}
+
+impl PlayerConfig {
+    fn key(player_index: u32) -> String {
+        format!("player{}", player_index + 1)
+    }
+
+    pub fn load(player_index: u32) -> Self {
+        let loadout = storage::load(&Self::key(player_index))
+            .and_then(|s| Loadout::ALL.into_iter().find(|l| l.name() == s.trim()))
+            .unwrap_or_default();
+        Self { loadout }
+    }
+
+    pub fn save(&self, player_index: u32) {
+        storage::save(&Self::key(player_index), self.loadout.name());
+    }
+}
+
+/// Configs of all the player slots
+pub fn load_player_configs() -> Vec<PlayerConfig> {
+    (0..4).map(PlayerConfig::load).collect()
+}
+
+/// Button that cycles through the loadouts of a player slot
+#[derive(Component)]
+pub struct LoadoutButton(pub u32);
+
+/// Labels the text of a loadout button
+#[derive(Component)]
+pub struct LoadoutText(pub u32);
+
+fn loadout_text(player_index: u32, config: &PlayerConfig) -> String {
+    format!("P{}: {}", player_index + 1, config.loadout.name())
+}
+
+pub fn update_loadout_buttons(
+    buttons: Query<(&Interaction, &LoadoutButton), Changed<Interaction>>,
+    mut texts: Query<(&mut Text, &LoadoutText)>,
+    mut configs: ResMut<Vec<PlayerConfig>>,
+) {
+    // Loadout buttons are always enabled when they exist.
+    for (interaction, button) in buttons.iter() {
+        if *interaction == Interaction::Clicked {
+            let config = &mut configs[button.0 as usize];
+            config.loadout = config.loadout.next();
+            config.save(button.0);
+        }
+    }
+
+    for (mut text, LoadoutText(player_index)) in texts.iter_mut() {
+        let value = loadout_text(*player_index, &configs[*player_index as usize]);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use decorum::Total;
+
+use crate::{
+    analysis::{sample_path, CONTACT_DIST, PATH_SAMPLES},
+    core::lang::{Function, Parametric},
+    graph::position_env,
+    projectile::{LastShotEnd, MotionModel},
+    projector::ProjectorCamera,
+    sketch::{cursor_on_field, solve},
+    ui::{
+        entry_box_index, ButtonsEnabled, FunctionEntryBox, FunctionStatus, FunctionWhere,
+        FunctionX, FunctionY, Textbox, TextboxesEditable, UiCamera,
+    },
+    Game, Owner, Player, PlayerLabel,
+};
+
+/// Number of target locks each player gets per match
+pub const LOCKS_PER_MATCH: u32 = 3;
+/// Most Levenberg-Marquardt steps taken per lock
+const MAX_STEPS: usize = 50;
+/// Step used to estimate how the path changes with each coefficient
+const DIFF_STEP: f64 = 1e-3;
+
+/// Indexes of the 'where' assignments that are just a number, like `a = 2`.
+/// These are the coefficients a target lock can change.
+pub(crate) fn free_coefficients(parametric: &Parametric) -> Vec<(usize, f64)> {
+    parametric
+        .assigns
+        .iter()
+        .take(parametric.where_assigns)
+        .enumerate()
+        .filter_map(|(i, f)| match f {
+            Function::Const(c) => Some((i, *c)),
+            Function::Neg(f) => match **f {
+                Function::Const(c) => Some((i, -c)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+fn with_coefficients(parametric: &Parametric, free: &[(usize, f64)], values: &[f64]) -> Parametric {
+    let mut parametric = parametric.clone();
+    for ((index, _), value) in free.iter().zip(values) {
+        parametric.assigns[*index] = Function::Const(*value);
+    }
+    parametric
+}
+
+/// Parameter and distance of the point on a path closest to the target
+fn closest_approach(motion: &MotionModel, target: Vec2) -> Option<(f32, f32)> {
+    sample_path(motion)
+        .iter()
+        .enumerate()
+        .map(|(i, pos)| (i as f32 / PATH_SAMPLES as f32, pos.distance(target)))
+        .min_by_key(|(_, dist)| Total::from(*dist))
+}
+
+/// Adjusts the free coefficients of a shot by damped least squares
+/// so its path passes within contact distance of the target.
+/// Returns the new value of each free coefficient, or `None` if it couldn't get close enough.
+pub fn lock_on(
+    parametric: &Parametric,
+    free: &[(usize, f64)],
+    start: Vec2,
+    target: Vec2,
+) -> Option<Vec<f64>> {
+    let motion = |values: &[f64]| {
+        MotionModel::parametric(with_coefficients(parametric, free, values), start)
+    };
+
+    let mut values = free.iter().map(|(_, c)| *c).collect::<Vec<_>>();
+    let (mut t, mut dist) = closest_approach(&motion(&values), target)?;
+    let mut damping = 1e-3;
+
+    for _ in 0..MAX_STEPS {
+        if dist < CONTACT_DIST {
+            return Some(values);
+        }
+
+        let pos = motion(&values).position(t);
+        let residual = [(pos.x - target.x) as f64, (pos.y - target.y) as f64];
+        let columns = (0..values.len())
+            .map(|k| {
+                let mut bumped = values.clone();
+                bumped[k] += DIFF_STEP;
+                let diff = motion(&bumped).position(t) - pos;
+                [diff.x as f64 / DIFF_STEP, diff.y as f64 / DIFF_STEP]
+            })
+            .collect::<Vec<_>>();
+
+        let n = values.len();
+        let a = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        let dot = columns[i][0] * columns[j][0] + columns[i][1] * columns[j][1];
+                        if i == j {
+                            dot + damping
+                        } else {
+                            dot
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let b = columns.iter().map(|c| -(c[0] * residual[0] + c[1] * residual[1])).collect();
+        let step = solve(a, b)?;
+
+        let candidate = values.iter().zip(&step).map(|(v, s)| v + s).collect::<Vec<_>>();
+        match closest_approach(&motion(&candidate), target) {
+            Some((new_t, new_dist))
+                if new_dist < dist && candidate.iter().all(|c| c.is_finite()) =>
+            {
+                values = candidate;
+                t = new_t;
+                dist = new_dist;
+                damping /= 10.0;
+            }
+            _ => damping *= 10.0,
+        }
+    }
+
+    (dist < CONTACT_DIST).then(|| values)
+}
+
+/// Names assigned to in the 'where' textbox, in order
+pub(crate) fn assign_names(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split('=').next().unwrap().trim().to_owned())
+        .collect()
+}
+
+/// Rewrites the 'where' textbox with new values for the assignments at some indexes
+pub(crate) fn rewrite_assigns(source: &str, free: &[(usize, f64)], values: &[f64]) -> String {
+    let mut lines = source.lines().map(str::to_owned).collect::<Vec<_>>();
+    // Blank lines don't count as assignments
+    let assign_lines = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    for ((index, _), value) in free.iter().zip(values) {
+        let line = &mut lines[assign_lines[*index]];
+        let name = line.split('=').next().unwrap().trim().to_owned();
+        *line = format!("{} = {:.3}", name, value);
+    }
+    lines.join("\n")
+}
+
+/// Ctrl+clicking the field adjusts the numbers assigned in the 'where' textbox
+/// so the current player's shot passes through the clicked point.
+/// Each player can only do this a few times per match.
+pub fn use_target_lock(
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    camera: Query<
+        (&GlobalTransform, &OrthographicProjection),
+        (Without<UiCamera>, Without<ProjectorCamera>),
+    >,
+    game: Res<Game>,
+    mut players: ResMut<Vec<Player>>,
+    player_comps: Query<(Entity, &Owner, &Transform, &LastShotEnd), With<PlayerLabel>>,
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>, Option<&FunctionWhere>),
+        With<FunctionEntryBox>,
+    >,
+    mut status: Query<&mut Text, With<FunctionStatus>>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    textboxes_editable: Res<TextboxesEditable>,
+) {
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    if !buttons_enabled.0
+        || !textboxes_editable.0
+        || !ctrl
+        || !mouse.just_pressed(MouseButton::Left)
+    {
+        return;
+    }
+    let target = if let Some(pos) = cursor_on_field(&windows, &camera) { pos } else { return };
+    if target.abs().cmpgt(game.half_size()).any() {
+        return;
+    }
+
+    let player_index = game.player_turn();
+    let start = if let Some((_, _, transform, _)) =
+        player_comps.iter().find(|(_, o, ..)| o.0 == player_index)
+    {
+        transform.translation.xy()
+    } else {
+        return;
+    };
+
+    let mut status = status.single_mut();
+    let mut set_status = |message: String, color: Color| {
+        status.sections[0].value = message;
+        status.sections[0].style.color = color;
+    };
+
+    let env = position_env(player_index, &players, &player_comps);
+    let player = &mut players[player_index as usize];
+    if player.target_locks == 0 {
+        set_status("No target locks left this match\n".into(), Color::MAROON);
+        return;
+    }
+
+    let mut functions: [String; 3] = Default::default();
+    for (textbox, x, y, _) in entry_boxes.iter() {
+        functions[entry_box_index(x, y)] = textbox.text.clone();
+    }
+    let parametric =
+        match player.coordinate_mode.parse(&functions[0], &functions[1], &functions[2], &env) {
+            Ok(parametric) => parametric,
+            Err(error) => {
+                set_status(format!("{}\n", error), Color::MAROON);
+                return;
+            }
+        };
+    let free = free_coefficients(&parametric);
+    if free.is_empty() {
+        set_status(
+            "Target lock needs a number assigned in 'where', like `a = 1`\n".into(),
+            Color::MAROON,
+        );
+        return;
+    }
+
+    if let Some(values) = lock_on(&parametric, &free, start, target) {
+        for (mut textbox, _, _, assigns) in entry_boxes.iter_mut() {
+            if assigns.is_some() {
+                textbox.text = rewrite_assigns(&functions[2], &free, &values);
+            }
+        }
+        player.target_locks -= 1;
+        set_status(
+            format!("Locked on target ({} locks left)\n", player.target_locks),
+            Color::DARK_GREEN,
+        );
+    } else {
+        set_status("Couldn't lock on that target\n".into(), Color::MAROON);
+    }
+}
@@ -0,0 +1,155 @@
+//! Optional log of what a match simulates, one line per event, for finding where two runs
+//! of the same match stop agreeing. Natively, setting the `GRAPH_WAR_MATCH_LOG` environment
+//! variable to a file path turns it on. The `logdiff` binary compares two logs.
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use rand::Rng;
+use rand_pcg::Pcg64;
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use crate::{
+    breakdown::FlightNote, projectile::SamplePath, Ball, Game, Mine, Owner, Player, PlayerLabel,
+};
+
+/// Environment variable holding the path of the match log
+pub const MATCH_LOG_VAR: &str = "GRAPH_WAR_MATCH_LOG";
+
+/// Where the match log goes, if it's on
+#[derive(Default)]
+pub struct MatchLog {
+    writer: Option<BufWriter<File>>,
+    /// Frames simulated while rockets fly, over the whole match
+    tick: u64,
+    /// Id the next rocket gets in the log
+    next_id: u32,
+}
+
+impl MatchLog {
+    /// Opens the log file named by the environment variable, if it's set
+    pub fn from_env() -> Self {
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(path) = std::env::var_os(MATCH_LOG_VAR) {
+            match File::create(&path) {
+                Ok(file) => return Self { writer: Some(BufWriter::new(file)), ..Self::default() },
+                Err(err) => log::warn!("Failed to open match log {:?}: {}", path, err),
+            }
+        }
+        Self::default()
+    }
+
+    fn line(&mut self, args: fmt::Arguments) {
+        if let Some(writer) = &mut self.writer {
+            if let Err(err) = writeln!(writer, "{}", args) {
+                log::warn!("Failed to write match log: {}", err);
+                self.writer = None;
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(writer) = &mut self.writer {
+            writer.flush().ok();
+        }
+    }
+}
+
+/// Id of a rocket in the match log. Entity ids can differ between runs, so these count up instead.
+#[derive(Clone, Copy, Debug, Component)]
+pub struct LogId(pub u32);
+
+/// Logs the state a round of firing starts from: the random number generator, players, items, and shots.
+/// Has to run before the rockets get fired, since that takes the shots out of the players.
+pub fn log_round_start(
+    mut match_log: ResMut<MatchLog>,
+    game: Res<Game>,
+    rng: Res<Pcg64>,
+    players: Res<Vec<Player>>,
+    player_comps: Query<(&Owner, &Transform), With<PlayerLabel>>,
+    balls: Query<&Transform, With<Ball>>,
+    mines: Query<&Transform, With<Mine>>,
+) {
+    if match_log.writer.is_none() {
+        return;
+    }
+
+    match_log.line(format_args!("round {}", game.round_index));
+    // The generator doesn't show its state, so a draw from a copy of it stands in for the state
+    match_log.line(format_args!("rng {:016x}", rng.clone().gen::<u64>()));
+    let mut positions = player_comps
+        .iter()
+        .map(|(owner, transform)| (owner.0, transform.translation.xy()))
+        .collect::<Vec<_>>();
+    positions.sort_by_key(|(index, _)| *index);
+    for (index, pos) in positions {
+        match_log.line(format_args!("player {} {} {}", index, pos.x, pos.y));
+    }
+    for transform in balls.iter() {
+        match_log
+            .line(format_args!("ball {} {}", transform.translation.x, transform.translation.y));
+    }
+    for transform in mines.iter() {
+        match_log
+            .line(format_args!("mine {} {}", transform.translation.x, transform.translation.y));
+    }
+    for (index, player) in players.iter().enumerate() {
+        for parametric in &player.parametrics {
+            let source = |s: &Option<String>| s.as_deref().unwrap_or("").replace('\n', "; ");
+            match_log.line(format_args!(
+                "shot {} seed {:016x} {} | {} | {}",
+                index,
+                parametric.seed,
+                source(&parametric.source_x),
+                source(&parametric.source_y),
+                source(&parametric.source_assigns)
+            ));
+        }
+    }
+}
+
+/// Logs every rocket's position each frame, along with what happens to rockets
+pub fn log_ticks(
+    mut commands: Commands,
+    mut match_log: ResMut<MatchLog>,
+    new_rockets: Query<(Entity, &Owner), Added<SamplePath>>,
+    rockets: Query<(&LogId, &Transform)>,
+    mut notes: EventReader<FlightNote>,
+) {
+    if match_log.writer.is_none() {
+        return;
+    }
+
+    match_log.tick += 1;
+    let tick = match_log.tick;
+    for (entity, owner) in new_rockets.iter() {
+        let id = match_log.next_id;
+        match_log.next_id += 1;
+        commands.entity(entity).insert(LogId(id));
+        match_log.line(format_args!("tick {} spawn {} owner {}", tick, id, owner.0));
+    }
+
+    let mut positions = rockets
+        .iter()
+        .map(|(id, transform)| (id.0, transform.translation.xy()))
+        .collect::<Vec<_>>();
+    positions.sort_by_key(|(id, _)| *id);
+    for (id, pos) in positions {
+        match_log.line(format_args!("tick {} rocket {} {} {}", tick, id, pos.x, pos.y));
+    }
+
+    for note in notes.iter() {
+        let id = rockets.get(note.projectile).map_or(-1, |(id, _)| id.0 as i64);
+        match_log.line(format_args!(
+            "tick {} {:?} {} at {} {} t={}",
+            tick, note.kind, id, note.position.x, note.position.y, note.param
+        ));
+    }
+}
+
+/// Makes sure the log is on disk after each round, in case the game gets closed or crashes
+pub fn flush_log(mut match_log: ResMut<MatchLog>) {
+    match_log.flush();
+}
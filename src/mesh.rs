@@ -0,0 +1,50 @@
+//! Meshes for drawing lots of little quads in one go, like trails and previews,
+//! so they take one entity and one draw call instead of one of each per quad
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+/// Builds a mesh out of quads, each given by its corners counterclockwise, all at depth `z`
+pub fn quad_mesh(quads: impl IntoIterator<Item = [Vec2; 4]>, z: f32) -> Mesh {
+    let mut positions = vec![];
+    let mut indices = vec![];
+    for corners in quads {
+        let first = positions.len() as u32;
+        positions.extend(corners.iter().map(|corner| [corner.x, corner.y, z]));
+        indices.extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+    }
+
+    // 2D meshes need normals and UVs, even though flat colors don't use them
+    let len = positions.len();
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; len]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; len]);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// Builds a mesh of a line through some points, with a rectangle for each segment
+pub fn line_mesh(points: &[Vec2], thickness: f32, z: f32) -> Mesh {
+    let segments = points.windows(2).map(|segment| {
+        let across = (segment[1] - segment[0]).normalize_or_zero().perp() * thickness / 2.0;
+        [segment[0] - across, segment[1] - across, segment[1] + across, segment[0] + across]
+    });
+    quad_mesh(segments, z)
+}
+
+/// Builds a mesh of square dots centered on some points
+pub fn dot_mesh(points: impl IntoIterator<Item = Vec2>, size: f32, z: f32) -> Mesh {
+    let half = size / 2.0;
+    let dots = points.into_iter().map(|point| {
+        [
+            point + Vec2::new(-half, -half),
+            point + Vec2::new(half, -half),
+            point + Vec2::new(half, half),
+            point + Vec2::new(-half, half),
+        ]
+    });
+    quad_mesh(dots, z)
+}
@@ -0,0 +1,287 @@
+//! Mod packages, which override the game's assets.
+//! Natively, each folder in `assets/mods` is a mod if it has a `mod.txt` manifest, such as:
+//!
+//! ```text
+//! name = Neon
+//! version = 1.2
+//! description = Glowing rockets and balls
+//! requires = Neon Sounds
+//! override ball.png = neon_ball.png
+//! override fire.ogg = sounds/fire.ogg
+//! ```
+//!
+//! Each override replaces an asset of the game with a file from the mod's folder.
+//! Files can't be taken from outside the folder. Mods are applied in alphabetical order
+//! of their folders, so when two mods override the same asset, the later one wins
+//! and the conflict gets logged. A mod only applies if the mods it requires, by name,
+//! are installed and on. Mods can be turned on and off from the menu between matches.
+//! Mods aren't supported on the web.
+
+use std::sync::RwLock;
+
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use egui::Align2;
+use fxhash::FxHashMap;
+use once_cell::sync::Lazy;
+
+use crate::asset;
+
+/// Folder with the mods, inside the asset folder
+pub const MODS_DIR: &str = "mods";
+/// Name of the manifest file of a mod
+const MANIFEST: &str = "mod.txt";
+
+/// Asset overrides of the mods that are on, from asset path to path of the replacement
+static OVERRIDES: Lazy<RwLock<FxHashMap<String, String>>> = Lazy::new(Default::default);
+
+/// What a mod manifest says
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    /// Names of the mods this one needs
+    pub requires: Vec<String>,
+    /// Pairs of asset path and path of the replacement, relative to the mod's folder
+    pub overrides: Vec<(String, String)>,
+}
+
+/// Whether a path stays inside the folder it's relative to
+fn is_sandboxed(path: &str) -> bool {
+    let path = std::path::Path::new(path);
+    !path.as_os_str().is_empty()
+        && path.components().all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+/// Parses a mod manifest. Blank lines and lines starting with `#` are skipped.
+pub fn parse_manifest(text: &str) -> Result<Manifest, String> {
+    let mut manifest = Manifest::default();
+    for (i, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) =
+            line.split_once('=').ok_or_else(|| format!("line {}: expected `=`", i))?;
+        let (key, value) = (key.trim(), value.trim());
+        match key.split_once(' ') {
+            None if key == "name" => manifest.name = value.to_owned(),
+            None if key == "version" => manifest.version = value.to_owned(),
+            None if key == "description" => manifest.description = value.to_owned(),
+            None if key == "requires" => manifest.requires.extend(
+                value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_owned),
+            ),
+            Some(("override", asset)) => {
+                let asset = asset.trim();
+                if !is_sandboxed(value) {
+                    return Err(format!("line {}: {} is outside the mod's folder", i, value));
+                }
+                manifest.overrides.push((asset.to_owned(), value.to_owned()));
+            }
+            _ => return Err(format!("line {}: unknown key `{}`", i, key)),
+        }
+    }
+    Ok(manifest)
+}
+
+/// Reads the manifests of all mods, with the names of their folders, in the order they apply
+#[cfg(not(target_family = "wasm"))]
+fn read_manifests() -> Vec<(String, Manifest)> {
+    let dir = std::path::Path::new("assets").join(MODS_DIR);
+    let mut folders = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join(MANIFEST).is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect::<Vec<_>>(),
+        Err(_) => return vec![],
+    };
+    folders.sort();
+
+    folders
+        .into_iter()
+        .filter_map(|folder| {
+            let text = std::fs::read_to_string(dir.join(&folder).join(MANIFEST)).ok()?;
+            match parse_manifest(&text) {
+                Ok(manifest) => Some((folder, manifest)),
+                Err(err) => {
+                    log::warn!("Skipped mod {}: {}", folder, err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(target_family = "wasm")]
+fn read_manifests() -> Vec<(String, Manifest)> {
+    vec![]
+}
+
+/// A mod in the mods folder
+#[derive(Clone, Debug)]
+pub struct InstalledMod {
+    pub folder: String,
+    pub manifest: Manifest,
+    /// Whether the player wants it on
+    pub enabled: bool,
+}
+
+impl InstalledMod {
+    /// Name from the manifest, or the folder's if it doesn't have one
+    pub fn name(&self) -> &str {
+        if self.manifest.name.is_empty() {
+            &self.folder
+        } else {
+            &self.manifest.name
+        }
+    }
+}
+
+/// The installed mods, in the order they apply
+#[derive(Debug, Default)]
+pub struct ModList {
+    pub mods: Vec<InstalledMod>,
+}
+
+impl ModList {
+    /// Which mods actually apply: the enabled ones whose requirements apply too
+    fn active(&self) -> Vec<bool> {
+        let mut active = self.mods.iter().map(|m| m.enabled).collect::<Vec<_>>();
+        // Turning off one mod can break another that needs it, so repeat until nothing changes
+        loop {
+            let broken = (0..self.mods.len())
+                .find(|&i| active[i] && !self.missing_requirements(i, &active).is_empty());
+            match broken {
+                Some(i) => active[i] = false,
+                None => return active,
+            }
+        }
+    }
+
+    /// Requirements of a mod that aren't among the active mods
+    fn missing_requirements(&self, index: usize, active: &[bool]) -> Vec<&str> {
+        self.mods[index]
+            .manifest
+            .requires
+            .iter()
+            .filter(|name| {
+                !self.mods.iter().zip(active).any(|(m, active)| *active && m.name() == *name)
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Makes assets load from the mods that are on
+    fn apply(&self) {
+        let mut overrides = FxHashMap::<String, String>::default();
+        let mut owners = FxHashMap::<String, &str>::default();
+        for (installed, active) in self.mods.iter().zip(self.active()) {
+            if !active {
+                if installed.enabled {
+                    log::warn!("Mod {} is missing a mod it requires", installed.name());
+                }
+                continue;
+            }
+            for (asset, replacement) in &installed.manifest.overrides {
+                if let Some(previous) = owners.insert(asset.clone(), installed.name()) {
+                    log::warn!(
+                        "Mods {} and {} both override {}; {} wins",
+                        previous,
+                        installed.name(),
+                        asset,
+                        installed.name()
+                    );
+                }
+                overrides.insert(
+                    asset.clone(),
+                    format!("{}/{}/{}", MODS_DIR, installed.folder, replacement),
+                );
+            }
+        }
+        *OVERRIDES.write().unwrap() = overrides;
+    }
+}
+
+/// Finds the installed mods and turns them all on. Runs before startup, since assets get loaded then.
+pub fn load_mods(mut commands: Commands) {
+    let mod_list = ModList {
+        mods: read_manifests()
+            .into_iter()
+            .map(|(folder, manifest)| InstalledMod { folder, manifest, enabled: true })
+            .collect(),
+    };
+    for installed in &mod_list.mods {
+        log::info!("Found mod {}", installed.name());
+    }
+    mod_list.apply();
+    commands.insert_resource(mod_list);
+}
+
+/// Path an asset gets loaded from, taking mods into account
+pub fn asset_path(path: &str) -> String {
+    OVERRIDES.read().unwrap().get(path).cloned().unwrap_or_else(|| path.to_owned())
+}
+
+/// Lists the installed mods on the menu, and lets them be turned on and off for the next match
+pub fn show_mod_browser(
+    mut egui_ctx: ResMut<EguiContext>,
+    mut mod_list: ResMut<ModList>,
+    asset_server: Res<AssetServer>,
+    mut used_assets: ResMut<Vec<HandleUntyped>>,
+) {
+    let active = mod_list.active();
+    let mut changed = false;
+
+    egui::Window::new("Mods")
+        .anchor(Align2::LEFT_TOP, [8.0, 8.0])
+        .default_open(false)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            if mod_list.mods.is_empty() {
+                ui.label(format!("No mods installed. Put them in assets/{}.", MODS_DIR));
+                return;
+            }
+
+            for index in 0..mod_list.mods.len() {
+                let missing = mod_list
+                    .missing_requirements(index, &active)
+                    .into_iter()
+                    .map(str::to_owned)
+                    .collect::<Vec<_>>();
+                let installed = &mut mod_list.mods[index];
+                let title = if installed.manifest.version.is_empty() {
+                    installed.name().to_owned()
+                } else {
+                    format!("{} {}", installed.name(), installed.manifest.version)
+                };
+
+                ui.separator();
+                changed |= ui.checkbox(&mut installed.enabled, title).changed();
+                if !installed.manifest.description.is_empty() {
+                    ui.label(&installed.manifest.description);
+                }
+                let assets = installed
+                    .manifest
+                    .overrides
+                    .iter()
+                    .map(|(asset, _)| asset.as_str())
+                    .collect::<Vec<_>>();
+                if !assets.is_empty() {
+                    ui.label(format!("Replaces {}", assets.join(", ")));
+                }
+                if !missing.is_empty() {
+                    let off = if installed.enabled { ", so it's off" } else { "" };
+                    ui.colored_label(
+                        egui::Color32::DARK_RED,
+                        format!("Needs {}{}", missing.join(", "), off),
+                    );
+                }
+            }
+        });
+
+    if changed {
+        mod_list.apply();
+        asset::load_all(&asset_server, &mut used_assets);
+    }
+}
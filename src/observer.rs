@@ -0,0 +1,173 @@
+//! Optional live feed of match events, for tournament overlays and casters.
+//! Natively, setting the `GRAPH_WAR_OBSERVER_PORT` environment variable to a port starts
+//! a WebSocket server on that port of localhost. It sends every client each event
+//! as a JSON object with a `type`:
+//!
+//! ```text
+//! {"type":"round","round":2}
+//! {"type":"shot","player":0,"x":"sin t","y":"t","where":"u = 2"}
+//! {"type":"note","kind":"hit","player":1,"x":0.5,"y":-1.25,"t":0.72}
+//! {"type":"scores","scores":[3,1]}
+//! ```
+//!
+//! Notes are what happens to rockets, with kind `hit`, `bounce`, `boom`, `end`, or `fizzle`.
+//! Clients only listen, and anything they send is ignored.
+
+use bevy::prelude::*;
+use std::sync::mpsc::Sender;
+
+use crate::{breakdown::FlightNote, Game, Owner, Player};
+
+/// Environment variable holding the port of the observer server
+pub const OBSERVER_PORT_VAR: &str = "GRAPH_WAR_OBSERVER_PORT";
+
+/// Where match events go, if the observer server is on
+#[derive(Default)]
+pub struct Observer {
+    sender: Option<Sender<String>>,
+    /// Scores last sent, so they only get sent again when they change
+    scores: Vec<u32>,
+}
+
+impl Observer {
+    /// Starts the server on the port named by the environment variable, if it's set
+    pub fn from_env() -> Self {
+        #[cfg(not(target_family = "wasm"))]
+        if let Ok(port) = std::env::var(OBSERVER_PORT_VAR) {
+            let sender = port
+                .parse::<u16>()
+                .map_err(|err| err.to_string())
+                .and_then(|port| serve(port).map_err(|err| err.to_string()));
+            match sender {
+                Ok(sender) => {
+                    log::info!("Observer server listening on port {}", port);
+                    return Self { sender: Some(sender), ..Self::default() };
+                }
+                Err(err) => log::warn!("Failed to start observer server on {}: {}", port, err),
+            }
+        }
+        Self::default()
+    }
+
+    fn send(&self, event: String) {
+        if let Some(sender) = &self.sender {
+            sender.send(event).ok();
+        }
+    }
+}
+
+/// Accepts clients and sends them the events that come through the returned sender,
+/// each on its own thread so the game never waits on the network
+#[cfg(not(target_family = "wasm"))]
+fn serve(port: u16) -> std::io::Result<Sender<String>> {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::{mpsc, Arc, Mutex},
+        thread,
+    };
+    use tungstenite::{Message, WebSocket};
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let clients = Arc::new(Mutex::new(Vec::<WebSocket<TcpStream>>::new()));
+
+    let accepted = Arc::clone(&clients);
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(Result::ok) {
+            match tungstenite::accept(stream) {
+                Ok(socket) => accepted.lock().unwrap().push(socket),
+                Err(err) => log::warn!("Observer client failed to connect: {}", err),
+            }
+        }
+    });
+
+    let (sender, receiver) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for event in receiver {
+            let mut clients = clients.lock().unwrap();
+            // Clients that can't be written to have disconnected
+            *clients = clients
+                .drain(..)
+                .filter_map(|mut socket| {
+                    socket.write_message(Message::Text(event.clone())).is_ok().then(|| socket)
+                })
+                .collect();
+        }
+    });
+    Ok(sender)
+}
+
+/// Quotes a string for JSON
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Sends the round that's starting and the shots in it.
+/// Has to run before the rockets get fired, since that takes the shots out of the players.
+pub fn observe_round_start(observer: Res<Observer>, game: Res<Game>, players: Res<Vec<Player>>) {
+    if observer.sender.is_none() {
+        return;
+    }
+
+    observer.send(format!(r#"{{"type":"round","round":{}}}"#, game.round_index));
+    for (index, player) in players.iter().enumerate() {
+        for parametric in &player.parametrics {
+            let source = |s: &Option<String>| json_string(s.as_deref().unwrap_or(""));
+            observer.send(format!(
+                r#"{{"type":"shot","player":{},"x":{},"y":{},"where":{}}}"#,
+                index,
+                source(&parametric.source_x),
+                source(&parametric.source_y),
+                source(&parametric.source_assigns)
+            ));
+        }
+    }
+}
+
+/// Sends what happens to rockets as they fly
+pub fn observe_notes(
+    observer: Res<Observer>,
+    mut notes: EventReader<FlightNote>,
+    owners: Query<&Owner>,
+) {
+    if observer.sender.is_none() {
+        return;
+    }
+
+    for note in notes.iter() {
+        let player = owners.get(note.projectile).map_or(-1, |owner| owner.0 as i64);
+        observer.send(format!(
+            r#"{{"type":"note","kind":"{}","player":{},"x":{},"y":{},"t":{}}}"#,
+            note.kind.label(),
+            player,
+            note.position.x,
+            note.position.y,
+            note.param
+        ));
+    }
+}
+
+/// Sends the number of balls each player has whenever it changes
+pub fn observe_scores(mut observer: ResMut<Observer>, players: Res<Vec<Player>>) {
+    if observer.sender.is_none() {
+        return;
+    }
+
+    let scores = players.iter().map(|player| player.num_balls).collect::<Vec<_>>();
+    if scores != observer.scores {
+        let list = scores.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+        observer.send(format!(r#"{{"type":"scores","scores":[{}]}}"#, list));
+        observer.scores = scores;
+    }
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use std::ops::Range;
+
+use crate::{
+    core::lang::ParseError,
+    ui::{entry_box_index, FunctionEntryBox, FunctionX, FunctionY, Textbox},
+    Owner,
+};
+
+const MARK_COLOR: Color = Color::RED;
+
+/// Sent when a player's functions fail to parse, to point out where in the textbox
+pub struct ParseErrorEvent {
+    pub player: u32,
+    /// Index of the textbox, like `entry_box_index` gives
+    pub textbox: usize,
+    /// Byte range of the textbox's text the error is about
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl ParseErrorEvent {
+    pub fn new(player: u32, error: &ParseError) -> Self {
+        Self {
+            player,
+            textbox: error.textbox(),
+            span: error.span(),
+            message: error.message().to_owned(),
+        }
+    }
+}
+
+/// Labels the text under the function textboxes that shows where the last parse error was
+#[derive(Component)]
+pub struct ParseErrorText;
+
+/// Sections showing the line of `source` with the error, with the span marked in red,
+/// and the message under it
+fn error_sections(
+    source: &str,
+    span: Range<usize>,
+    message: &str,
+    style: &TextStyle,
+) -> Vec<TextSection> {
+    let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[span.start..].find('\n').map_or(source.len(), |i| span.start + i);
+    let end = span.end.min(line_end);
+    let before = &source[line_start..span.start];
+    let (marked, after) = match (&source[span.start..end], &source[end..line_end]) {
+        // Errors at a single spot mark the character there, or a blank past the end of the line
+        ("", after) => match after.chars().next() {
+            Some(c) => after.split_at(c.len_utf8()),
+            None => ("_", ""),
+        },
+        marked => marked,
+    };
+
+    // The font is monospace, so the carets line up with the marked part
+    let carets = format!(
+        "{}{} {}",
+        " ".repeat(before.chars().count()),
+        "^".repeat(marked.chars().count()),
+        message
+    );
+    let marked_style = TextStyle { color: MARK_COLOR, ..style.clone() };
+    vec![
+        TextSection { value: before.to_owned(), style: style.clone() },
+        TextSection { value: marked.to_owned(), style: marked_style.clone() },
+        TextSection { value: format!("{}\n", after), style: style.clone() },
+        TextSection { value: carets, style: marked_style },
+    ]
+}
+
+/// Shows where the last parse error was, until the textbox it's in gets edited
+pub fn show_parse_errors(
+    mut events: EventReader<ParseErrorEvent>,
+    entry_boxes: Query<
+        (&Owner, &Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+    mut text: Query<&mut Text, With<ParseErrorText>>,
+    mut shown: Local<Option<(u32, usize, String)>>,
+) {
+    let source = |player: u32, textbox: usize| {
+        entry_boxes
+            .iter()
+            .find(|(owner, _, x, y)| owner.0 == player && entry_box_index(*x, *y) == textbox)
+            .map(|(_, textbox, _, _)| textbox.text.clone())
+    };
+    let mut text = text.single_mut();
+    let style = text.sections[0].style.clone();
+
+    for event in events.iter() {
+        // The text could have changed since the error
+        let current = source(event.player, event.textbox)
+            .filter(|current| current.get(event.span.clone()).is_some());
+        if let Some(current) = current {
+            text.sections = error_sections(&current, event.span.clone(), &event.message, &style);
+            *shown = Some((event.player, event.textbox, current));
+        }
+    }
+
+    // Once the text changes, the error is out of date
+    if let Some((player, textbox, shown_source)) = &*shown {
+        if source(*player, *textbox).as_ref() != Some(shown_source) {
+            text.sections = vec![TextSection { value: String::new(), style }];
+            *shown = None;
+        }
+    }
+}
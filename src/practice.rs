@@ -0,0 +1,249 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use fxhash::FxHashMap;
+use rand::prelude::Distribution;
+use rand_pcg::Pcg64;
+
+use crate::{
+    asset,
+    collision::PrevPosition,
+    daily::GameMode,
+    intersect::path_distance,
+    projectile::Projectile,
+    projector::ProjectorCamera,
+    sketch::cursor_on_field,
+    ui::{ButtonsEnabled, TextboxesEditable, UiCamera},
+    DespawnOnExit, Field, Game, PlayState, RelativeTextSize, RenderLayer, FIELD_CONFIGS,
+};
+
+/// Number of targets a practice match starts with
+const NUM_TARGETS: usize = 3;
+/// Most targets that can be on the field at once. Placing another removes the oldest.
+const MAX_TARGETS: usize = 5;
+/// Outer radius of each ring of a target, from the bullseye out, in field units
+const RING_RADII: [f32; 3] = [0.2, 0.45, 0.75];
+/// Points for getting within each ring
+const RING_POINTS: [u32; 3] = [10, 5, 2];
+/// Colors of the rings, from the bullseye out
+const RING_COLORS: [Color; 3] =
+    [Color::rgb(0.9, 0.1, 0.1), Color::rgb(0.95, 0.95, 0.95), Color::rgb(0.9, 0.1, 0.1)];
+
+/// Points for a rocket whose path got within some distance of a target's center
+fn ring_score(dist: f32) -> u32 {
+    RING_RADII.iter().zip(RING_POINTS).find(|(radius, _)| dist < **radius).map_or(0, |(_, p)| p)
+}
+
+/// A bullseye to aim at in practice matches. Rockets score by how close they get to the center,
+/// and don't stop when they do.
+#[derive(Component)]
+pub struct Target {
+    /// Counts up as targets get placed, so the oldest can be found
+    order: u32,
+    /// Best score any rocket got on this target
+    best: Option<u32>,
+}
+
+/// Labels the text under a target with its best score
+#[derive(Component)]
+pub struct TargetBestText;
+
+/// Closest each rocket in flight has come to each target
+#[derive(Debug, Default)]
+pub struct TargetApproaches {
+    closest: FxHashMap<(Entity, Entity), f32>,
+    next_order: u32,
+}
+
+fn spawn_target(
+    node: &mut ChildBuilder,
+    images: &Assets<Image>,
+    fonts: &Assets<Font>,
+    position: Vec2,
+    order: u32,
+) {
+    node.spawn_bundle((
+        Transform::from_translation(position.extend(RenderLayer::Target.z())),
+        GlobalTransform::identity(),
+    ))
+    .insert(Target { order, best: None })
+    .with_children(|node| {
+        for (i, (radius, color)) in RING_RADII.iter().zip(RING_COLORS).enumerate().rev() {
+            node.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(2.0 * radius)),
+                    ..Default::default()
+                },
+                texture: images.get_handle(asset::Ball),
+                transform: Transform::from_xyz(
+                    0.0,
+                    0.0,
+                    RenderLayer::STEP * (RING_RADII.len() - i) as f32,
+                ),
+                ..Default::default()
+            });
+        }
+        node.spawn_bundle(Text2dBundle {
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    color: Color::BLACK,
+                    font: fonts.get_handle(asset::Font),
+                    font_size: 0.0,
+                },
+                TextAlignment { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
+            ),
+            transform: Transform::from_xyz(
+                0.0,
+                -RING_RADII[RING_RADII.len() - 1],
+                RenderLayer::STEP * (RING_RADII.len() + 1) as f32,
+            ),
+            ..Default::default()
+        })
+        .insert(RelativeTextSize(0.15))
+        .insert(TargetBestText);
+    });
+}
+
+/// Puts some targets on the field at the start of a practice match
+pub fn spawn_targets(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    game: Res<Game>,
+    mut rng: ResMut<Pcg64>,
+    mut approaches: ResMut<TargetApproaches>,
+    targets: Query<(), With<Target>>,
+    field: Query<Entity, With<Field>>,
+    images: Res<Assets<Image>>,
+    fonts: Res<Assets<Font>>,
+) {
+    if *mode != GameMode::Practice || targets.iter().next().is_some() {
+        return;
+    }
+
+    let item_region = &FIELD_CONFIGS[game.num_players() as usize].item_region;
+    let item_distribution = item_region.scaled(game.half_size());
+    commands.entity(field.single()).with_children(|node| {
+        for position in (&item_distribution).sample_iter(&mut *rng).take(NUM_TARGETS) {
+            spawn_target(node, &images, &fonts, position, approaches.next_order);
+            approaches.next_order += 1;
+        }
+    });
+}
+
+/// Places a target where the field gets Shift+clicked in a practice match
+pub fn place_target(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    camera: Query<
+        (&GlobalTransform, &OrthographicProjection),
+        (Without<UiCamera>, Without<ProjectorCamera>),
+    >,
+    game: Res<Game>,
+    mut approaches: ResMut<TargetApproaches>,
+    targets: Query<(Entity, &Target)>,
+    field: Query<Entity, With<Field>>,
+    images: Res<Assets<Image>>,
+    fonts: Res<Assets<Font>>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    textboxes_editable: Res<TextboxesEditable>,
+) {
+    let shift = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+    if *mode != GameMode::Practice
+        || !buttons_enabled.0
+        || !textboxes_editable.0
+        || !shift
+        || !mouse.just_pressed(MouseButton::Left)
+    {
+        return;
+    }
+    let position = if let Some(pos) = cursor_on_field(&windows, &camera) { pos } else { return };
+    if position.abs().cmpgt(game.half_size()).any() {
+        return;
+    }
+
+    if targets.iter().count() >= MAX_TARGETS {
+        if let Some((oldest, _)) = targets.iter().min_by_key(|(_, target)| target.order) {
+            commands.entity(oldest).despawn_recursive();
+        }
+    }
+    commands.entity(field.single()).with_children(|node| {
+        spawn_target(node, &images, &fonts, position, approaches.next_order);
+    });
+    approaches.next_order += 1;
+}
+
+/// Keeps track of how close rockets get to targets, and scores each rocket once it's gone
+pub fn track_targets(
+    mut commands: Commands,
+    mut approaches: ResMut<TargetApproaches>,
+    rockets: Query<(Entity, &PrevPosition, &Transform), With<Projectile>>,
+    mut targets: Query<(Entity, &mut Target, &Transform, &Children)>,
+    mut texts: Query<&mut Text, With<TargetBestText>>,
+    field: Query<Entity, With<Field>>,
+    fonts: Res<Assets<Font>>,
+) {
+    for (rocket, prev_pos, transform) in rockets.iter() {
+        let path = [prev_pos.0, transform.translation.xy()];
+        for (target, _, target_transform, _) in targets.iter() {
+            let dist = path_distance(&path, target_transform.translation.xy());
+            let closest = approaches.closest.entry((rocket, target)).or_insert(f32::INFINITY);
+            *closest = closest.min(dist);
+        }
+    }
+
+    let finished = approaches
+        .closest
+        .keys()
+        .filter(|(rocket, _)| rockets.get(*rocket).is_err())
+        .copied()
+        .collect::<Vec<_>>();
+    for key in finished {
+        let dist = approaches.closest.remove(&key).unwrap_or(f32::INFINITY);
+        let (_, mut target, transform, children) =
+            if let Ok(target) = targets.get_mut(key.1) { target } else { continue };
+        let score = ring_score(dist);
+        if score == 0 {
+            continue;
+        }
+
+        log::info!("A rocket scored {} on a target, {:.2} from the center", score, dist);
+        if target.best.map_or(true, |best| score > best) {
+            target.best = Some(score);
+            for child in children.iter() {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value = format!("best {}", score);
+                }
+            }
+        }
+
+        let position = transform.translation.xy() + Vec2::Y * RING_RADII[RING_RADII.len() - 1];
+        commands.entity(field.single()).with_children(|node| {
+            node.spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    format!("+{}", score),
+                    TextStyle {
+                        color: Color::rgb(0.9, 0.1, 0.1),
+                        font: fonts.get_handle(asset::Font),
+                        font_size: 0.0,
+                    },
+                    TextAlignment {
+                        horizontal: HorizontalAlign::Center,
+                        vertical: VerticalAlign::Bottom,
+                    },
+                ),
+                transform: Transform::from_translation(position.extend(RenderLayer::Score.z())),
+                ..Default::default()
+            })
+            .insert(RelativeTextSize(0.2))
+            .insert(DespawnOnExit(PlayState::Fire));
+        });
+    }
+}
+
+/// Forgets about rockets that never finished, such as when the round got skipped
+pub fn clear_approaches(mut approaches: ResMut<TargetApproaches>) {
+    approaches.closest.clear();
+}
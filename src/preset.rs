@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+use crate::{
+    ui::{entry_box_index, ButtonsEnabled, FunctionEntryBox, FunctionX, FunctionY, Textbox},
+    Game, Player,
+};
+
+/// Number of function presets each player has
+pub const NUM_PRESETS: usize = 3;
+
+/// Keys for the presets. Ctrl+key loads a preset and Ctrl+Shift+key saves to it.
+const PRESET_KEYS: [KeyCode; NUM_PRESETS] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+
+/// Name of a preset, by index
+fn preset_name(index: usize) -> char {
+    (b'A' + index as u8) as char
+}
+
+/// Labels the text above the textboxes that shows the current player's presets as tabs.
+/// It has one section per preset.
+#[derive(Component)]
+pub struct PresetTabs;
+
+/// Saves and loads presets of the current player with their hotkeys
+pub fn use_presets(
+    keys: Res<Input<KeyCode>>,
+    game: Res<Game>,
+    mut players: ResMut<Vec<Player>>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+) {
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    if !buttons_enabled.0 || !ctrl {
+        return;
+    }
+    let index = if let Some(index) = PRESET_KEYS.iter().position(|key| keys.just_pressed(*key)) {
+        index
+    } else {
+        return;
+    };
+
+    let player = &mut players[game.player_turn() as usize];
+    if keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift) {
+        let mut functions: [String; 3] = Default::default();
+        for (textbox, x, y) in entry_boxes.iter() {
+            functions[entry_box_index(x, y)] = textbox.text.clone();
+        }
+        player.presets[index] = Some(functions);
+    } else if let Some(functions) = &player.presets[index] {
+        for (mut textbox, x, y) in entry_boxes.iter_mut() {
+            textbox.text = functions[entry_box_index(x, y)].clone();
+        }
+    }
+}
+
+/// Dims empty presets and highlights the one that matches what's in the textboxes
+pub fn update_preset_tabs(
+    mut tabs: Query<&mut Text, With<PresetTabs>>,
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    entry_boxes: Query<(&Textbox, Option<&FunctionX>, Option<&FunctionY>), With<FunctionEntryBox>>,
+) {
+    let mut current: [&str; 3] = Default::default();
+    for (textbox, x, y) in entry_boxes.iter() {
+        current[entry_box_index(x, y)] = &textbox.text;
+    }
+
+    let player = &players[game.player_turn() as usize];
+    let mut text = tabs.single_mut();
+    for (index, section) in text.sections.iter_mut().enumerate() {
+        let (value, color) = match &player.presets[index] {
+            Some(functions) if functions.iter().map(String::as_str).eq(current) => {
+                (format!("[{}]", preset_name(index)), Color::rgb(0.1, 0.4, 0.8))
+            }
+            Some(_) => (format!(" {} ", preset_name(index)), Color::BLACK),
+            None => (format!(" {} ", preset_name(index)), Color::GRAY),
+        };
+        if section.value != value {
+            section.value = value;
+        }
+        if section.style.color != color {
+            section.style.color = color;
+        }
+    }
+}
@@ -0,0 +1,802 @@
+use bevy::{ecs::system::EntityCommands, math::Vec3Swizzles, prelude::*};
+use bevy_kira_audio::{Audio, AudioChannel, AudioSource};
+use bevy_rapier2d::prelude::*;
+use std::{iter, time::Duration};
+
+use crate::{
+    analysis::sample_path,
+    asset,
+    breakdown::{FlightNote, NoteKind},
+    charge::{blast_radius, Charge},
+    collision::{CollisionGroups, PrevParam, PrevPosition, ProjectileCollision},
+    core::lang::Parametric,
+    dilation::Dilation,
+    graph::{Graph, GRAPH_COLORS},
+    ricochet::{spawn_bounce_counter, Bounces},
+    rules::{GameRules, OnExpire},
+    spawn_item,
+    status::{StatusEffects, StatusKind, SLOW_FACTOR},
+    style::{style_points, StylePoints},
+    time::GameTime,
+    ui::{ButtonsEnabled, FunctionDisplayBox, FunctionWhere, FunctionX, FunctionY, Textbox},
+    Ball, DespawnOnExit, Field, Game, Mine, Owner, PlayState, Player, PlayerLabel, RenderLayer,
+    ITEM_BALL, ITEM_MINE,
+};
+
+/// Flight time of a rocket, in seconds
+pub const ROCKET_TIME: f32 = 5.0;
+/// Downward acceleration in physics mode, in field units per unit of parameter squared
+pub const GRAVITY: f32 = 8.0;
+/// Fraction of velocity lost to drag per unit of parameter in physics mode
+pub const DRAG: f32 = 0.5;
+/// Integration steps per unit of parameter in physics mode
+const PHYSICS_STEPS: usize = 512;
+
+/// Kinds of projectiles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectileKind {
+    Rocket,
+}
+
+impl ProjectileKind {
+    /// Texture of the projectile when fired by some player
+    pub fn texture(self, player: u32) -> asset::Asset {
+        match self {
+            Self::Rocket => asset::Rocket(player),
+        }
+    }
+
+    /// Size of the sprite before it gets scaled
+    pub fn sprite_size(self) -> Vec2 {
+        match self {
+            Self::Rocket => Vec2::new(2.8, 1.4),
+        }
+    }
+
+    /// Sound looped while the projectile is flying
+    pub fn move_sound(self, player: u32) -> asset::Asset {
+        match self {
+            Self::Rocket => asset::RocketMove(player),
+        }
+    }
+
+    /// Flight time, in seconds
+    pub fn flight_time(self) -> f32 {
+        match self {
+            Self::Rocket => ROCKET_TIME,
+        }
+    }
+}
+
+/// How a projectile moves. The parameter goes from 0 to 1 over the flight.
+/// A `reversed` motion goes along the same path backwards.
+#[derive(Clone, Debug, Component)]
+pub enum MotionModel {
+    /// Follows a parametric equation, moved so it starts at `start`,
+    /// with `transform` applied around the start.
+    /// Only the part of the equation up to `t_max` gets followed.
+    Parametric {
+        parametric: Parametric,
+        origin: Vec2,
+        start: Vec2,
+        transform: Mat2,
+        t_max: f32,
+        reversed: bool,
+    },
+    /// Treats a parametric equation as an acceleration, with gravity and drag added,
+    /// starting at rest at `start`. `samples` holds the integrated displacement,
+    /// with sample `i` at parameter `i / PHYSICS_STEPS`, up to at least `t_max`.
+    Physics {
+        parametric: Parametric,
+        samples: Vec<Vec2>,
+        start: Vec2,
+        transform: Mat2,
+        t_max: f32,
+        reversed: bool,
+    },
+}
+
+/// Integrates an acceleration given by a parametric equation up to parameter `t_max`,
+/// starting at rest at the origin. Once the path stops being finite, it stays that way.
+fn integrate(parametric: &Parametric, t_max: f32) -> Vec<Vec2> {
+    let steps = (t_max * PHYSICS_STEPS as f32).ceil() as usize;
+    let dt = 1.0 / PHYSICS_STEPS as f32;
+    let mut pos = Vec2::ZERO;
+    let mut vel = Vec2::ZERO;
+    let mut samples = Vec::with_capacity(steps + 1);
+    samples.push(pos);
+    for i in 0..steps {
+        let accel = parametric.eval((i as f32 * dt) as f64) - Vec2::Y * GRAVITY - vel * DRAG;
+        vel += accel * dt;
+        pos += vel * dt;
+        samples.push(pos);
+    }
+    samples
+}
+
+impl MotionModel {
+    /// Follow a parametric equation, moved so it starts at `start`
+    pub fn parametric(parametric: Parametric, start: Vec2) -> Self {
+        let origin = parametric.eval(0.0);
+        Self::Parametric {
+            parametric,
+            origin,
+            start,
+            transform: Mat2::IDENTITY,
+            t_max: 1.0,
+            reversed: false,
+        }
+    }
+
+    /// Treat a parametric equation as an acceleration, starting at rest at `start`
+    pub fn physics(parametric: Parametric, start: Vec2) -> Self {
+        let samples = integrate(&parametric, 1.0);
+        Self::Physics {
+            parametric,
+            samples,
+            start,
+            transform: Mat2::IDENTITY,
+            t_max: 1.0,
+            reversed: false,
+        }
+    }
+
+    /// Picks the motion model the rules call for
+    pub fn for_rules(parametric: Parametric, start: Vec2, rules: &GameRules) -> Self {
+        if rules.physics {
+            Self::physics(parametric, start)
+        } else {
+            Self::parametric(parametric, start)
+        }
+    }
+
+    /// The equation the path follows
+    pub fn equation(&self) -> &Parametric {
+        match self {
+            Self::Parametric { parametric, .. } | Self::Physics { parametric, .. } => parametric,
+        }
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        match self {
+            Self::Parametric { reversed, .. } | Self::Physics { reversed, .. } => *reversed,
+        }
+    }
+
+    /// Switches which way the path gets followed
+    pub fn reverse(&mut self) {
+        match self {
+            Self::Parametric { reversed, .. } | Self::Physics { reversed, .. } => {
+                *reversed = !*reversed
+            }
+        }
+    }
+
+    /// Applies a linear transform to the path, around its start
+    pub fn with_transform(mut self, new_transform: Mat2) -> Self {
+        match &mut self {
+            Self::Parametric { transform, .. } | Self::Physics { transform, .. } => {
+                *transform = new_transform * *transform
+            }
+        }
+        self
+    }
+
+    /// Reflects the path about the line through `point` perpendicular to `normal`,
+    /// which must be a unit vector
+    pub fn reflect(&mut self, point: Vec2, normal: Vec2) {
+        let reflection =
+            Mat2::from_cols(Vec2::X - 2.0 * normal.x * normal, Vec2::Y - 2.0 * normal.y * normal);
+        match self {
+            Self::Parametric { start, transform, .. } | Self::Physics { start, transform, .. } => {
+                *start = point + reflection * (*start - point);
+                *transform = reflection * *transform;
+            }
+        }
+    }
+
+    /// Follows the equation further in time, by some factor
+    pub fn with_time_factor(mut self, factor: f32) -> Self {
+        match &mut self {
+            Self::Parametric { t_max, .. } => *t_max *= factor,
+            Self::Physics { parametric, samples, t_max, .. } => {
+                *t_max *= factor;
+                if samples.len() <= (*t_max * PHYSICS_STEPS as f32).ceil() as usize {
+                    *samples = integrate(parametric, *t_max);
+                }
+            }
+        }
+        self
+    }
+
+    pub fn position(&self, t: f32) -> Vec2 {
+        let t = if self.is_reversed() { 1.0 - t } else { t };
+        match self {
+            Self::Parametric { parametric, origin, start, transform, t_max, .. } => {
+                *start + *transform * (parametric.eval((t * *t_max) as f64) - *origin)
+            }
+            Self::Physics { samples, start, transform, t_max, .. } => {
+                let s = t * *t_max * PHYSICS_STEPS as f32;
+                let i = (s.max(0.0) as usize).min(samples.len() - 1);
+                let next = samples.get(i + 1).unwrap_or(&samples[i]);
+                *start + *transform * samples[i].lerp(*next, s - i as f32)
+            }
+        }
+    }
+
+    /// Like `position`, but `None` where the path is undefined or infinite.
+    /// Anything that moves physics objects should use this.
+    pub fn try_position(&self, t: f32) -> Option<Vec2> {
+        let pos = self.position(t);
+        pos.is_finite().then(|| pos)
+    }
+
+    /// Parameter at which the path reaches some arc length, or 1 if the path is shorter than that.
+    /// Stops early if the path stops being finite.
+    pub fn param_at_length(&self, length: f32) -> f32 {
+        const SAMPLES: usize = 256;
+
+        let mut total = 0.0;
+        let mut prev = self.position(0.0);
+        for i in 1..=SAMPLES {
+            let t_prev = (i - 1) as f32 / SAMPLES as f32;
+            let pos = self.position(i as f32 / SAMPLES as f32);
+            let step = (pos - prev).length();
+            if !step.is_finite() {
+                return t_prev;
+            }
+            if total + step >= length {
+                return t_prev + (length - total) / step / SAMPLES as f32;
+            }
+            total += step;
+            prev = pos;
+        }
+        1.0
+    }
+
+    /// Cuts off the path where it reaches some arc length.
+    /// Returns the fraction of the original path that's left.
+    pub fn truncate_to_length(&mut self, length: f32) -> f32 {
+        let fraction = self.param_at_length(length);
+        match self {
+            Self::Parametric { t_max, .. } | Self::Physics { t_max, .. } => *t_max *= fraction,
+        }
+        fraction
+    }
+}
+
+/// Labels a projectile
+#[derive(Clone, Copy, Debug, Component)]
+pub struct Projectile {
+    pub kind: ProjectileKind,
+    pub on_expire: OnExpire,
+}
+
+impl Projectile {
+    pub fn new(kind: ProjectileKind) -> Self {
+        Self { kind, on_expire: OnExpire::Despawn }
+    }
+
+    pub fn with_on_expire(mut self, on_expire: OnExpire) -> Self {
+        self.on_expire = on_expire;
+        self
+    }
+}
+
+/// The flight time of a projectile is up. This is an event.
+pub struct ProjectileExpired {
+    pub projectile: Entity,
+}
+
+/// A projectile got destroyed by something other than running into things,
+/// such as an EMP. This is an event.
+pub struct ProjectileDestroyed {
+    pub projectile: Entity,
+}
+
+/// Where a player's last rocket was when its flight time ran out, so the next shot can
+/// pick up from there. Rockets that hit something or fizzle out don't count.
+#[derive(Clone, Copy, Debug, Default, Component)]
+pub struct LastShotEnd(pub Option<Vec2>);
+
+/// Audio channel for a projectile.
+#[derive(Component)]
+pub struct ProjectileChannel(pub AudioChannel);
+
+/// Where a projectile is expected to go, sampled when it gets spawned.
+/// Sample `i` is at parameter `i / analysis::PATH_SAMPLES`.
+#[derive(Clone, Debug, Component)]
+pub struct SamplePath(pub Vec<Vec2>);
+
+/// How big a projectile looks and how big its hitbox is, which are kept apart
+/// so hitboxes can be balanced without touching the art
+#[derive(Clone, Copy, Debug, Component)]
+pub struct ProjectileSize {
+    /// Scale of the sprite
+    pub scale: f32,
+    /// Radius of the hitbox, in field units
+    pub radius: f32,
+}
+
+impl ProjectileSize {
+    pub fn for_rules(rules: &GameRules) -> Self {
+        Self { scale: rules.rocket_scale, radius: rules.rocket_radius }
+    }
+}
+
+/// The graph a projectile draws as it moves
+#[derive(Clone, Copy, Debug, Component)]
+pub struct Trail(pub Entity);
+
+/// Spawns a projectile, along with the graph it draws, and returns the projectile's commands
+pub fn spawn_projectile<'w, 's, 'a, 'b>(
+    node: &'b mut ChildBuilder<'w, 's, 'a>,
+    images: &Assets<Image>,
+    audio: &Audio,
+    sounds: &Assets<AudioSource>,
+    projectile: Projectile,
+    motion: MotionModel,
+    size: ProjectileSize,
+    flight_time: f32,
+    owner: Owner,
+) -> EntityCommands<'w, 's, 'b> {
+    let start = motion.position(0.0);
+    let path = SamplePath(sample_path(&motion));
+
+    let graph = node
+        .spawn()
+        .insert(Transform::identity())
+        .insert(GlobalTransform::identity())
+        .insert(owner)
+        .insert(Graph { color: GRAPH_COLORS[owner.0 as usize], points: vec![start] })
+        .id();
+
+    let mut entity_commands = node.spawn_bundle(SpriteBundle {
+        sprite: Sprite { custom_size: Some(projectile.kind.sprite_size()), ..Default::default() },
+        texture: images.get_handle(projectile.kind.texture(owner.0)),
+        transform: Transform::from_translation(start.extend(RenderLayer::Rocket.z()))
+            .with_scale([size.scale; 3].into()),
+        ..Default::default()
+    });
+
+    let channel = AudioChannel::new(format!("projectile{}", entity_commands.id().id()));
+    audio.play_looped_in_channel(sounds.get_handle(projectile.kind.move_sound(owner.0)), &channel);
+
+    entity_commands
+        .insert(projectile)
+        .insert(motion)
+        .insert(path)
+        .insert(size)
+        .insert(Timer::new(Duration::from_secs_f32(flight_time), false))
+        .insert(owner)
+        .insert(PrevPosition(start))
+        .insert(PrevParam(0.0))
+        .insert(ProjectileChannel(channel))
+        .insert(Trail(graph))
+        .insert_bundle(RigidBodyBundle {
+            body_type: RigidBodyType::KinematicPositionBased.into(),
+            position: start.extend(0.0).into(),
+            // kinematic-static CCD doesn't work
+            ..Default::default()
+        })
+        .with_children(|body| {
+            body.spawn_bundle(ColliderBundle {
+                shape: ColliderShape::ball(size.radius).into(),
+                collider_type: ColliderType::Solid.into(),
+                position: Vec2::ZERO.into(),
+                flags: ColliderFlags {
+                    collision_groups: InteractionGroups::new(
+                        CollisionGroups::ROCKET.bits(),
+                        CollisionGroups::ROCKET_CAST.bits(),
+                    ),
+                    active_collision_types: ActiveCollisionTypes::KINEMATIC_KINEMATIC
+                        | ActiveCollisionTypes::KINEMATIC_STATIC,
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            });
+        });
+    entity_commands
+}
+
+pub fn fire_rockets(
+    mut commands: Commands,
+    mut textboxes_fx: Query<(&Owner, &mut Textbox), (With<FunctionDisplayBox>, With<FunctionX>)>,
+    mut textboxes_fy: Query<
+        (&Owner, &mut Textbox),
+        (With<FunctionDisplayBox>, With<FunctionY>, Without<FunctionX>),
+    >,
+    mut textboxes_where: Query<
+        (&Owner, &mut Textbox),
+        (With<FunctionDisplayBox>, With<FunctionWhere>, Without<FunctionX>, Without<FunctionY>),
+    >,
+    mut players: ResMut<Vec<Player>>,
+    mut player_comps: Query<(&Owner, &GlobalTransform, &mut StatusEffects), With<PlayerLabel>>,
+    images: Res<Assets<Image>>,
+    fonts: Res<Assets<Font>>,
+    field: Query<Entity, With<Field>>,
+    rules: Res<GameRules>,
+    audio: Res<Audio>,
+    sounds: Res<Assets<AudioSource>>,
+) {
+    /// Joins the sources of all the rockets a player fired
+    fn joined_sources(
+        player: &mut Player,
+        source: impl Fn(&mut Parametric) -> &mut Option<String>,
+        separator: &str,
+    ) -> String {
+        player
+            .parametrics
+            .iter_mut()
+            .map(|parametric| source(parametric).take().unwrap())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    for (owner, mut textbox) in textboxes_fx.iter_mut() {
+        if let Some(player) = players.get_mut(owner.0 as usize) {
+            textbox.text = joined_sources(player, |p| &mut p.source_x, " | ");
+        }
+    }
+    for (owner, mut textbox) in textboxes_fy.iter_mut() {
+        if let Some(player) = players.get_mut(owner.0 as usize) {
+            textbox.text = joined_sources(player, |p| &mut p.source_y, " | ");
+        }
+    }
+    for (owner, mut textbox) in textboxes_where.iter_mut() {
+        if let Some(player) = players.get_mut(owner.0 as usize) {
+            textbox.text = joined_sources(player, |p| &mut p.source_assigns, "\n\n");
+        }
+    }
+
+    let fire_channel = AudioChannel::new("Fire".into());
+    audio.play_in_channel(sounds.get_handle(asset::Fire), &fire_channel);
+    audio.set_volume_in_channel(2.0, &fire_channel);
+
+    // A ricochet pickup goes to the next rocket its player fires
+    let mut ricochets = vec![];
+    for (owner, _, mut effects) in player_comps.iter_mut() {
+        if !players[owner.0 as usize].parametrics.is_empty()
+            && effects.consume(StatusKind::Ricochet)
+        {
+            ricochets.push(owner.0);
+        }
+    }
+
+    let launchers = player_comps
+        .iter()
+        .map(|(owner, transform, effects)| (*owner, transform.translation.xy(), effects))
+        .collect::<Vec<_>>();
+
+    commands.entity(field.single()).with_children(|node| {
+        for (owner, _, _) in &launchers {
+            let parametrics = std::mem::take(&mut players[owner.0 as usize].parametrics);
+            let charges = std::mem::take(&mut players[owner.0 as usize].charges);
+            for (parametric, charge) in
+                parametrics.into_iter().zip(charges.into_iter().chain(iter::repeat(0.0)))
+            {
+                let style = rules.style_score.then(|| style_points(&parametric));
+                // Rockets evaluate their functions every frame, so they fly the simplified ones,
+                // compiled to bytecode
+                let parametric = parametric.simplify().compile();
+
+                // In mirror matches, everyone else fires the same rocket, flipped around
+                let mirrors =
+                    launchers.iter().filter(|(other, ..)| rules.mirror || other.0 == owner.0);
+
+                for (launcher, start, effects) in mirrors {
+                    let kind = ProjectileKind::Rocket;
+                    let mut motion = MotionModel::for_rules(parametric.clone(), *start, &rules);
+                    if launcher.0 != owner.0 {
+                        motion = motion.with_transform(Mat2::from_diagonal(Vec2::splat(-1.0)));
+                    }
+                    let flight_factor = players[launcher.0 as usize].loadout.flight_factor();
+                    motion = motion.with_time_factor(flight_factor);
+                    let mut flight_time = kind.flight_time() * flight_factor;
+
+                    // Running out of fuel ends the flight early, at the same speed
+                    if let Some(fuel) = rules.fuel {
+                        flight_time *= motion.truncate_to_length(fuel);
+                    }
+                    if effects.has(StatusKind::Slow) {
+                        flight_time *= SLOW_FACTOR;
+                    }
+
+                    let ricochet = launcher.0 == owner.0
+                        && ricochets
+                            .iter()
+                            .position(|r| *r == owner.0)
+                            .map(|index| ricochets.swap_remove(index))
+                            .is_some();
+
+                    let mut projectile = spawn_projectile(
+                        node,
+                        &images,
+                        &audio,
+                        &sounds,
+                        Projectile::new(kind).with_on_expire(rules.on_expire),
+                        motion,
+                        ProjectileSize::for_rules(&rules),
+                        flight_time,
+                        *launcher,
+                    );
+                    if ricochet {
+                        projectile.insert(Bounces::new(rules.ricochet_bounces.unwrap_or(0)));
+                    }
+                    if charge > 0.0 {
+                        projectile.insert(Charge(charge));
+                    }
+                    if let Some(style) = style {
+                        projectile.insert(StylePoints(style));
+                    }
+                    let projectile = projectile.id();
+                    if ricochet {
+                        spawn_bounce_counter(node, &fonts, projectile);
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Despawns projectiles that are still around, such as when leaving the firing phase early
+pub fn despawn_projectiles(
+    mut commands: Commands,
+    projectiles: Query<(Entity, &ProjectileChannel), With<Projectile>>,
+    audio: Res<Audio>,
+) {
+    for (entity, channel) in projectiles.iter() {
+        audio.stop_channel(&channel.0);
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn move_projectiles(
+    mut projectiles: Query<
+        (
+            &mut Transform,
+            &mut RigidBodyPositionComponent,
+            &mut MotionModel,
+            &mut Timer,
+            Entity,
+            &RigidBodyCollidersComponent,
+            &ProjectileChannel,
+            Option<&mut Bounces>,
+            Option<&Dilation>,
+            &Owner,
+        ),
+        With<Projectile>,
+    >,
+    mut last_ends: Query<(&Owner, &mut LastShotEnd), With<PlayerLabel>>,
+    time: Res<GameTime>,
+    mut buttons_enabled: ResMut<ButtonsEnabled>,
+    mut expired_events: EventWriter<ProjectileExpired>,
+    mut destroyed_events: EventWriter<ProjectileDestroyed>,
+    mut flight_notes: EventWriter<FlightNote>,
+    audio: Res<Audio>,
+    game: Res<Game>,
+) {
+    let mut projectiles_exist = false;
+    for (
+        mut transform,
+        mut body_position,
+        mut motion,
+        mut timer,
+        entity,
+        colliders,
+        channel,
+        bounces,
+        dilation,
+        owner,
+    ) in projectiles.iter_mut()
+    {
+        // The colliders are missing for 1 frame, so skip that frame
+        if colliders.0 .0.is_empty() {
+            return;
+        }
+
+        projectiles_exist = true;
+        timer.tick(time.delta().mul_f32(dilation.map_or(1.0, |d| d.rate)));
+
+        let curr_pos = transform.translation.xy();
+        // Rockets fizzle out where their path stops being defined, like at a division by zero
+        let mut next_pos = match motion.try_position(timer.percent()) {
+            Some(pos) => pos,
+            None => {
+                flight_notes.send(FlightNote {
+                    projectile: entity,
+                    param: timer.percent(),
+                    position: curr_pos,
+                    kind: NoteKind::Fizzled,
+                });
+                destroyed_events.send(ProjectileDestroyed { projectile: entity });
+                continue;
+            }
+        };
+        if timer.just_finished() {
+            expired_events.send(ProjectileExpired { projectile: entity });
+        }
+        if let Some(mut bounces) = bounces {
+            let bounces_before = bounces.left;
+            next_pos =
+                bounces.bounce_off_edges(&mut motion, next_pos, timer.percent(), game.half_size());
+            if bounces.left < bounces_before {
+                flight_notes.send(FlightNote {
+                    projectile: entity,
+                    param: timer.percent(),
+                    position: next_pos,
+                    kind: NoteKind::Bounce,
+                });
+            }
+        }
+        if next_pos - curr_pos != Vec2::ZERO {
+            transform.rotation =
+                Quat::from_rotation_arc_2d(Vec2::X, (next_pos - curr_pos).normalize());
+        }
+        transform.translation = next_pos.extend(RenderLayer::Rocket.z());
+        body_position.0.next_position =
+            Isometry::new(next_pos.into(), transform.rotation.to_axis_angle().1);
+        if timer.just_finished() {
+            for (_, mut last_end) in last_ends.iter_mut().filter(|(o, _)| o.0 == owner.0) {
+                last_end.0 = Some(next_pos);
+            }
+        }
+
+        // Sound modulation
+        const MAX_VOLUME_SPEED: f32 = 15.0 / ROCKET_TIME;
+        const MAX_VOLUME: f32 = 3.0;
+        let half_width = game.half_size().x;
+        if time.delta() == Duration::ZERO {
+            continue;
+        }
+        let speed = ((next_pos - curr_pos).length() / time.delta_seconds()).min(MAX_VOLUME_SPEED);
+        audio.set_panning_in_channel((next_pos.x - -half_width) / (2.0 * half_width), &channel.0);
+        audio.set_volume_in_channel(speed / MAX_VOLUME_SPEED * MAX_VOLUME, &channel.0);
+    }
+
+    if !projectiles_exist {
+        buttons_enabled.0 = true;
+    }
+}
+
+/// Applies the on-expire behavior of projectiles whose flight time is up
+pub fn expire_projectiles(
+    mut expired_events: EventReader<ProjectileExpired>,
+    mut projectiles: Query<(
+        &Projectile,
+        &Transform,
+        &mut Timer,
+        &mut MotionModel,
+        &mut PrevParam,
+        Option<&Charge>,
+    )>,
+    others: Query<(Entity, &Transform), With<Projectile>>,
+    mut flight_notes: EventWriter<FlightNote>,
+    mut destroyed_events: EventWriter<ProjectileDestroyed>,
+    field: Query<Entity, With<Field>>,
+    images: Res<Assets<Image>>,
+    mut commands: Commands,
+) {
+    for event in expired_events.iter() {
+        let (projectile, transform, mut timer, mut motion, mut prev_param, charge) =
+            if let Ok(p) = projectiles.get_mut(event.projectile) { p } else { continue };
+        let position = transform.translation.xy();
+        flight_notes.send(FlightNote {
+            projectile: event.projectile,
+            param: 1.0,
+            position,
+            kind: NoteKind::Expired,
+        });
+
+        match projectile.on_expire {
+            OnExpire::Despawn => {}
+            OnExpire::Explode => {
+                let radius = blast_radius(charge.map_or(0.0, |charge| charge.0));
+                for (other, other_transform) in others.iter() {
+                    let in_blast = other_transform.translation.xy().distance(position) < radius;
+                    if other != event.projectile && in_blast {
+                        destroyed_events.send(ProjectileDestroyed { projectile: other });
+                    }
+                }
+                commands.entity(field.single()).with_children(|node| {
+                    node.spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::splat(2.0 * radius)),
+                            ..Default::default()
+                        },
+                        texture: images.get_handle(asset::Boom),
+                        transform: Transform::from_translation(
+                            position.extend(RenderLayer::Boom.z()),
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(DespawnOnExit(PlayState::Fire));
+                });
+            }
+            OnExpire::DropMine => {
+                commands.entity(field.single()).with_children(|node| {
+                    spawn_item(
+                        node,
+                        &images,
+                        position.extend(RenderLayer::Mine.z()),
+                        &ITEM_MINE,
+                        0,
+                    )
+                    .insert(Mine);
+                });
+            }
+            OnExpire::DropBall => {
+                commands.entity(field.single()).with_children(|node| {
+                    spawn_item(
+                        node,
+                        &images,
+                        position.extend(RenderLayer::Pickup.z()),
+                        &ITEM_BALL,
+                        0,
+                    )
+                    .insert(Ball);
+                });
+            }
+            // Comes back once, so a returned projectile despawns like any other
+            OnExpire::Return if !motion.is_reversed() => {
+                motion.reverse();
+                timer.reset();
+                // Keeps collisions from sweeping the whole path on the frame it turns around
+                prev_param.0 = 0.0;
+                continue;
+            }
+            OnExpire::Return => {}
+        }
+        commands.entity(event.projectile).despawn_recursive();
+    }
+}
+
+/// Despawns projectiles that got destroyed
+pub fn destroy_projectiles(
+    mut destroyed_events: EventReader<ProjectileDestroyed>,
+    projectiles: Query<(), With<Projectile>>,
+    mut commands: Commands,
+) {
+    for event in destroyed_events.iter() {
+        if projectiles.get(event.projectile).is_ok() {
+            commands.entity(event.projectile).despawn_recursive();
+        }
+    }
+}
+
+pub fn stop_projectile_sounds(
+    mut collisions: EventReader<ProjectileCollision>,
+    mut expirations: EventReader<ProjectileExpired>,
+    mut destructions: EventReader<ProjectileDestroyed>,
+    audio: Res<Audio>,
+    channels: Query<&ProjectileChannel>,
+) {
+    let mut num_stopped_projectiles = 0;
+
+    for collision in collisions.iter() {
+        for entity in [collision.projectile, collision.other] {
+            if let Ok(channel) = channels.get(entity) {
+                audio.stop_channel(&channel.0);
+                num_stopped_projectiles += 1;
+            }
+        }
+    }
+
+    for expiration in expirations.iter() {
+        if let Ok(channel) = channels.get(expiration.projectile) {
+            audio.stop_channel(&channel.0);
+        }
+    }
+
+    for destruction in destructions.iter() {
+        if let Ok(channel) = channels.get(destruction.projectile) {
+            audio.stop_channel(&channel.0);
+            num_stopped_projectiles += 1;
+        }
+    }
+
+    if num_stopped_projectiles > 0 && num_stopped_projectiles == channels.iter().len() {
+        audio.stop_channel(&AudioChannel::new("Fire".into()));
+    }
+}
@@ -0,0 +1,155 @@
+//! A second window that only shows the field, for putting a local tournament on a projector
+//! while the players keep the main window, with its textboxes, to themselves.
+//! Bevy draws each 2D camera through a driver node in the render graph, and only has one
+//! for the main camera, so the projector camera gets its own.
+
+use bevy::{
+    core_pipeline::{draw_2d_graph, node, Transparent2d},
+    prelude::*,
+    render::{
+        camera::{ActiveCameras, ExtractedCameraNames, ScalingMode},
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotValue},
+        render_phase::RenderPhase,
+        renderer::RenderContext,
+        RenderApp, RenderStage,
+    },
+    window::{CreateWindow, WindowCreated, WindowId},
+};
+
+use crate::Game;
+
+/// Name of the projector camera, which the render graph looks it up by
+const PROJECTOR_CAMERA: &str = "projector";
+const PROJECTOR_PASS_DRIVER: &str = "projector_pass_driver";
+/// Opens the projector window
+const PROJECTOR_KEY: KeyCode = KeyCode::F8;
+
+/// Labels the camera of the projector window
+#[derive(Component)]
+pub struct ProjectorCamera;
+
+/// The projector window, if it's been asked for
+#[derive(Debug, Default)]
+pub struct Projector {
+    window: Option<WindowId>,
+}
+
+/// Lets a projector window be opened. It only works natively, since the web build has
+/// one canvas.
+pub struct ProjectorPlugin;
+
+impl Plugin for ProjectorPlugin {
+    fn build(&self, app: &mut App) {
+        if cfg!(target_family = "wasm") {
+            return;
+        }
+
+        app.insert_resource(Projector::default())
+            .add_system(open_projector)
+            .add_system(spawn_projector_camera)
+            .add_system(fit_projector);
+        app.world.get_resource_mut::<ActiveCameras>().unwrap().add(PROJECTOR_CAMERA);
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.add_system_to_stage(RenderStage::Extract, extract_projector_phases);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(PROJECTOR_PASS_DRIVER, ProjectorPassDriver);
+        graph.add_node_edge(node::MAIN_PASS_DEPENDENCIES, PROJECTOR_PASS_DRIVER).unwrap();
+    }
+}
+
+/// Opens the projector window when its key is pressed. Bevy can't close windows,
+/// so it stays open once it's there.
+fn open_projector(
+    keys: Res<Input<KeyCode>>,
+    mut projector: ResMut<Projector>,
+    mut create_window_events: EventWriter<CreateWindow>,
+) {
+    if !keys.just_pressed(PROJECTOR_KEY) || projector.window.is_some() {
+        return;
+    }
+
+    let id = WindowId::new();
+    projector.window = Some(id);
+    create_window_events.send(CreateWindow {
+        id,
+        descriptor: WindowDescriptor {
+            title: "Graph War (projector)".to_owned(),
+            ..Default::default()
+        },
+    });
+}
+
+/// Gives the projector window its camera once the window exists
+fn spawn_projector_camera(
+    mut commands: Commands,
+    projector: Res<Projector>,
+    mut created_events: EventReader<WindowCreated>,
+) {
+    for event in created_events.iter() {
+        if Some(event.id) != projector.window {
+            continue;
+        }
+
+        let mut camera = OrthographicCameraBundle::new_2d();
+        camera.camera.name = Some(PROJECTOR_CAMERA.to_owned());
+        camera.camera.window = event.id;
+        camera.orthographic_projection.scaling_mode = ScalingMode::None;
+        commands.spawn_bundle(camera).insert(ProjectorCamera);
+    }
+}
+
+/// Fits the whole field in the projector window, centered, whatever shape the window is
+fn fit_projector(
+    game: Res<Game>,
+    windows: Res<Windows>,
+    mut camera: Query<(&Camera, &mut OrthographicProjection), With<ProjectorCamera>>,
+) {
+    let (camera, mut projection) = if let Ok(camera) = camera.get_single_mut() {
+        camera
+    } else {
+        return;
+    };
+    let window = if let Some(window) = windows.get(camera.window) { window } else { return };
+    let aspect_ratio = window.width() / window.height();
+
+    let half_size = if aspect_ratio >= game.aspect {
+        Vec2::new(aspect_ratio, 1.0)
+    } else {
+        Vec2::new(game.aspect, game.aspect / aspect_ratio)
+    };
+    if projection.right != half_size.x || projection.top != half_size.y {
+        projection.left = -half_size.x;
+        projection.right = half_size.x;
+        projection.bottom = -half_size.y;
+        projection.top = half_size.y;
+    }
+    if projection.scale != game.scale {
+        projection.scale = game.scale;
+    }
+}
+
+/// Gives the projector camera a phase for 2D things to be drawn in, like the main camera has
+fn extract_projector_phases(mut commands: Commands, active_cameras: Res<ActiveCameras>) {
+    if let Some(entity) = active_cameras.get(PROJECTOR_CAMERA).and_then(|camera| camera.entity) {
+        commands.get_or_spawn(entity).insert(RenderPhase::<Transparent2d>::default());
+    }
+}
+
+/// Draws what the projector camera sees
+struct ProjectorPassDriver;
+
+impl Node for ProjectorPassDriver {
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        _render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let cameras = world.get_resource::<ExtractedCameraNames>().unwrap();
+        if let Some(camera) = cameras.entities.get(PROJECTOR_CAMERA) {
+            graph.run_sub_graph(draw_2d_graph::NAME, vec![SlotValue::Entity(*camera)])?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+/// Average frame time above which detail drops, in seconds
+const SLOW_FRAME_TIME: f32 = 1.0 / 40.0;
+/// Average frame time below which detail comes back, in seconds.
+/// It's lower than `SLOW_FRAME_TIME` so the detail doesn't flicker back and forth.
+const FAST_FRAME_TIME: f32 = 1.0 / 55.0;
+/// How much each frame moves the average frame time
+const SMOOTHING: f32 = 0.05;
+/// Points sampled along the shot preview at full detail
+const PREVIEW_SAMPLES: usize = 512;
+/// Points sampled along the shot preview at low detail
+const LOW_PREVIEW_SAMPLES: usize = 128;
+/// Shortest trail segment drawn at low detail, in field units.
+/// Shorter moves wait to become part of a longer segment.
+const LOW_TRAIL_SEGMENT: f32 = 0.1;
+
+/// How much detail previews and trails get, based on recent frame times,
+/// so slow hardware like some running the web build stays smooth
+#[derive(Debug)]
+pub struct RenderQuality {
+    /// Recent average frame time, in seconds
+    frame_time: f32,
+    low: bool,
+}
+
+impl Default for RenderQuality {
+    fn default() -> Self {
+        Self { frame_time: 1.0 / 60.0, low: false }
+    }
+}
+
+impl RenderQuality {
+    /// Number of points to sample along the shot preview
+    pub fn preview_samples(&self) -> usize {
+        if self.low {
+            LOW_PREVIEW_SAMPLES
+        } else {
+            PREVIEW_SAMPLES
+        }
+    }
+
+    /// Shortest trail segment to draw, in field units
+    pub fn min_trail_segment(&self) -> f32 {
+        if self.low {
+            LOW_TRAIL_SEGMENT
+        } else {
+            0.0
+        }
+    }
+}
+
+pub fn track_frame_time(time: Res<Time>, mut quality: ResMut<RenderQuality>) {
+    let delta = time.delta_seconds();
+    // Skip hitches like loading and window dragging, which aren't about rendering
+    if delta <= 0.0 || delta > 0.5 {
+        return;
+    }
+
+    quality.frame_time += (delta - quality.frame_time) * SMOOTHING;
+    let low = if quality.low {
+        quality.frame_time > FAST_FRAME_TIME
+    } else {
+        quality.frame_time > SLOW_FRAME_TIME
+    };
+    if low != quality.low {
+        log::info!(
+            "Frames take {:.1} ms on average, so detail is now {}",
+            quality.frame_time * 1000.0,
+            if low { "low" } else { "full" }
+        );
+        quality.low = low;
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use decorum::Total;
+use egui::Align2;
+
+use crate::ui::{
+    entry_box_index, ButtonsEnabled, FocusedEntryBox, FunctionEntryBox, FunctionX, FunctionY,
+    Textbox, TextboxesEditable,
+};
+
+/// Pages of tokens the radial menu can type. The shoulder buttons flip between them.
+const PAGES: [&[&str]; 3] = [
+    &["0", "1", "2", "3", "4", "5", "6", "7", "8", "9", ".", "pi"],
+    &["t", "+", "-", "*", "/", "^", "(", ")", ",", " "],
+    &["sin(", "cos(", "tan(", "sqrt(", "abs(", "exp(", "ln(", "floor(", "min(", "max(", "if("],
+];
+
+/// How far the stick has to be pushed to pick a token
+const DEADZONE: f32 = 0.5;
+/// Distance from the center of the menu to its tokens, in points
+const RADIUS: f32 = 90.0;
+const FONT_SIZE: f32 = 18.0;
+
+/// State of the gamepad formula builder, a ring of tokens picked with the left stick.
+/// The North button opens and closes it, South types the picked token, East deletes a token,
+/// and West switches which textbox gets typed in.
+#[derive(Debug, Default)]
+pub struct RadialMenu {
+    open: bool,
+    page: usize,
+    /// Index into the page of the token the stick points at
+    picked: Option<usize>,
+    /// Tokens typed in each textbox since the menu was opened, so East can delete whole tokens
+    typed: [Vec<&'static str>; 3],
+}
+
+/// Token of a page the stick points at, starting at the top and going clockwise
+fn picked_token(stick: Vec2, num_tokens: usize) -> Option<usize> {
+    if stick.length() < DEADZONE {
+        return None;
+    }
+    let angle = stick.x.atan2(stick.y).rem_euclid(std::f32::consts::TAU);
+    let slice = std::f32::consts::TAU / num_tokens as f32;
+    Some(((angle / slice).round() as usize) % num_tokens)
+}
+
+/// Lets players type functions with a gamepad instead of a keyboard
+pub fn use_radial_menu(
+    mut menu: ResMut<RadialMenu>,
+    mut focused: ResMut<FocusedEntryBox>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut egui_ctx: ResMut<EguiContext>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    textboxes_editable: Res<TextboxesEditable>,
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+) {
+    if !buttons_enabled.0 || !textboxes_editable.0 {
+        *menu = RadialMenu::default();
+        return;
+    }
+
+    // Any connected gamepad can type, since only the current player can edit anyway
+    let pressed = |button_type| {
+        gamepads.iter().any(|gamepad| buttons.just_pressed(GamepadButton(*gamepad, button_type)))
+    };
+    if pressed(GamepadButtonType::North) {
+        let open = !menu.open;
+        *menu = RadialMenu { open, ..Default::default() };
+    }
+    if !menu.open {
+        return;
+    }
+
+    if pressed(GamepadButtonType::LeftTrigger) {
+        menu.page = (menu.page + PAGES.len() - 1) % PAGES.len();
+    }
+    if pressed(GamepadButtonType::RightTrigger) {
+        menu.page = (menu.page + 1) % PAGES.len();
+    }
+    if pressed(GamepadButtonType::West) {
+        focused.0 = (focused.0 + 1) % 3;
+    }
+
+    let page = PAGES[menu.page];
+    let stick = gamepads
+        .iter()
+        .map(|gamepad| {
+            let axis = |axis_type| axes.get(GamepadAxis(*gamepad, axis_type)).unwrap_or(0.0);
+            Vec2::new(axis(GamepadAxisType::LeftStickX), axis(GamepadAxisType::LeftStickY))
+        })
+        .max_by_key(|stick| Total::from(stick.length()))
+        .unwrap_or(Vec2::ZERO);
+    menu.picked = picked_token(stick, page.len());
+
+    let target = focused.0;
+    let typing = pressed(GamepadButtonType::South).then(|| menu.picked).flatten();
+    let deleting = pressed(GamepadButtonType::East);
+    for (mut textbox, x, y) in entry_boxes.iter_mut() {
+        if entry_box_index(x, y) != target {
+            continue;
+        }
+        if let Some(index) = typing {
+            textbox.text.push_str(page[index]);
+            menu.typed[target].push(page[index]);
+        } else if deleting {
+            // Tokens typed with the menu get deleted whole, and anything else a character at a time
+            let len = match menu.typed[target].pop() {
+                Some(token) if textbox.text.ends_with(token) => token.len(),
+                _ => textbox.text.chars().last().map_or(0, char::len_utf8),
+            };
+            let new_len = textbox.text.len() - len;
+            textbox.text.truncate(new_len);
+        }
+    }
+
+    let labels = ["x(t)", "y(t)", "where"];
+    egui::Area::new("radial menu").anchor(Align2::CENTER_CENTER, [0.0, 0.0]).show(
+        egui_ctx.ctx_mut(),
+        |ui| {
+            let (rect, _) = ui.allocate_exact_size(
+                egui::vec2(RADIUS * 2.0 + 4.0 * FONT_SIZE, RADIUS * 2.0 + 4.0 * FONT_SIZE),
+                egui::Sense::hover(),
+            );
+            let painter = ui.painter();
+            let center = rect.center();
+            let font = egui::FontId { family: egui::FontFamily::Monospace, size: FONT_SIZE };
+            painter.circle_filled(
+                center,
+                RADIUS + 1.5 * FONT_SIZE,
+                egui::Color32::from_white_alpha(220),
+            );
+            painter.text(
+                center,
+                Align2::CENTER_CENTER,
+                format!("{} ({}/{})", labels[target], menu.page + 1, PAGES.len()),
+                font.clone(),
+                egui::Color32::DARK_GRAY,
+            );
+
+            for (index, token) in page.iter().enumerate() {
+                let angle = std::f32::consts::TAU * index as f32 / page.len() as f32;
+                // Screen y points down, so the top of the ring has negative y
+                let pos = center + RADIUS * egui::vec2(angle.sin(), -angle.cos());
+                let color = if menu.picked == Some(index) {
+                    painter.circle_filled(pos, FONT_SIZE, egui::Color32::from_rgb(26, 102, 204));
+                    egui::Color32::WHITE
+                } else {
+                    egui::Color32::BLACK
+                };
+                let token = if *token == " " { "␣" } else { token };
+                painter.text(pos, Align2::CENTER_CENTER, token, font.clone(), color);
+            }
+        },
+    );
+}
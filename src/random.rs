@@ -12,7 +12,7 @@ pub struct RectRegion {
 #[derive(Clone, Copy, Debug)]
 pub struct ScaledRectRegion<'a> {
     region: &'a RectRegion,
-    scale: f32,
+    scale: Vec2,
 }
 
 impl RectRegion {
@@ -34,7 +34,8 @@ impl RectRegion {
         Self { rects }
     }
 
-    pub fn scaled(&self, scale: f32) -> ScaledRectRegion {
+    /// Scales the region along each axis
+    pub fn scaled(&self, scale: Vec2) -> ScaledRectRegion {
         ScaledRectRegion { region: self, scale }
     }
 }
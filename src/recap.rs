@@ -0,0 +1,148 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_egui::EguiContext;
+use decorum::Total;
+use egui::Align2;
+use fxhash::FxHashMap;
+
+use crate::{
+    breakdown::{FlightNote, NoteKind},
+    projectile::{MotionModel, ProjectileDestroyed, SamplePath},
+    Game, Owner, PlayerLabel,
+};
+
+/// Most lines the recap keeps
+const MAX_LINES: usize = 200;
+/// Longest a shot gets written in the recap, in characters
+const MAX_SHOT_LEN: usize = 40;
+
+/// What happened to a rocket so far
+#[derive(Debug)]
+struct Flight {
+    player_index: u32,
+    shot: String,
+    balls: u32,
+}
+
+/// Log of what happened during the match, so players can look back at it while entering functions
+#[derive(Debug, Default)]
+pub struct RecapLog {
+    lines: Vec<String>,
+    flights: FxHashMap<Entity, Flight>,
+    round_index: u32,
+}
+
+impl RecapLog {
+    fn push(&mut self, line: String) {
+        if self.lines.len() >= MAX_LINES {
+            self.lines.remove(0);
+        }
+        self.lines.push(line);
+    }
+
+    /// Writes the line for a rocket that's done flying
+    fn finish(&mut self, projectile: Entity, ending: &str) {
+        if let Some(flight) = self.flights.remove(&projectile) {
+            let result = match flight.balls {
+                0 => "missed".to_owned(),
+                1 => "collected a ball".to_owned(),
+                n => format!("collected {} balls", n),
+            };
+            self.push(format!(
+                "P{} fired {}, {}{}",
+                flight.player_index + 1,
+                flight.shot,
+                result,
+                ending
+            ));
+        }
+    }
+}
+
+/// Short description of a shot, like `(t, sin(3t))`
+fn describe_shot(motion: &MotionModel) -> String {
+    let parametric = motion.equation();
+    let source = |s: &Option<String>| s.as_deref().unwrap_or("?").trim().to_owned();
+    let shot = format!("({}, {})", source(&parametric.source_x), source(&parametric.source_y));
+    if shot.chars().count() > MAX_SHOT_LEN {
+        format!("{}…", shot.chars().take(MAX_SHOT_LEN - 1).collect::<String>())
+    } else {
+        shot
+    }
+}
+
+pub fn clear_recap(mut recap: ResMut<RecapLog>) {
+    *recap = RecapLog::default();
+}
+
+/// Adds lines to the recap from what happens to rockets
+pub fn record_flights(
+    mut recap: ResMut<RecapLog>,
+    game: Res<Game>,
+    new_rockets: Query<(Entity, &Owner, &MotionModel), Added<SamplePath>>,
+    players: Query<(&Owner, &Transform), With<PlayerLabel>>,
+    mut notes: EventReader<FlightNote>,
+    mut destroyed_events: EventReader<ProjectileDestroyed>,
+) {
+    if recap.round_index != game.round_index {
+        recap.round_index = game.round_index;
+        recap.push(format!("-- Round {} --", game.round_index));
+    }
+
+    for (projectile, owner, motion) in new_rockets.iter() {
+        let flight = Flight { player_index: owner.0, shot: describe_shot(motion), balls: 0 };
+        recap.flights.insert(projectile, flight);
+    }
+
+    for note in notes.iter() {
+        match note.kind {
+            NoteKind::Hit => {
+                if let Some(flight) = recap.flights.get_mut(&note.projectile) {
+                    flight.balls += 1;
+                }
+            }
+            NoteKind::Bounce => {}
+            NoteKind::Destroyed => {
+                let nearest = players.iter().min_by_key(|(_, transform)| {
+                    Total::from(transform.translation.xy().distance(note.position))
+                });
+                let ending = nearest
+                    .map(|(owner, _)| format!(", and detonated near P{}", owner.0 + 1))
+                    .unwrap_or_default();
+                recap.finish(note.projectile, &ending);
+            }
+            NoteKind::Expired => recap.finish(note.projectile, ""),
+            NoteKind::Fizzled => recap.finish(note.projectile, ", and fizzled out"),
+        }
+    }
+
+    for event in destroyed_events.iter() {
+        recap.finish(event.projectile, ", and got destroyed");
+    }
+}
+
+/// Writes down rockets that were still flying when the round ended
+pub fn finish_recap_flights(mut recap: ResMut<RecapLog>) {
+    let projectiles = recap.flights.keys().copied().collect::<Vec<_>>();
+    for projectile in projectiles {
+        recap.finish(projectile, "");
+    }
+}
+
+/// Shows the recap in a collapsible window that can be scrolled through
+pub fn show_recap(recap: Res<RecapLog>, mut egui_ctx: ResMut<EguiContext>) {
+    if recap.lines.is_empty() {
+        return;
+    }
+
+    egui::Window::new("Recap")
+        .anchor(Align2::RIGHT_TOP, [-8.0, 8.0])
+        .default_open(false)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                for line in &recap.lines {
+                    ui.label(line);
+                }
+            });
+        });
+}
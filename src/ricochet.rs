@@ -0,0 +1,110 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{asset, projectile::MotionModel, RelativeTextSize, RenderLayer};
+
+/// Number of ricochet pickups spawned in a normal round, if the rules have them
+pub const NUM_RICOCHET_PICKUPS: usize = 2;
+/// Number of round starts an unused ricochet pickup survives
+pub const RICOCHET_ROUNDS: u32 = 3;
+
+/// Labels a ricochet pickup. The player whose rocket collects it
+/// gets a `StatusKind::Ricochet` stack, used up by their next rocket.
+#[derive(Component)]
+pub struct Ricochet;
+
+/// Bounces a projectile has left
+#[derive(Component)]
+pub struct Bounces {
+    pub left: u32,
+    /// Obstacle the projectile last bounced off, which it can't hit again right away
+    pub last_obstacle: Option<Entity>,
+}
+
+impl Bounces {
+    pub fn new(left: u32) -> Self {
+        Self { left, last_obstacle: None }
+    }
+
+    /// Reflects the rest of a projectile's path at a contact point, if there are bounces left.
+    /// Returns whether the projectile bounced.
+    pub fn bounce(
+        &mut self,
+        motion: &mut MotionModel,
+        contact: Vec2,
+        normal: Vec2,
+        obstacle: Option<Entity>,
+    ) -> bool {
+        if self.left == 0 || normal == Vec2::ZERO {
+            return false;
+        }
+        self.left -= 1;
+        self.last_obstacle = obstacle;
+        motion.reflect(contact, normal);
+        true
+    }
+
+    /// Bounces a projectile off the edges of the field it's about to leave.
+    /// Returns the position the projectile should move to instead.
+    pub fn bounce_off_edges(
+        &mut self,
+        motion: &mut MotionModel,
+        next_pos: Vec2,
+        t: f32,
+        half_size: Vec2,
+    ) -> Vec2 {
+        let mut pos = next_pos;
+        for axis in [Vec2::X, Vec2::Y] {
+            let coord = pos.dot(axis);
+            let bound = half_size.dot(axis);
+            if coord.abs() > bound {
+                let edge = axis * coord.signum() * bound;
+                if self.bounce(motion, edge, axis, None) {
+                    pos =
+                        motion.try_position(t).unwrap_or_else(|| pos.clamp(-half_size, half_size));
+                }
+            }
+        }
+        pos
+    }
+}
+
+/// Shows the bounces a projectile has left
+#[derive(Component)]
+pub struct BounceCounter(pub Entity);
+
+/// Spawns the bounce counter of a projectile
+pub fn spawn_bounce_counter(node: &mut ChildBuilder, fonts: &Assets<Font>, projectile: Entity) {
+    node.spawn_bundle(Text2dBundle {
+        text: Text::with_section(
+            "",
+            TextStyle { color: Color::WHITE, font: fonts.get_handle(asset::Font), font_size: 0.0 },
+            TextAlignment { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
+        ),
+        transform: Transform::from_xyz(0.0, 0.0, RenderLayer::Score.z()),
+        ..Default::default()
+    })
+    .insert(RelativeTextSize(0.2))
+    .insert(BounceCounter(projectile));
+}
+
+/// Moves bounce counters along with their projectiles, and removes them once they're gone
+pub fn update_bounce_counters(
+    mut commands: Commands,
+    mut counters: Query<(Entity, &BounceCounter, &mut Text, &mut Transform)>,
+    projectiles: Query<(&Bounces, &Transform), Without<BounceCounter>>,
+) {
+    for (entity, counter, mut text, mut transform) in counters.iter_mut() {
+        match projectiles.get(counter.0) {
+            Ok((bounces, projectile_transform)) if bounces.left > 0 => {
+                let value = bounces.left.to_string();
+                if text.sections[0].value != value {
+                    text.sections[0].value = value;
+                }
+                transform.translation = (projectile_transform.translation.xy()
+                    + Vec2::new(0.0, 0.25))
+                .extend(RenderLayer::Score.z());
+            }
+            _ => commands.entity(entity).despawn_recursive(),
+        }
+    }
+}
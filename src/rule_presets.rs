@@ -0,0 +1,109 @@
+//! Named rule presets that can be picked on the menu. Natively, each `.txt` file in
+//! `assets/rules` is a preset, in the format `parse_preset` reads, so new presets don't need code.
+//! On the web, the presets that ship with the game are built in.
+
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use egui::Align2;
+
+use crate::rules::{parse_preset, GameRules, RulePreset};
+
+/// Folder with the rule presets, inside the asset folder
+pub const RULES_DIR: &str = "rules";
+/// Name of the preset picked when the game starts
+const DEFAULT_PRESET: &str = "Classic";
+
+/// The rule presets, and which one is picked
+#[derive(Debug, Default)]
+pub struct RulePresets {
+    pub presets: Vec<RulePreset>,
+    pub picked: Option<usize>,
+}
+
+/// Reads the text of each preset file, with the file's name, sorted by name
+#[cfg(not(target_family = "wasm"))]
+fn read_presets() -> Vec<(String, String)> {
+    let dir = std::path::Path::new("assets").join(RULES_DIR);
+    let mut files = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "txt"))
+            .filter_map(|path| {
+                let text = std::fs::read_to_string(&path).ok()?;
+                Some((path.file_name()?.to_str()?.to_owned(), text))
+            })
+            .collect::<Vec<_>>(),
+        Err(_) => return vec![],
+    };
+    files.sort();
+    files
+}
+
+#[cfg(target_family = "wasm")]
+fn read_presets() -> Vec<(String, String)> {
+    [
+        ("chaos.txt", include_str!("../assets/rules/chaos.txt")),
+        ("classic.txt", include_str!("../assets/rules/classic.txt")),
+        ("precision.txt", include_str!("../assets/rules/precision.txt")),
+    ]
+    .into_iter()
+    .map(|(file, text)| (file.to_owned(), text.to_owned()))
+    .collect()
+}
+
+/// Reads the rule presets and picks the default one
+pub fn load_rule_presets(mut commands: Commands, mut rules: ResMut<GameRules>) {
+    let presets = read_presets()
+        .into_iter()
+        .filter_map(|(file, text)| match parse_preset(&text) {
+            Ok(mut preset) => {
+                if preset.name.is_empty() {
+                    preset.name = file.trim_end_matches(".txt").to_owned();
+                }
+                Some(preset)
+            }
+            Err(err) => {
+                log::warn!("Skipped rule preset {}: {}", file, err);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let picked = presets.iter().position(|preset| preset.name == DEFAULT_PRESET);
+    if let Some(picked) = picked {
+        *rules = presets[picked].rules.clone();
+    }
+    commands.insert_resource(RulePresets { presets, picked });
+}
+
+/// Lists the rule presets on the menu, and lets one be picked for the next match
+pub fn show_rule_presets(
+    mut egui_ctx: ResMut<EguiContext>,
+    mut presets: ResMut<RulePresets>,
+    mut rules: ResMut<GameRules>,
+) {
+    if presets.presets.is_empty() {
+        return;
+    }
+
+    let mut picked = presets.picked;
+    egui::Window::new("Rules").anchor(Align2::LEFT_BOTTOM, [8.0, -8.0]).resizable(false).show(
+        egui_ctx.ctx_mut(),
+        |ui| {
+            for (index, preset) in presets.presets.iter().enumerate() {
+                ui.radio_value(&mut picked, Some(index), &preset.name);
+                if !preset.description.is_empty() {
+                    ui.label(&preset.description);
+                }
+            }
+        },
+    );
+
+    if picked != presets.picked {
+        presets.picked = picked;
+        if let Some(picked) = picked {
+            *rules = presets.presets[picked].rules.clone();
+        }
+    }
+}
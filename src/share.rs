@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+
+use crate::ui::{entry_box_index, ButtonsEnabled, FunctionEntryBox, FunctionX, FunctionY, Textbox};
+
+/// What share codes start with, so they can be told apart from functions
+const CODE_PREFIX: &str = "gw:";
+/// Separates the functions in a share code. It can't be typed into a textbox.
+const SEPARATOR: char = '\u{1f}';
+/// URL-safe base64 alphabet
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity((bytes.len() * 4 + 2) / 3);
+    for chunk in bytes.chunks(3) {
+        let bits =
+            chunk.iter().enumerate().fold(0u32, |bits, (i, b)| bits | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            result.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    result
+}
+
+fn decode_base64(code: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(code.len() * 3 / 4);
+    for chunk in code.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut bits = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|a| a == c)? as u32;
+            bits |= value << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            result.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(result)
+}
+
+/// Share code for x(t), y(t), and a where clause
+pub fn encode(functions: &[String; 3]) -> String {
+    format!("{}{}", CODE_PREFIX, encode_base64(functions.join(&SEPARATOR.to_string()).as_bytes()))
+}
+
+/// Functions in a share code, or `None` if it isn't one
+pub fn decode(code: &str) -> Option<[String; 3]> {
+    let bytes = decode_base64(code.trim().strip_prefix(CODE_PREFIX)?)?;
+    let text = String::from_utf8(bytes).ok()?;
+    let mut functions = text.split(SEPARATOR).map(str::to_owned);
+    let result = [functions.next()?, functions.next()?, functions.next()?];
+    functions.next().is_none().then(|| result)
+}
+
+/// Button that copies a share code for the functions in the textboxes
+#[derive(Component)]
+pub struct CopyCodeButton;
+
+pub fn update_copy_code_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<CopyCodeButton>)>,
+    entry_boxes: Query<(&Textbox, Option<&FunctionX>, Option<&FunctionY>), With<FunctionEntryBox>>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    mut egui_ctx: ResMut<EguiContext>,
+) {
+    if !buttons_enabled.0 || !buttons.iter().any(|i| *i == Interaction::Clicked) {
+        return;
+    }
+
+    let mut functions: [String; 3] = Default::default();
+    for (textbox, x, y) in entry_boxes.iter() {
+        functions[entry_box_index(x, y)] = textbox.text.clone();
+    }
+    let code = encode(&functions);
+    log::info!("Share code: {}", code);
+    egui_ctx.ctx_mut().output().copied_text = code;
+}
+
+/// Replaces a share code pasted into any entry textbox with the functions in it
+pub fn import_share_codes(
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+) {
+    let functions = if let Some(functions) = entry_boxes
+        .iter()
+        .filter(|(textbox, _, _)| textbox.text.trim_start().starts_with(CODE_PREFIX))
+        .find_map(|(textbox, _, _)| decode(&textbox.text))
+    {
+        functions
+    } else {
+        return;
+    };
+
+    for (mut textbox, x, y) in entry_boxes.iter_mut() {
+        textbox.text = functions[entry_box_index(x, y)].clone();
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use decorum::Total;
+
+use crate::{
+    projector::ProjectorCamera,
+    ui::{
+        entry_box_index, ButtonsEnabled, FunctionEntryBox, FunctionX, FunctionY, Textbox,
+        TextboxesEditable, UiCamera,
+    },
+    DespawnOnExit, Field, Game, PlayState, RenderLayer,
+};
+
+/// Degree of the polynomials sketches get fit to
+const FIT_DEGREE: usize = 5;
+/// Fewest points a sketch needs to get fit
+const MIN_POINTS: usize = 8;
+/// Shortest distance between two recorded points of a sketch
+const MIN_SPACING: f32 = 0.05;
+
+/// Path a player is drawing with the mouse, in field coordinates
+#[derive(Debug, Default)]
+pub struct Sketch {
+    points: Vec<Vec2>,
+}
+
+/// Dot showing part of a sketch
+#[derive(Component)]
+pub struct SketchDot;
+
+/// Where the cursor is on the field, if it's in the window
+pub(crate) fn cursor_on_field(
+    windows: &Windows,
+    camera: &Query<
+        (&GlobalTransform, &OrthographicProjection),
+        (Without<UiCamera>, Without<ProjectorCamera>),
+    >,
+) -> Option<Vec2> {
+    let window = windows.get_primary()?;
+    let cursor = window.cursor_position()?;
+    let (transform, projection) = camera.get_single().ok()?;
+    let fraction = cursor / Vec2::new(window.width(), window.height());
+    let local = Vec2::new(
+        projection.left + (projection.right - projection.left) * fraction.x,
+        projection.bottom + (projection.top - projection.bottom) * fraction.y,
+    ) * projection.scale;
+    Some((transform.compute_matrix() * local.extend(0.0).extend(1.0)).truncate().truncate())
+}
+
+/// Solves a linear system with Gaussian elimination. `None` if it's singular.
+pub(crate) fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by_key(|row| Total::from(a[*row][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = a[row][col] / a[col][col];
+            for k in col..n {
+                a[row][k] -= factor * a[col][k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum = (row + 1..n).map(|k| a[row][k] * x[k]).sum::<f64>();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Least-squares coefficients of `c1 t + c2 t^2 + ...` through some values at some parameters.
+/// There's no constant term, since curves get moved to start at the player anyway.
+fn fit_polynomial(params: &[f64], values: &[f64], degree: usize) -> Option<Vec<f64>> {
+    let basis = |t: f64, k: usize| t.powi(k as i32 + 1);
+    let a = (0..degree)
+        .map(|i| {
+            (0..degree).map(|j| params.iter().map(|t| basis(*t, i) * basis(*t, j)).sum()).collect()
+        })
+        .collect();
+    let b = (0..degree)
+        .map(|i| params.iter().zip(values).map(|(t, v)| basis(*t, i) * v).sum())
+        .collect();
+    solve(a, b)
+}
+
+/// Writes `c1 t + c2 t^2 + ...` in the function language
+fn polynomial_source(coefficients: &[f64]) -> String {
+    coefficients
+        .iter()
+        .enumerate()
+        .map(|(k, c)| {
+            let power = if k == 0 { "t".to_owned() } else { format!("t^{}", k + 1) };
+            let sign = match (k, *c < 0.0) {
+                (0, true) => "-",
+                (0, false) => "",
+                (_, true) => " - ",
+                (_, false) => " + ",
+            };
+            format!("{}{:.3} * {}", sign, c.abs(), power)
+        })
+        .collect()
+}
+
+/// Fits a polynomial curve through a sketch, parameterized by arc length.
+/// Returns the sources of x(t) and y(t).
+pub fn fit_sketch(points: &[Vec2]) -> Option<(String, String)> {
+    if points.len() < MIN_POINTS {
+        return None;
+    }
+
+    let mut lengths = vec![0.0f64];
+    for pair in points.windows(2) {
+        lengths.push(lengths.last().unwrap() + pair[0].distance(pair[1]) as f64);
+    }
+    let total = *lengths.last().unwrap();
+    if total == 0.0 {
+        return None;
+    }
+    let params = lengths.iter().map(|l| l / total).collect::<Vec<_>>();
+
+    let start = points[0];
+    let xs = points.iter().map(|p| (p.x - start.x) as f64).collect::<Vec<_>>();
+    let ys = points.iter().map(|p| (p.y - start.y) as f64).collect::<Vec<_>>();
+    let degree = FIT_DEGREE.min(points.len() - 1);
+    let fx = fit_polynomial(&params, &xs, degree)?;
+    let fy = fit_polynomial(&params, &ys, degree)?;
+    Some((polynomial_source(&fx), polynomial_source(&fy)))
+}
+
+/// Lets the current player draw a path on the field by holding the right mouse button.
+/// When they let go, the path gets fit to functions that go into the textboxes for editing.
+pub fn draw_sketch(
+    mut commands: Commands,
+    mut sketch: ResMut<Sketch>,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    camera: Query<
+        (&GlobalTransform, &OrthographicProjection),
+        (Without<UiCamera>, Without<ProjectorCamera>),
+    >,
+    field: Query<Entity, With<Field>>,
+    dots: Query<Entity, With<SketchDot>>,
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+    buttons_enabled: Res<ButtonsEnabled>,
+    textboxes_editable: Res<TextboxesEditable>,
+    game: Res<Game>,
+) {
+    if !buttons_enabled.0 || !textboxes_editable.0 {
+        sketch.points.clear();
+        return;
+    }
+
+    if mouse.pressed(MouseButton::Right) {
+        let pos = if let Some(pos) = cursor_on_field(&windows, &camera) { pos } else { return };
+        if pos.abs().cmpgt(game.half_size()).any() {
+            return;
+        }
+        if sketch.points.last().map_or(true, |last| last.distance(pos) >= MIN_SPACING) {
+            sketch.points.push(pos);
+            commands.entity(field.single()).with_children(|node| {
+                node.spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(0.2, 0.2, 0.2, 0.6),
+                        custom_size: Some(Vec2::splat(0.06)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(pos.extend(RenderLayer::Graph.z())),
+                    ..Default::default()
+                })
+                .insert(SketchDot)
+                .insert(DespawnOnExit(PlayState::Enter));
+            });
+        }
+    } else if mouse.just_released(MouseButton::Right) {
+        for dot in dots.iter() {
+            commands.entity(dot).despawn_recursive();
+        }
+        if let Some((x, y)) = fit_sketch(&sketch.points) {
+            let functions = [x, y, String::new()];
+            for (mut textbox, fx, fy) in entry_boxes.iter_mut() {
+                textbox.text = functions[entry_box_index(fx, fy)].clone();
+            }
+        }
+        sketch.points.clear();
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use rand_pcg::Pcg64;
+
+use crate::{
+    daily::GameMode,
+    debris::Debris,
+    dilation::DilationPickup,
+    emp::EmpPickup,
+    graph::Graph,
+    hazard::{self, Hazard},
+    ricochet::Ricochet,
+    spawn_item,
+    status::StatusEffects,
+    ui::{
+        ButtonsEnabled, DoneButton, FunctionEntryBox, PlayerFunctionDisplay, Textbox,
+        TextboxesEditable,
+    },
+    Ball, Field, Game, Mine, Owner, Player, PlayerLabel, FIELD_CONFIGS, ITEM_BALL, ITEM_DEBRIS,
+    ITEM_DILATION, ITEM_EMP, ITEM_MINE, ITEM_PLAYER_BALL, ITEM_RICOCHET,
+};
+
+/// Key that goes back to the start of the previous round
+const UNDO_KEY: KeyCode = KeyCode::F9;
+
+/// An item on the field
+#[derive(Clone, Copy, Debug)]
+enum SavedItem {
+    Ball,
+    /// A ball belonging to some player, in the destruction round
+    PlayerBall(u32),
+    Mine,
+    Ricochet,
+    Emp,
+    Dilation,
+    Debris(Debris),
+}
+
+/// Gameplay state at the start of a round's input phase
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub game: Game,
+    pub players: Vec<Player>,
+    rng: Pcg64,
+    /// Player index, position, and status effects of each player on the field
+    player_states: Vec<(u32, Vec3, StatusEffects)>,
+    items: Vec<(SavedItem, Vec3)>,
+}
+
+/// Snapshots of the current match, one per round so far.
+/// Undo goes through these, and so should anything else that needs to go back in time.
+#[derive(Debug, Default)]
+pub struct Snapshots {
+    snapshots: Vec<Snapshot>,
+    /// Whether a snapshot should be taken once the items of the round exist
+    pending: bool,
+}
+
+impl Snapshots {
+    /// Snapshot from the start of some round, which is 1-indexed like `Game::round_index`
+    pub fn get(&self, round_index: u32) -> Option<&Snapshot> {
+        self.snapshots.iter().find(|snapshot| snapshot.game.round_index == round_index)
+    }
+}
+
+pub fn clear_snapshots(mut snapshots: ResMut<Snapshots>) {
+    *snapshots = Snapshots::default();
+}
+
+/// Items get spawned with commands when the input phase starts,
+/// so the snapshot waits until the first update
+pub fn request_snapshot(mut snapshots: ResMut<Snapshots>) {
+    snapshots.pending = true;
+}
+
+pub fn take_snapshot(
+    mut snapshots: ResMut<Snapshots>,
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    rng: Res<Pcg64>,
+    player_comps: Query<(&Owner, &Transform, &StatusEffects), With<PlayerLabel>>,
+    balls: Query<(&Transform, Option<&Owner>), With<Ball>>,
+    mines: Query<&Transform, With<Mine>>,
+    pickups: Query<
+        (&Transform, Option<&Ricochet>, Option<&EmpPickup>),
+        Or<(With<Ricochet>, With<EmpPickup>, With<DilationPickup>)>,
+    >,
+    debris: Query<(&Transform, &Debris)>,
+) {
+    if !snapshots.pending {
+        return;
+    }
+    snapshots.pending = false;
+
+    let balls = balls.iter().map(|(transform, owner)| {
+        let item = owner.map_or(SavedItem::Ball, |owner| SavedItem::PlayerBall(owner.0));
+        (item, transform.translation)
+    });
+    let mines = mines.iter().map(|transform| (SavedItem::Mine, transform.translation));
+    let pickups = pickups.iter().map(|(transform, ricochet, emp)| {
+        let item = match (ricochet, emp) {
+            (Some(_), _) => SavedItem::Ricochet,
+            (_, Some(_)) => SavedItem::Emp,
+            _ => SavedItem::Dilation,
+        };
+        (item, transform.translation)
+    });
+    let debris = debris
+        .iter()
+        .map(|(transform, debris)| (SavedItem::Debris(*debris), transform.translation));
+
+    let snapshot = Snapshot {
+        game: (*game).clone(),
+        players: (*players).clone(),
+        rng: (*rng).clone(),
+        player_states: player_comps
+            .iter()
+            .map(|(owner, transform, statuses)| (owner.0, transform.translation, statuses.clone()))
+            .collect(),
+        items: balls.chain(mines).chain(pickups).chain(debris).collect(),
+    };
+    snapshots.snapshots.retain(|s| s.game.round_index < game.round_index);
+    snapshots.snapshots.push(snapshot);
+}
+
+/// Goes back to the start of the previous round when the undo key is pressed.
+/// Not available in the daily challenge, where every attempt counts.
+pub fn undo_round(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mode: Res<GameMode>,
+    mut snapshots: ResMut<Snapshots>,
+    mut game: ResMut<Game>,
+    mut players: ResMut<Vec<Player>>,
+    mut rng: ResMut<Pcg64>,
+    images: Res<Assets<Image>>,
+    field: Query<Entity, With<Field>>,
+    items: Query<
+        Entity,
+        Or<(
+            With<Ball>,
+            With<Mine>,
+            With<Ricochet>,
+            With<EmpPickup>,
+            With<DilationPickup>,
+            With<Debris>,
+            With<Graph>,
+            With<Hazard>,
+        )>,
+    >,
+    mut player_comps: Query<(&Owner, &mut Transform, &mut StatusEffects), With<PlayerLabel>>,
+    mut owned_ui: Query<
+        &mut Owner,
+        (Or<(With<FunctionEntryBox>, With<DoneButton>)>, Without<PlayerLabel>),
+    >,
+    mut entry_textboxes: Query<&mut Textbox, With<FunctionEntryBox>>,
+    mut displays: Query<(&mut Style, &PlayerFunctionDisplay)>,
+    mut textboxes_editable: ResMut<TextboxesEditable>,
+    mut buttons_enabled: ResMut<ButtonsEnabled>,
+) {
+    if !keys.just_pressed(UNDO_KEY)
+        || *mode != GameMode::Normal
+        || !buttons_enabled.0
+        || snapshots.snapshots.len() < 2
+    {
+        return;
+    }
+
+    snapshots.snapshots.pop();
+    let snapshot = snapshots.snapshots.last().unwrap().clone();
+    *game = snapshot.game;
+    *players = snapshot.players;
+    *rng = snapshot.rng;
+
+    // Players knocked out in the undone round come back
+    for (mut style, display) in displays.iter_mut() {
+        if let Some(player) = players.get(display.player_index as usize) {
+            style.display = if player.eliminated { Display::None } else { Display::Flex };
+        }
+    }
+
+    for (owner, mut transform, mut statuses) in player_comps.iter_mut() {
+        if let Some((_, position, saved)) =
+            snapshot.player_states.iter().find(|(index, ..)| *index == owner.0)
+        {
+            transform.translation = *position;
+            *statuses = saved.clone();
+        }
+    }
+
+    for entity in items.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.entity(field.single()).with_children(|node| {
+        for (item, position) in &snapshot.items {
+            match *item {
+                SavedItem::Ball => {
+                    spawn_item(node, &images, *position, &ITEM_BALL, 0).insert(Ball);
+                }
+                SavedItem::PlayerBall(owner) => {
+                    spawn_item(node, &images, *position, &ITEM_PLAYER_BALL, owner)
+                        .insert(Owner(owner))
+                        .insert(Ball);
+                }
+                SavedItem::Mine => {
+                    spawn_item(node, &images, *position, &ITEM_MINE, 0).insert(Mine);
+                }
+                SavedItem::Ricochet => {
+                    spawn_item(node, &images, *position, &ITEM_RICOCHET, 0).insert(Ricochet);
+                }
+                SavedItem::Emp => {
+                    spawn_item(node, &images, *position, &ITEM_EMP, 0).insert(EmpPickup);
+                }
+                SavedItem::Dilation => {
+                    spawn_item(node, &images, *position, &ITEM_DILATION, 0).insert(DilationPickup);
+                }
+                SavedItem::Debris(debris) => {
+                    spawn_item(node, &images, *position, &ITEM_DEBRIS, 0).insert(debris);
+                }
+            }
+        }
+        if !game.is_on_destruction_round() {
+            let hazards = &FIELD_CONFIGS[game.num_players() as usize].hazards;
+            hazard::spawn_hazards(node, &images, hazards, game.half_size());
+        }
+    });
+
+    for mut owner in owned_ui.iter_mut() {
+        owner.0 = game.player_turn();
+    }
+    for mut textbox in entry_textboxes.iter_mut() {
+        textbox.text.clear();
+    }
+    textboxes_editable.0 = true;
+    buttons_enabled.0 = true;
+    log::info!("Went back to round {}", game.round_index);
+}
@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use egui::Align2;
+use fxhash::{FxHashMap, FxHashSet};
+
+use crate::{
+    breakdown::{FlightNote, NoteKind},
+    core::lang::{Function, SeriesOp},
+    daily::GameMode,
+    projectile::SamplePath,
+    storage, Owner, Player, WinnerBox,
+};
+
+/// Number of local profiles. Each player slot has its own.
+pub const NUM_PROFILES: usize = 4;
+/// Number of favorite builtins shown per profile
+const NUM_FAVORITES: usize = 3;
+
+/// Lifetime statistics of a local profile
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    pub matches: u32,
+    pub wins: u32,
+    /// Rockets launched
+    pub rockets: u32,
+    /// Rockets that collected at least one ball
+    pub hits: u32,
+    /// Times each builtin function was used in a sent shot, by name
+    pub builtins: FxHashMap<String, u32>,
+}
+
+impl Profile {
+    fn key(index: usize) -> String {
+        format!("stats-p{}", index + 1)
+    }
+
+    /// Loads a profile, one stat per line. Unknown or broken lines are skipped.
+    pub fn load(index: usize) -> Self {
+        let mut profile = Self::default();
+        for line in storage::load(&Self::key(index)).unwrap_or_default().lines() {
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            match parts[..] {
+                ["matches", n] => profile.matches = n.parse().unwrap_or(0),
+                ["wins", n] => profile.wins = n.parse().unwrap_or(0),
+                ["rockets", n] => profile.rockets = n.parse().unwrap_or(0),
+                ["hits", n] => profile.hits = n.parse().unwrap_or(0),
+                ["builtin", name, n] => {
+                    profile.builtins.insert(name.to_owned(), n.parse().unwrap_or(0));
+                }
+                _ => {}
+            }
+        }
+        profile
+    }
+
+    pub fn save(&self, index: usize) {
+        let mut value = format!(
+            "matches {}\nwins {}\nrockets {}\nhits {}\n",
+            self.matches, self.wins, self.rockets, self.hits
+        );
+        for (name, count) in &self.builtins {
+            value += &format!("builtin {} {}\n", name, count);
+        }
+        storage::save(&Self::key(index), &value);
+    }
+
+    /// Fraction of matches won, from 0 to 1
+    pub fn win_rate(&self) -> f32 {
+        if self.matches == 0 {
+            0.0
+        } else {
+            self.wins as f32 / self.matches as f32
+        }
+    }
+
+    /// Fraction of rockets that collected a ball, from 0 to 1
+    pub fn accuracy(&self) -> f32 {
+        if self.rockets == 0 {
+            0.0
+        } else {
+            self.hits as f32 / self.rockets as f32
+        }
+    }
+
+    /// Most used builtins, most used first
+    pub fn favorite_builtins(&self, count: usize) -> Vec<&str> {
+        let mut builtins = self.builtins.iter().collect::<Vec<_>>();
+        builtins.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+        builtins.into_iter().take(count).map(|(name, _)| name.as_str()).collect()
+    }
+}
+
+/// Profiles of all player slots, along with what's needed to track the current match
+#[derive(Debug)]
+pub struct Stats {
+    pub profiles: Vec<Profile>,
+    /// Rockets in flight that already collected a ball
+    hit_rockets: FxHashSet<Entity>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            profiles: (0..NUM_PROFILES).map(Profile::load).collect(),
+            hit_rockets: FxHashSet::default(),
+        }
+    }
+}
+
+impl Stats {
+    fn save(&self) {
+        for (index, profile) in self.profiles.iter().enumerate() {
+            profile.save(index);
+        }
+    }
+}
+
+fn count_builtins(function: &Function, builtins: &mut FxHashMap<String, u32>) {
+    match function {
+        Function::Var(_) | Function::Const(_) | Function::ImagUnit | Function::Index(_) => {}
+        Function::Add(fs) | Function::Mul(fs) => {
+            fs.iter().for_each(|(f, _)| count_builtins(f, builtins));
+        }
+        Function::Exp(fs) => fs.iter().for_each(|f| count_builtins(f, builtins)),
+        Function::Neg(f) => count_builtins(f, builtins),
+        Function::Integral(f) => {
+            *builtins.entry("int".to_owned()).or_default() += 1;
+            count_builtins(f, builtins);
+        }
+        Function::Call1(call, f) => {
+            *builtins.entry(call.name().to_owned()).or_default() += 1;
+            count_builtins(f, builtins);
+        }
+        Function::Call2(call, fs) => {
+            *builtins.entry(call.name().to_owned()).or_default() += 1;
+            fs.iter().for_each(|f| count_builtins(f, builtins));
+        }
+        Function::Cmp(_, fs) => fs.iter().for_each(|f| count_builtins(f, builtins)),
+        Function::Series(op, _, fs) => {
+            let name = if *op == SeriesOp::Sum { "sum" } else { "prod" };
+            *builtins.entry(name.to_owned()).or_default() += 1;
+            fs.iter().for_each(|f| count_builtins(f, builtins));
+        }
+        Function::If(fs) => {
+            *builtins.entry("if".to_owned()).or_default() += 1;
+            fs.iter().for_each(|f| count_builtins(f, builtins));
+        }
+        Function::Rand(_, fs) => {
+            *builtins.entry("rand".to_owned()).or_default() += 1;
+            fs.iter().for_each(|f| count_builtins(f, builtins));
+        }
+    }
+}
+
+/// Counts the builtins in the shots about to be fired. Has to run before the rockets get fired,
+/// since that takes the shots out of the players.
+pub fn record_shots(mut stats: ResMut<Stats>, mode: Res<GameMode>, players: Res<Vec<Player>>) {
+    if *mode != GameMode::Normal {
+        return;
+    }
+    for (profile, player) in stats.profiles.iter_mut().zip(players.iter()) {
+        for parametric in &player.parametrics {
+            [&parametric.x, &parametric.y]
+                .into_iter()
+                .chain(&parametric.assigns)
+                .for_each(|f| count_builtins(f, &mut profile.builtins));
+        }
+    }
+}
+
+/// Counts rockets launched and rockets that collected balls
+pub fn record_rockets(
+    mut stats: ResMut<Stats>,
+    mode: Res<GameMode>,
+    new_rockets: Query<&Owner, Added<SamplePath>>,
+    owners: Query<&Owner>,
+    mut notes: EventReader<FlightNote>,
+) {
+    if *mode != GameMode::Normal {
+        return;
+    }
+    for owner in new_rockets.iter() {
+        if let Some(profile) = stats.profiles.get_mut(owner.0 as usize) {
+            profile.rockets += 1;
+        }
+    }
+    for note in notes.iter().filter(|note| note.kind == NoteKind::Hit) {
+        let owner = if let Ok(owner) = owners.get(note.projectile) { owner } else { continue };
+        if stats.hit_rockets.insert(note.projectile) {
+            if let Some(profile) = stats.profiles.get_mut(owner.0 as usize) {
+                profile.hits += 1;
+            }
+        }
+    }
+}
+
+pub fn clear_hit_rockets(mut stats: ResMut<Stats>) {
+    stats.hit_rockets.clear();
+}
+
+/// Records matches and wins once the winners are shown, and saves the profiles
+pub fn record_match(
+    mut stats: ResMut<Stats>,
+    mode: Res<GameMode>,
+    players: Res<Vec<Player>>,
+    winner_boxes: Query<(), Added<WinnerBox>>,
+) {
+    if *mode != GameMode::Normal || winner_boxes.iter().next().is_none() {
+        return;
+    }
+
+    let alive = players.iter().filter(|p| !p.eliminated);
+    let max_score = alive.map(|p| p.num_balls).max().unwrap_or(0);
+    for (profile, player) in stats.profiles.iter_mut().zip(players.iter()) {
+        profile.matches += 1;
+        if !player.eliminated && player.num_balls == max_score {
+            profile.wins += 1;
+        }
+    }
+    stats.save();
+}
+
+/// Shows the lifetime stats of each profile on the menu
+pub fn show_profiles(stats: Res<Stats>, mut egui_ctx: ResMut<EguiContext>) {
+    egui::Window::new("Profiles")
+        .anchor(Align2::RIGHT_TOP, [-8.0, 8.0])
+        .default_open(false)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            for (index, profile) in stats.profiles.iter().enumerate() {
+                ui.heading(format!("P{}", index + 1));
+                ui.label(format!(
+                    "{} matches, {:.0}% won",
+                    profile.matches,
+                    profile.win_rate() * 100.0
+                ));
+                ui.label(format!(
+                    "{} rockets, {:.0}% hit a ball",
+                    profile.rockets,
+                    profile.accuracy() * 100.0
+                ));
+                let favorites = profile.favorite_builtins(NUM_FAVORITES);
+                if !favorites.is_empty() {
+                    ui.label(format!("Favorite builtins: {}", favorites.join(", ")));
+                }
+            }
+        });
+}
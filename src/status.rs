@@ -0,0 +1,181 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{time::AdvanceRound, Owner, Player, PlayerLabel, RenderLayer};
+
+/// Kinds of status effects a player can have
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatusKind {
+    /// A mine hit doesn't destroy the player's rocket. Each hit uses up a stack.
+    Shield,
+    /// The player's rockets take longer to fly. EMPs slow the players whose rockets they destroy.
+    Slow,
+    /// Balls collected by the player's rockets are worth nothing, and the player loses style
+    /// points at each round start. Hitting a mine without a shield curses the player.
+    Curse,
+    /// The player's balls can't be destroyed. Losing a ball makes the player invulnerable
+    /// for the rest of the round.
+    Invulnerable,
+    /// The player's next rocket can bounce off obstacles and field edges
+    Ricochet,
+    /// The player can destroy everyone else's rockets while they fly. Each use takes a stack.
+    Emp,
+    /// The player can slow down or speed up their own rockets while they fly.
+    /// Each use takes a stack.
+    Dilation,
+}
+
+/// How a new status effect combines with an existing one of the same kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stacking {
+    /// Reset the duration to the longer of the two
+    Refresh,
+    /// Add a stack and refresh the duration
+    Stack,
+    /// Keep the existing effect as is
+    Ignore,
+}
+
+impl StatusKind {
+    pub fn stacking(self) -> Stacking {
+        match self {
+            Self::Shield | Self::Ricochet | Self::Emp | Self::Dilation => Stacking::Stack,
+            Self::Slow | Self::Curse => Stacking::Refresh,
+            Self::Invulnerable => Stacking::Ignore,
+        }
+    }
+
+    /// What the effect does to its player at each round start it lasts past
+    fn on_round_start(self, player: &mut Player) {
+        if self == Self::Curse {
+            player.style_points = player.style_points.saturating_sub(CURSE_DRAIN);
+        }
+    }
+
+    /// Short text shown next to the player
+    pub fn icon(self) -> &'static str {
+        match self {
+            Self::Shield => "S",
+            Self::Slow => "~",
+            Self::Curse => "C",
+            Self::Invulnerable => "I",
+            Self::Ricochet => "R",
+            Self::Emp => "E",
+            Self::Dilation => "D",
+        }
+    }
+}
+
+/// Factor rocket flight time gets multiplied by while slowed
+pub const SLOW_FACTOR: f32 = 1.5;
+/// Style points a curse takes from its player at each round start
+const CURSE_DRAIN: u32 = 1;
+
+#[derive(Clone, Copy, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Number of round starts the effect survives
+    pub rounds_left: u32,
+    pub stacks: u32,
+}
+
+/// Status effects on a player
+#[derive(Clone, Debug, Default, Component)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    /// Adds an effect that lasts for some number of round starts,
+    /// combining it with an existing one the way its kind stacks
+    pub fn add(&mut self, kind: StatusKind, rounds: u32) {
+        if let Some(effect) = self.0.iter_mut().find(|e| e.kind == kind) {
+            match kind.stacking() {
+                Stacking::Refresh => effect.rounds_left = effect.rounds_left.max(rounds),
+                Stacking::Stack => {
+                    effect.stacks += 1;
+                    effect.rounds_left = effect.rounds_left.max(rounds);
+                }
+                Stacking::Ignore => {}
+            }
+        } else {
+            self.0.push(StatusEffect { kind, rounds_left: rounds, stacks: 1 });
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.0.iter().any(|e| e.kind == kind)
+    }
+
+    /// Uses up one stack of an effect. Returns whether there was a stack to use up.
+    pub fn consume(&mut self, kind: StatusKind) -> bool {
+        if let Some(index) = self.0.iter().position(|e| e.kind == kind) {
+            self.0[index].stacks -= 1;
+            if self.0[index].stacks == 0 {
+                self.0.remove(index);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Called at the start of each round. Counts down the effects,
+    /// and lets the ones still left act on the player.
+    fn tick(&mut self, player: &mut Player) {
+        for effect in &mut self.0 {
+            effect.rounds_left = effect.rounds_left.saturating_sub(1);
+        }
+        self.0.retain(|e| e.rounds_left > 0);
+        for effect in &self.0 {
+            effect.kind.on_round_start(player);
+        }
+    }
+}
+
+pub fn tick_status_effects(
+    mut advance_round_events: EventReader<AdvanceRound>,
+    mut players: ResMut<Vec<Player>>,
+    mut effects: Query<(&Owner, &mut StatusEffects)>,
+) {
+    if advance_round_events.iter().next().is_none() {
+        return;
+    }
+
+    for (owner, mut effects) in effects.iter_mut() {
+        effects.tick(&mut players[owner.0 as usize]);
+    }
+}
+
+/// Labels the text showing a player's status effects
+#[derive(Component)]
+pub struct StatusIcons;
+
+pub fn update_status_icons(
+    mut icons: Query<(&Owner, &mut Text, &mut Transform), With<StatusIcons>>,
+    players: Query<
+        (&Owner, &Transform, &StatusEffects),
+        (With<PlayerLabel>, Or<(Changed<StatusEffects>, Changed<Transform>)>, Without<StatusIcons>),
+    >,
+) {
+    for (owner, player_transform, effects) in players.iter() {
+        let (_, mut text, mut transform) =
+            if let Some(icons) = icons.iter_mut().find(|(o, ..)| o.0 == owner.0) {
+                icons
+            } else {
+                continue;
+            };
+
+        text.sections[0].value = effects
+            .0
+            .iter()
+            .map(|e| {
+                if e.stacks > 1 {
+                    format!("{}{}", e.kind.icon(), e.stacks)
+                } else {
+                    e.kind.icon().to_owned()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        transform.translation = (player_transform.translation.xy() - Vec2::new(0.0, 0.35))
+            .extend(RenderLayer::Score.z());
+    }
+}
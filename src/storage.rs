@@ -0,0 +1,37 @@
+//! Small key-value storage that persists between sessions.
+//! Natively, each key is a file in the `saves` directory. On the web, local storage is used.
+
+#[cfg(not(target_family = "wasm"))]
+fn path(key: &str) -> std::path::PathBuf {
+    std::path::Path::new("saves").join(key)
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(path(key)).ok()
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub fn save(key: &str, value: &str) {
+    let result = std::fs::create_dir_all("saves").and_then(|_| std::fs::write(path(key), value));
+    if let Err(err) = result {
+        log::warn!("Failed to save {}: {}", key, err);
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_family = "wasm")]
+pub fn load(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok()?
+}
+
+#[cfg(target_family = "wasm")]
+pub fn save(key: &str, value: &str) {
+    if local_storage().and_then(|storage| storage.set_item(key, value).ok()).is_none() {
+        log::warn!("Failed to save {}", key);
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+
+use crate::{
+    analysis::sample_path,
+    core::lang::{Call1, Call2, Function, Parametric},
+    projectile::MotionModel,
+};
+
+/// Style points for how much the curvature of a path varies, per radian of standard deviation
+/// of the turning angle between samples
+const CURVATURE_WEIGHT: f32 = 50.0;
+/// Most style points a rocket can get from its curvature
+const MAX_CURVATURE_POINTS: u32 = 5;
+
+/// Style points a rocket earns its player for each ball it collects
+#[derive(Clone, Copy, Debug, Component)]
+pub struct StylePoints(pub u32);
+
+/// Collects the builtin functions used in a function
+fn collect_builtins(function: &Function, call1s: &mut Vec<Call1>, call2s: &mut Vec<Call2>) {
+    match function {
+        Function::Var(_) | Function::Const(_) | Function::ImagUnit | Function::Index(_) => {}
+        Function::Add(terms) | Function::Mul(terms) => {
+            for (term, _) in terms {
+                collect_builtins(term, call1s, call2s);
+            }
+        }
+        Function::Exp(factors) => {
+            for factor in factors {
+                collect_builtins(factor, call1s, call2s);
+            }
+        }
+        Function::Neg(inner) | Function::Integral(inner) => collect_builtins(inner, call1s, call2s),
+        Function::Call1(call, arg) => {
+            if !call1s.contains(call) {
+                call1s.push(*call);
+            }
+            collect_builtins(arg, call1s, call2s);
+        }
+        Function::Call2(call, args) => {
+            if !call2s.contains(call) {
+                call2s.push(*call);
+            }
+            for arg in args.iter() {
+                collect_builtins(arg, call1s, call2s);
+            }
+        }
+        Function::Cmp(_, args) | Function::Rand(_, args) => {
+            for arg in args.iter() {
+                collect_builtins(arg, call1s, call2s);
+            }
+        }
+        Function::If(args) | Function::Series(_, _, args) => {
+            for arg in args.iter() {
+                collect_builtins(arg, call1s, call2s);
+            }
+        }
+    }
+}
+
+/// Number of distinct builtin functions a parametric uses
+pub fn num_builtins(parametric: &Parametric) -> u32 {
+    let (mut call1s, mut call2s) = (vec![], vec![]);
+    for function in [&parametric.x, &parametric.y].into_iter().chain(&parametric.assigns) {
+        collect_builtins(function, &mut call1s, &mut call2s);
+    }
+    (call1s.len() + call2s.len()) as u32
+}
+
+/// Standard deviation of the turning angle between samples of a path, in radians.
+/// Straight lines and circles get 0.
+pub fn curvature_spread(path: &[Vec2]) -> f32 {
+    let turns = path
+        .windows(3)
+        .filter_map(|points| {
+            let (d0, d1) = (points[1] - points[0], points[2] - points[1]);
+            (d0 != Vec2::ZERO && d1 != Vec2::ZERO).then(|| d0.angle_between(d1))
+        })
+        .filter(|angle| angle.is_finite())
+        .collect::<Vec<_>>();
+    if turns.is_empty() {
+        return 0.0;
+    }
+
+    let mean = turns.iter().sum::<f32>() / turns.len() as f32;
+    let variance = turns.iter().map(|a| (a - mean).powi(2)).sum::<f32>() / turns.len() as f32;
+    variance.sqrt()
+}
+
+/// Style points for a rocket, for using more builtins and for curves whose bendiness varies
+pub fn style_points(parametric: &Parametric) -> u32 {
+    let path = sample_path(&MotionModel::parametric(parametric.clone(), Vec2::ZERO));
+    let curvature_points =
+        ((curvature_spread(&path) * CURVATURE_WEIGHT).round() as u32).min(MAX_CURVATURE_POINTS);
+    num_builtins(parametric) + curvature_points
+}
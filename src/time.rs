@@ -1,6 +1,9 @@
 use std::time::Duration;
 
-use bevy::prelude::*;
+use bevy::{prelude::*, window::WindowFocused};
+
+/// Shortest time between frames while the window isn't focused, in seconds
+const UNFOCUSED_FRAME_TIME: f32 = 0.1;
 
 #[derive(Clone, Copy, Debug, Component, PartialEq, Eq)]
 pub enum DelayedEvent {
@@ -28,9 +31,64 @@ impl DelayedEventBundle {
     }
 }
 
+/// Time that passes in the game. It stops while the window isn't focused,
+/// so rockets don't fly and turns don't advance while nobody is watching.
+#[derive(Debug, Default)]
+pub struct GameTime {
+    delta: Duration,
+    focused: bool,
+    was_focused: bool,
+}
+
+impl GameTime {
+    pub fn new() -> Self {
+        Self { delta: Duration::ZERO, focused: true, was_focused: true }
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        !self.focused
+    }
+}
+
+/// Advances game time along with real time while the window is focused.
+/// The first frame after getting focus back doesn't count, since it can be very long.
+pub fn update_game_time(
+    time: Res<Time>,
+    mut focus_events: EventReader<WindowFocused>,
+    mut game_time: ResMut<GameTime>,
+) {
+    game_time.was_focused = game_time.focused;
+    for event in focus_events.iter() {
+        game_time.focused = event.focused;
+    }
+    game_time.delta =
+        if game_time.focused && game_time.was_focused { time.delta() } else { Duration::ZERO };
+}
+
+/// Keeps the game from using a whole core while it's in the background.
+/// Browsers already throttle background tabs, so this is only needed natively.
+pub fn throttle_when_unfocused(game_time: Res<GameTime>, time: Res<Time>) {
+    if !game_time.is_paused() || cfg!(target_family = "wasm") {
+        return;
+    }
+
+    let elapsed = time.delta_seconds();
+    if elapsed < UNFOCUSED_FRAME_TIME {
+        std::thread::sleep(Duration::from_secs_f32(UNFOCUSED_FRAME_TIME - elapsed));
+    }
+}
+
 pub fn advance_timers(
     mut commands: Commands,
-    time: Res<Time>,
+    time: Res<GameTime>,
     mut timers: Query<(Entity, &mut Timer, &DelayedEvent)>,
     mut advance_turn_events: EventWriter<AdvanceTurn>,
 ) {
@@ -0,0 +1,156 @@
+use bevy::{math::Vec3Swizzles, prelude::*, sprite::MaterialMesh2dBundle};
+use bevy_egui::EguiContext;
+use egui::Align2;
+
+use crate::{
+    analysis::sample_path_with,
+    coordinates::CoordinateMode,
+    graph::{position_env, GRAPH_COLORS},
+    lock::{assign_names, free_coefficients, rewrite_assigns},
+    mesh::dot_mesh,
+    projectile::{LastShotEnd, MotionModel},
+    quality::RenderQuality,
+    rules::GameRules,
+    ui::{
+        entry_box_index, ButtonsEnabled, FunctionEntryBox, FunctionWhere, FunctionX, FunctionY,
+        Textbox, TextboxesEditable, TypedShot,
+    },
+    DespawnOnExit, Field, Game, Owner, PlayState, Player, PlayerLabel, RenderLayer,
+};
+
+/// Smallest range a coefficient slider covers on either side of 0
+const MIN_SLIDER_BOUND: f64 = 10.0;
+/// Number of path samples per preview dot
+const PREVIEW_STRIDE: usize = 2;
+
+/// What the preview was last drawn for: the player, how they enter functions, and their functions.
+/// `None` if nothing should be drawn.
+#[derive(Debug, Default)]
+pub struct Preview {
+    shown: Option<(u32, CoordinateMode, [String; 3])>,
+}
+
+/// Labels the dots showing the preview of a shot, which are one mesh
+#[derive(Component)]
+pub struct PreviewDots;
+
+/// Shows a slider for each number assigned in the 'where' textbox, like `a = 1`.
+/// Moving a slider rewrites the assignment, which gets the shot reparsed.
+pub fn show_sliders(
+    mut egui_ctx: ResMut<EguiContext>,
+    shot: Res<TypedShot>,
+    mut where_boxes: Query<&mut Textbox, (With<FunctionEntryBox>, With<FunctionWhere>)>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    textboxes_editable: Res<TextboxesEditable>,
+) {
+    if !buttons_enabled.0 || !textboxes_editable.0 {
+        return;
+    }
+
+    let (parametric, functions) = match (&shot.parsed, shot.functions()) {
+        (Some(Ok(parametric)), Some(functions)) => (parametric, functions),
+        _ => return,
+    };
+    let free = free_coefficients(parametric);
+    if free.is_empty() {
+        return;
+    }
+    let names = assign_names(&functions[2]);
+
+    let mut values = free.iter().map(|(_, c)| *c).collect::<Vec<_>>();
+    let mut changed = false;
+    egui::Window::new("Coefficients")
+        .anchor(Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            for ((index, _), value) in free.iter().zip(&mut values) {
+                let bound = MIN_SLIDER_BOUND.max(value.abs().ceil());
+                let slider = egui::Slider::new(value, -bound..=bound).text(names[*index].as_str());
+                changed |= ui.add(slider).changed();
+            }
+        });
+
+    if changed {
+        let assigns = rewrite_assigns(&functions[2], &free, &values);
+        for mut textbox in where_boxes.iter_mut() {
+            textbox.text = assigns.clone();
+        }
+    }
+}
+
+/// Draws the path of the current player's shot while it has coefficients to tune,
+/// redrawing it whenever the functions change. The rules can turn previews off.
+pub fn update_preview(
+    mut commands: Commands,
+    mut preview: ResMut<Preview>,
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    player_comps: Query<(Entity, &Owner, &Transform, &LastShotEnd), With<PlayerLabel>>,
+    entry_boxes: Query<(&Textbox, Option<&FunctionX>, Option<&FunctionY>), With<FunctionEntryBox>>,
+    dots: Query<Entity, With<PreviewDots>>,
+    field: Query<Entity, With<Field>>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    rules: Res<GameRules>,
+    quality: Res<RenderQuality>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let player = game.player_turn();
+    let shown = (buttons_enabled.0 && rules.previews).then(|| {
+        let mut functions: [String; 3] = Default::default();
+        for (textbox, x, y) in entry_boxes.iter() {
+            functions[entry_box_index(x, y)] = textbox.text.clone();
+        }
+        (player, players[player as usize].coordinate_mode, functions)
+    });
+    if shown == preview.shown {
+        return;
+    }
+
+    for dot in dots.iter() {
+        commands.entity(dot).despawn_recursive();
+    }
+    preview.shown = shown;
+    let (mode, functions) =
+        if let Some((_, mode, functions)) = &preview.shown { (*mode, functions) } else { return };
+
+    let env = position_env(player, &players, &player_comps);
+    let parametric =
+        if let Ok(parametric) = mode.parse(&functions[0], &functions[1], &functions[2], &env) {
+            parametric
+        } else {
+            return;
+        };
+    if free_coefficients(&parametric).is_empty() {
+        return;
+    }
+    let start =
+        if let Some((_, _, transform, _)) = player_comps.iter().find(|(_, o, ..)| o.0 == player) {
+            transform.translation.xy()
+        } else {
+            return;
+        };
+
+    let motion = MotionModel::for_rules(parametric, start, &rules);
+    let path = sample_path_with(&motion, quality.preview_samples());
+    if path.is_empty() {
+        return;
+    }
+    let mut color = GRAPH_COLORS[player as usize];
+    color.set_a(0.5);
+    let dots = dot_mesh(path.iter().step_by(PREVIEW_STRIDE).copied(), 0.05, RenderLayer::Graph.z());
+    commands.entity(field.single()).with_children(|node| {
+        node.spawn_bundle(MaterialMesh2dBundle {
+            mesh: meshes.add(dots).into(),
+            material: materials.add(color.into()),
+            ..Default::default()
+        })
+        .insert(PreviewDots)
+        .insert(DespawnOnExit(PlayState::Enter));
+    });
+}
+
+/// Forgets the preview when leaving the entering phase, which despawns its dots
+pub fn clear_preview(mut preview: ResMut<Preview>) {
+    preview.shown = None;
+}
@@ -1,14 +1,36 @@
-use bevy::{ecs::system::EntityCommands, prelude::*};
+use bevy::{
+    ecs::system::{EntityCommands, SystemParam},
+    prelude::*,
+};
 use bevy_egui::EguiContext;
 use bevy_inspector_egui::Inspectable;
+use bevy_kira_audio::{Audio, AudioSource};
 use egui::Align2;
 use fxhash::FxHashMap;
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
 
 use crate::{
     asset,
-    graph::{SendFunctions, QUICK_HELP},
+    charge::Charging,
+    complexity::ComplexityText,
+    coordinates::{AxisLabel, AxisRow, CoordinateButton, CoordinateMode, CoordinateText},
+    core::lang::{Parametric, ParseError},
+    daily::{self, DailyButtonText, DailyRecord, GameMode},
+    domain::DomainWarningText,
+    energy::{EnergyBar, EnergyFill},
+    graph::{position_env, SendAction, SendFunctions, QUICK_HELP},
+    ink::InkText,
+    library::LibrarySpot,
+    loadout::{Loadout, LoadoutButton, LoadoutText, PlayerConfig},
+    lock::LOCKS_PER_MATCH,
+    parse_error::ParseErrorText,
+    preset::{PresetTabs, NUM_PRESETS},
+    projectile::LastShotEnd,
+    rules::GameRules,
+    share::CopyCodeButton,
     time::{AdvanceRound, AdvanceTurn},
-    Field, Game, Owner, PlayState, Player,
+    Field, Game, Owner, PlayState, Player, PlayerLabel,
 };
 
 const FONT_SIZE: f32 = 18.0;
@@ -89,7 +111,13 @@ impl<'w, 's, 'a> EntityCommandsExt for EntityCommands<'w, 's, 'a> {
                 ..Default::default()
             });
 
-            for num_players in 2..=4 {
+            for (num_players, kind) in [
+                (2, PlayKind::Normal),
+                (3, PlayKind::Normal),
+                (4, PlayKind::Normal),
+                (1, PlayKind::Daily),
+                (1, PlayKind::Practice),
+            ] {
                 node.spawn_bundle(ButtonBundle {
                     style: Style {
                         align_self: AlignSelf::Center,
@@ -101,19 +129,58 @@ impl<'w, 's, 'a> EntityCommandsExt for EntityCommands<'w, 's, 'a> {
                     color: UiColor(NORMAL_BUTTON),
                     ..Default::default()
                 })
-                .insert(PlayButton { num_players })
+                .insert(PlayButton { num_players, kind })
                 .with_children(|node| {
                     node.spawn_bundle(TextBundle {
                         text: Text::with_section(
-                            format!("{}-Player Game", num_players),
+                            match kind {
+                                PlayKind::Normal => format!("{}-Player Game", num_players),
+                                PlayKind::Daily => "Daily Challenge".to_owned(),
+                                PlayKind::Practice => "Practice".to_owned(),
+                            },
                             button_style.clone(),
                             center_align,
                         ),
                         style: Style { margin: Rect::all(Val::Px(4.0)), ..Default::default() },
                         ..Default::default()
-                    });
+                    })
+                    .maybe_insert((kind == PlayKind::Daily).then(|| DailyButtonText));
                 });
             }
+
+            // Starting loadouts
+            node.spawn_bundle(NodeBundle {
+                style: Style { flex_direction: FlexDirection::Row, ..Default::default() },
+                color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                ..Default::default()
+            })
+            .with_children(|node| {
+                for player_index in 0..4 {
+                    node.spawn_bundle(ButtonBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            margin: Rect::all(Val::Px(7.0)),
+                            ..Default::default()
+                        },
+                        color: UiColor(NORMAL_BUTTON),
+                        ..Default::default()
+                    })
+                    .insert(LoadoutButton(player_index))
+                    .with_children(|node| {
+                        node.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "",
+                                TextStyle { font_size: 24.0, ..button_style.clone() },
+                                center_align,
+                            ),
+                            style: Style { margin: Rect::all(Val::Px(4.0)), ..Default::default() },
+                            ..Default::default()
+                        })
+                        .insert(LoadoutText(player_index));
+                    });
+                }
+            });
         })
     }
 
@@ -162,6 +229,70 @@ impl<'w, 's, 'a> EntityCommandsExt for EntityCommands<'w, 's, 'a> {
             })
             .insert(FunctionStatus);
 
+            node.spawn_bundle(TextBundle {
+                text: Text::with_section("", function_label_style.clone(), center_align),
+                style: Style { align_self: AlignSelf::Center, ..Default::default() },
+                ..Default::default()
+            })
+            .insert(AmmoText);
+
+            node.spawn_bundle(TextBundle {
+                text: Text::with_section("", function_label_style.clone(), center_align),
+                style: Style { align_self: AlignSelf::Center, ..Default::default() },
+                ..Default::default()
+            })
+            .insert(InkText);
+
+            node.spawn_bundle(NodeBundle {
+                style: Style {
+                    align_self: AlignSelf::Center,
+                    size: Size::new(Val::Px(120.0), Val::Px(8.0)),
+                    margin: Rect::all(Val::Px(4.0)),
+                    display: Display::None,
+                    ..Default::default()
+                },
+                color: UiColor(Color::DARK_GRAY),
+                ..Default::default()
+            })
+            .insert(EnergyBar)
+            .with_children(|node| {
+                node.spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(EnergyFill);
+            });
+
+            node.spawn_bundle(TextBundle {
+                text: Text {
+                    sections: (0..NUM_PRESETS)
+                        .map(|_| TextSection {
+                            value: String::new(),
+                            style: function_label_style.clone(),
+                        })
+                        .collect(),
+                    alignment: center_align,
+                },
+                style: Style { align_self: AlignSelf::Center, ..Default::default() },
+                ..Default::default()
+            })
+            .insert(PresetTabs);
+
+            node.spawn_bundle(NodeBundle {
+                style: Style {
+                    align_self: AlignSelf::Center,
+                    size: Size::new(Val::Px(160.0), Val::Px(23.0)),
+                    margin: Rect::all(Val::Px(4.0)),
+                    ..Default::default()
+                },
+                color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                ..Default::default()
+            })
+            .insert(LibrarySpot);
+
             for axis in ["x", "y"] {
                 node.spawn_bundle(NodeBundle {
                     style: Style {
@@ -185,7 +316,8 @@ impl<'w, 's, 'a> EntityCommandsExt for EntityCommands<'w, 's, 'a> {
                             ..Default::default()
                         },
                         ..Default::default()
-                    });
+                    })
+                    .insert(AxisLabel(if axis == "x" { 0 } else { 1 }));
 
                     // Textbox spot
                     node.spawn_bundle(NodeBundle {
@@ -206,7 +338,8 @@ impl<'w, 's, 'a> EntityCommandsExt for EntityCommands<'w, 's, 'a> {
                     .maybe_insert((axis == "y").then(|| FunctionY))
                     .insert(Textbox { text: "".to_owned(), multiline: false })
                     .insert(EguiId::default());
-                });
+                })
+                .insert(AxisRow(if axis == "x" { 0 } else { 1 }));
             }
 
             node.spawn_bundle(NodeBundle {
@@ -248,24 +381,134 @@ impl<'w, 's, 'a> EntityCommandsExt for EntityCommands<'w, 's, 'a> {
                 .insert(EguiId::default());
             });
 
-            node.spawn_bundle(ButtonBundle {
+            node.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "",
+                    function_label_style.clone(),
+                    TextAlignment { horizontal: HorizontalAlign::Left, ..center_align },
+                ),
+                style: Style { align_self: AlignSelf::Center, ..Default::default() },
+                ..Default::default()
+            })
+            .insert(ParseErrorText);
+
+            node.spawn_bundle(TextBundle {
+                text: Text::with_section("", function_label_style.clone(), center_align),
+                style: Style { align_self: AlignSelf::Center, ..Default::default() },
+                ..Default::default()
+            })
+            .insert(ComplexityText);
+
+            node.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "",
+                    TextStyle { color: Color::ORANGE, ..function_label_style.clone() },
+                    center_align,
+                ),
+                style: Style { align_self: AlignSelf::Center, ..Default::default() },
+                ..Default::default()
+            })
+            .insert(DomainWarningText);
+
+            node.spawn_bundle(NodeBundle {
                 style: Style {
+                    flex_direction: FlexDirection::Row,
                     align_self: AlignSelf::Center,
-                    align_items: AlignItems::Center,
-                    justify_content: JustifyContent::Center,
-                    margin: Rect { top: Val::Px(6.0), ..Default::default() },
                     ..Default::default()
                 },
-                color: UiColor(NORMAL_BUTTON),
+                color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
                 ..Default::default()
             })
-            .insert(DoneButton)
-            .insert(Owner(player_index))
             .with_children(|node| {
-                node.spawn_bundle(TextBundle {
-                    text: Text::with_section("Fire", button_style.clone(), center_align),
-                    style: Style { margin: Rect::all(Val::Px(4.0)), ..Default::default() },
+                for (label, action) in [
+                    ("Add Rocket", SendAction::Queue),
+                    ("Fire", SendAction::Fire),
+                    ("Move", SendAction::Move),
+                ] {
+                    node.spawn_bundle(ButtonBundle {
+                        style: Style {
+                            align_self: AlignSelf::Center,
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            margin: Rect {
+                                top: Val::Px(6.0),
+                                left: Val::Px(4.0),
+                                right: Val::Px(4.0),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        color: UiColor(NORMAL_BUTTON),
+                        ..Default::default()
+                    })
+                    .insert(DoneButton(action))
+                    .insert(Owner(player_index))
+                    .with_children(|node| {
+                        node.spawn_bundle(TextBundle {
+                            text: Text::with_section(label, button_style.clone(), center_align),
+                            style: Style { margin: Rect::all(Val::Px(4.0)), ..Default::default() },
+                            ..Default::default()
+                        });
+                    });
+                }
+
+                node.spawn_bundle(ButtonBundle {
+                    style: Style {
+                        align_self: AlignSelf::Center,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        margin: Rect {
+                            top: Val::Px(6.0),
+                            left: Val::Px(4.0),
+                            right: Val::Px(4.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    color: UiColor(NORMAL_BUTTON),
                     ..Default::default()
+                })
+                .insert(CopyCodeButton)
+                .with_children(|node| {
+                    node.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Copy Code",
+                            TextStyle { font_size: FONT_SIZE, ..button_style.clone() },
+                            center_align,
+                        ),
+                        style: Style { margin: Rect::all(Val::Px(4.0)), ..Default::default() },
+                        ..Default::default()
+                    });
+                });
+
+                node.spawn_bundle(ButtonBundle {
+                    style: Style {
+                        align_self: AlignSelf::Center,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        margin: Rect {
+                            top: Val::Px(6.0),
+                            left: Val::Px(4.0),
+                            right: Val::Px(4.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    color: UiColor(NORMAL_BUTTON),
+                    ..Default::default()
+                })
+                .insert(CoordinateButton)
+                .with_children(|node| {
+                    node.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Coords: x, y",
+                            TextStyle { font_size: FONT_SIZE, ..button_style.clone() },
+                            center_align,
+                        ),
+                        style: Style { margin: Rect::all(Val::Px(4.0)), ..Default::default() },
+                        ..Default::default()
+                    })
+                    .insert(CoordinateText);
                 });
             });
 
@@ -537,15 +780,81 @@ pub fn load_ui(mut commands: Commands, fonts: Res<Assets<Font>>, images: Res<Ass
             .spawn_function_ui(&fonts, 0);
         })
         .insert(GameScreen);
+
+    // Privacy screen, which covers everything between turns
+    let privacy_style =
+        TextStyle { font: fonts.get_handle(asset::Font), font_size: 38.0, color: Color::WHITE };
+    let center_align =
+        TextAlignment { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                flex_direction: FlexDirection::ColumnReverse,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(0.0),
+                    left: Val::Percent(0.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: UiColor(Color::rgb(0.15, 0.15, 0.15)),
+            ..Default::default()
+        })
+        .with_children(|node| {
+            node.spawn_bundle(TextBundle {
+                text: Text::with_section("", privacy_style.clone(), center_align),
+                style: Style { margin: Rect::all(Val::Px(10.0)), ..Default::default() },
+                ..Default::default()
+            })
+            .insert(PrivacyText);
+
+            node.spawn_bundle(ButtonBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                color: UiColor(NORMAL_BUTTON),
+                ..Default::default()
+            })
+            .insert(PrivacyButton)
+            .with_children(|node| {
+                node.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "Ready",
+                        TextStyle { color: Color::BLACK, ..privacy_style },
+                        center_align,
+                    ),
+                    style: Style { margin: Rect::all(Val::Px(4.0)), ..Default::default() },
+                    ..Default::default()
+                });
+            });
+        })
+        .insert(PrivacyScreen);
+}
+
+/// Kinds of matches the menu can start
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PlayKind {
+    Normal,
+    Daily,
+    Practice,
 }
 
 #[derive(Component)]
 pub struct PlayButton {
     num_players: u32,
+    kind: PlayKind,
 }
 
+/// Labels buttons that send functions, along with what to do with them
 #[derive(Component)]
-pub struct DoneButton;
+pub struct DoneButton(pub SendAction);
 
 #[derive(Component)]
 pub struct NextRoundButton;
@@ -575,51 +884,172 @@ pub fn update_buttons(
     }
 }
 
-pub fn update_play_button(
-    buttons: Query<(&Interaction, &PlayButton), Changed<Interaction>>,
-    mut play_state: ResMut<State<PlayState>>,
-    mut players: ResMut<Vec<Player>>,
-    mut game: ResMut<Game>,
-    mut menu_screen: Query<&mut Style, With<MenuScreen>>,
-    mut game_screen: Query<&mut Style, (With<GameScreen>, Without<MenuScreen>)>,
-    mut displays: Query<
-        (&mut Style, &PlayerFunctionDisplay),
+/// What starting a match changes
+#[derive(SystemParam)]
+pub struct MatchSetup<'w, 's> {
+    play_state: ResMut<'w, State<PlayState>>,
+    players: ResMut<'w, Vec<Player>>,
+    game: ResMut<'w, Game>,
+    rules: Res<'w, GameRules>,
+    configs: Res<'w, Vec<PlayerConfig>>,
+    menu_screen: Query<'w, 's, &'static mut Style, With<MenuScreen>>,
+    game_screen: Query<'w, 's, &'static mut Style, (With<GameScreen>, Without<MenuScreen>)>,
+    displays: Query<
+        'w,
+        's,
+        (&'static mut Style, &'static PlayerFunctionDisplay),
         (Without<GameScreen>, Without<MenuScreen>),
     >,
+}
+
+impl MatchSetup<'_, '_> {
+    /// Sets up the players and the game for a match, and switches from the menu to the game
+    pub fn start(&mut self, num_players: u32, mode: GameMode) {
+        let rules = &*self.rules;
+        let game = &mut *self.game;
+
+        game.set_num_players(num_players);
+        game.scale = rules.arena_half_size.y;
+        game.aspect = rules.arena_half_size.x / rules.arena_half_size.y;
+        game.has_destruction_round = !matches!(mode, GameMode::Daily { .. } | GameMode::Practice);
+        if rules.elimination {
+            // Someone gets knocked out after every round but the last
+            game.num_rounds = num_players.saturating_sub(1).max(1);
+        }
+        let configs = &*self.configs;
+        *self.players = (0..num_players as usize)
+            .map(|i| {
+                // Everyone plays the daily challenge the same way, and bots don't pick loadouts
+                let loadout =
+                    if mode == GameMode::Normal { configs[i].loadout } else { Loadout::None };
+                Player {
+                    ammo: rules.ammo.unwrap_or(0),
+                    ink: rules.ink.unwrap_or(0),
+                    loadout,
+                    extra_rockets: if loadout == Loadout::MultiShot { 1 } else { 0 },
+                    target_locks: LOCKS_PER_MATCH,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        self.play_state.set(PlayState::Load).ok();
+        self.menu_screen.single_mut().display = Display::None;
+        self.game_screen.single_mut().display = Display::Flex;
+
+        for (mut style, PlayerFunctionDisplay { player_index }) in self.displays.iter_mut() {
+            style.display = if *player_index < num_players { Display::Flex } else { Display::None };
+        }
+    }
+}
+
+pub fn update_play_button(
+    buttons: Query<(&Interaction, &PlayButton), Changed<Interaction>>,
+    mut mode: ResMut<GameMode>,
+    mut rng: ResMut<Pcg64>,
+    mut setup: MatchSetup,
 ) {
     // Play buttons are always enabled when they exist.
-    if let Some((interaction, PlayButton { num_players })) = buttons.iter().next() {
+    if let Some((interaction, PlayButton { num_players, kind })) = buttons.iter().next() {
         if *interaction == Interaction::Clicked {
-            game.set_num_players(*num_players);
-            *players = vec![Player::default(); *num_players as usize];
-            play_state.set(PlayState::Load).ok();
-            menu_screen.single_mut().display = Display::None;
-            game_screen.single_mut().display = Display::Flex;
-
-            for (mut style, PlayerFunctionDisplay { player_index }) in displays.iter_mut() {
-                style.display =
-                    if player_index < num_players { Display::Flex } else { Display::None };
+            if *kind == PlayKind::Daily {
+                let day = daily::today();
+                let mut record = DailyRecord::load(day);
+                if record.attempts_left() == 0 {
+                    return;
+                }
+                record.attempts += 1;
+                record.save(day);
+
+                // Everyone gets the same field on the same day
+                *rng = Pcg64::seed_from_u64(daily::seed(day));
+                *mode = GameMode::Daily { day };
+            } else {
+                let new_mode =
+                    if *kind == PlayKind::Practice { GameMode::Practice } else { GameMode::Normal };
+                if *mode != new_mode {
+                    crate::reseed_rng(&mut rng);
+                    *mode = new_mode;
+                }
             }
+
+            setup.start(*num_players, *mode);
         }
     }
 }
 
+/// Sends functions when a done button gets clicked.
+/// If rockets can be charged, the Add Rocket and Fire buttons send them when released instead.
 pub fn update_done_button(
-    buttons: Query<(&Interaction, &Owner), (Changed<Interaction>, With<DoneButton>)>,
+    buttons: Query<(&Interaction, &Owner, &DoneButton), Changed<Interaction>>,
     mut fire_events: EventWriter<SendFunctions>,
     buttons_enabled: Res<ButtonsEnabled>,
+    rules: Res<GameRules>,
+    time: Res<Time>,
+    mut charging: ResMut<Charging>,
+    audio: Res<Audio>,
+    sounds: Res<Assets<AudioSource>>,
 ) {
     if !buttons_enabled.0 {
+        charging.cancel(&audio);
         return;
     }
 
-    if let Ok((interaction, owner)) = buttons.get_single() {
-        if *interaction == Interaction::Clicked {
-            fire_events.send(SendFunctions { player_index: owner.0 });
+    let now = time.seconds_since_startup();
+    for (interaction, owner, button) in buttons.iter() {
+        let max_charge = rules.max_charge.filter(|_| button.0 != SendAction::Move);
+        let charge = match (*interaction, max_charge) {
+            (Interaction::Clicked, Some(_)) => {
+                charging.start(button.0, now, owner.0, &audio, &sounds);
+                None
+            }
+            (Interaction::Clicked, None) => Some(0.0),
+            (_, Some(max_charge)) => charging.finish(button.0, now, max_charge, &audio),
+            (_, None) => None,
+        };
+
+        if let Some(charge) = charge {
+            fire_events.send(SendFunctions { player_index: owner.0, action: button.0, charge });
         }
     }
 }
 
+/// Keyboard shortcuts for sending functions: Enter in the x(t) or y(t) textbox fires,
+/// and Ctrl+Enter fires the player's last functions again without having to retype them
+pub fn keyboard_fire(
+    keys: Res<Input<KeyCode>>,
+    mut submit: ResMut<SubmitRequested>,
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    buttons_enabled: Res<ButtonsEnabled>,
+    mut entry_boxes: Query<
+        (&mut Textbox, Option<&FunctionX>, Option<&FunctionY>),
+        With<FunctionEntryBox>,
+    >,
+    mut fire_events: EventWriter<SendFunctions>,
+) {
+    let submitted = std::mem::take(&mut submit.0);
+    if !buttons_enabled.0 {
+        return;
+    }
+
+    let player_index = game.player_turn();
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    if ctrl && keys.just_pressed(KeyCode::Return) {
+        let last = if let Some(last) = &players[player_index as usize].last_functions {
+            last
+        } else {
+            return;
+        };
+        for (mut textbox, x, y) in entry_boxes.iter_mut() {
+            textbox.text = last[entry_box_index(x, y)].clone();
+        }
+    } else if !submitted {
+        return;
+    }
+
+    fire_events.send(SendFunctions { player_index, action: SendAction::Fire, charge: 0.0 });
+}
+
 pub fn update_next_round_button(
     buttons: Query<&Interaction, (Changed<Interaction>, With<NextRoundButton>)>,
     mut advance_round_events: EventWriter<AdvanceRound>,
@@ -633,7 +1063,7 @@ pub fn update_next_round_button(
 
     if let Ok(interaction) = buttons.get_single() {
         if *interaction == Interaction::Clicked {
-            if game.is_on_destruction_round() {
+            if game.is_on_final_round() {
                 play_state.set(PlayState::Menu).unwrap();
             } else {
                 advance_round_events.send(AdvanceRound);
@@ -699,7 +1129,7 @@ pub fn advance_turn(
         function_display.single_mut().display = Display::Flex;
         play_state.set(PlayState::Fire).unwrap();
 
-        let round_text = if game.is_on_destruction_round() {
+        let round_text = if game.is_on_final_round() {
             "End Game".to_owned()
         } else if game.is_on_last_normal_round() {
             "To Final Round (Destruction)".to_owned()
@@ -711,8 +1141,46 @@ pub fn advance_turn(
     }
 }
 
+/// Covers the game when the next player starts entering functions, if the rules ask for it
+pub fn show_privacy_screen(
+    mut advance_turn_events: EventReader<AdvanceTurn>,
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    rules: Res<GameRules>,
+    mut game_screen: Query<&mut Style, With<GameScreen>>,
+    mut privacy_screen: Query<&mut Style, (With<PrivacyScreen>, Without<GameScreen>)>,
+    mut privacy_text: Query<&mut Text, With<PrivacyText>>,
+) {
+    if advance_turn_events.iter().next().is_none() || !rules.privacy_screen {
+        return;
+    }
+    // Nobody enters anything while firing, and eliminated players get skipped
+    if game.order_index == 0 || players[game.player_turn() as usize].eliminated {
+        return;
+    }
+
+    let player = game.player_turn() + 1;
+    game_screen.single_mut().display = Display::None;
+    privacy_screen.single_mut().display = Display::Flex;
+    privacy_text.single_mut().sections[0].value =
+        format!("Pass to P{}\nPress Ready when only P{} can see the screen", player, player);
+}
+
+pub fn update_privacy_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<PrivacyButton>)>,
+    mut game_screen: Query<&mut Style, With<GameScreen>>,
+    mut privacy_screen: Query<&mut Style, (With<PrivacyScreen>, Without<GameScreen>)>,
+) {
+    if let Ok(Interaction::Clicked) = buttons.get_single() {
+        game_screen.single_mut().display = Display::Flex;
+        privacy_screen.single_mut().display = Display::None;
+    }
+}
+
 pub fn advance_round(
     mut game: ResMut<Game>,
+    mut players: ResMut<Vec<Player>>,
+    rules: Res<GameRules>,
     mut advance_round_events: EventReader<AdvanceRound>,
     mut play_state: ResMut<State<PlayState>>,
     mut function_ui: Query<&mut Style, With<FunctionUi>>,
@@ -725,6 +1193,13 @@ pub fn advance_round(
     game.round_index += 1;
     play_state.set(PlayState::Enter).unwrap();
 
+    // The first round starts with full ammo
+    if let Some(max_ammo) = rules.ammo.filter(|_| game.round_index > 1) {
+        for player in players.iter_mut() {
+            player.ammo = (player.ammo + rules.ammo_per_round).min(max_ammo);
+        }
+    }
+
     function_ui.get_single_mut().map(|mut ui| ui.display = Display::Flex).ok();
     function_display.get_single_mut().map(|mut ui| ui.display = Display::None).ok();
     textboxes_editable.0 = true;
@@ -744,6 +1219,16 @@ pub struct MenuScreen;
 #[derive(Component)]
 pub struct GameScreen;
 
+/// Labels the screen that hides the game while the keyboard gets passed to the next player
+#[derive(Component)]
+pub struct PrivacyScreen;
+
+#[derive(Component)]
+pub struct PrivacyText;
+
+#[derive(Component)]
+pub struct PrivacyButton;
+
 /// Labels the function entry UI
 #[derive(Component)]
 pub struct FunctionUi;
@@ -782,6 +1267,10 @@ pub struct Textbox {
 /// Whether textboxes are editable
 pub struct TextboxesEditable(pub bool);
 
+/// Whether Enter was pressed in a single-line function entry textbox
+#[derive(Default)]
+pub struct SubmitRequested(pub bool);
+
 /// Whether buttons are enabled
 pub struct ButtonsEnabled(pub bool);
 
@@ -789,6 +1278,74 @@ pub struct ButtonsEnabled(pub bool);
 #[derive(Component)]
 pub struct FunctionEntryBox;
 
+/// Index of the function entry textbox that was focused last, like `entry_box_index` gives
+#[derive(Default)]
+pub struct FocusedEntryBox(pub usize);
+
+/// Index of a function entry textbox when x(t), y(t), and the where clause
+/// are stored together in that order
+pub fn entry_box_index(x: Option<&FunctionX>, y: Option<&FunctionY>) -> usize {
+    if x.is_some() {
+        0
+    } else if y.is_some() {
+        1
+    } else {
+        2
+    }
+}
+
+/// What the current player has typed in the function entry textboxes, parsed the way sending
+/// would parse it, for the meters and sliders around the textboxes. It's only reparsed when
+/// the text, the player, or their coordinate mode changes, so systems reading it can wait
+/// for it to change before redoing their work. Players don't move while functions are
+/// being typed, so their positions don't need remembering.
+#[derive(Debug, Default)]
+pub struct TypedShot {
+    /// The player, their coordinate mode, and the text of x(t), y(t), and the where clause
+    typed: Option<(u32, CoordinateMode, [String; 3])>,
+    /// `None` if all the textboxes are empty
+    pub parsed: Option<Result<Parametric, ParseError>>,
+}
+
+impl TypedShot {
+    /// Text of x(t), y(t), and the where clause that got parsed
+    pub fn functions(&self) -> Option<&[String; 3]> {
+        self.typed.as_ref().map(|(_, _, functions)| functions)
+    }
+}
+
+/// Reparses what the current player typed when it changes
+pub fn update_typed_shot(
+    mut shot: ResMut<TypedShot>,
+    game: Res<Game>,
+    players: Res<Vec<Player>>,
+    player_comps: Query<(Entity, &Owner, &Transform, &LastShotEnd), With<PlayerLabel>>,
+    entry_boxes: Query<(&Textbox, Option<&FunctionX>, Option<&FunctionY>), With<FunctionEntryBox>>,
+) {
+    let player = game.player_turn();
+    let mode = players[player as usize].coordinate_mode;
+    let unchanged = shot.typed.as_ref().map_or(false, |(p, m, functions)| {
+        *p == player
+            && *m == mode
+            && entry_boxes
+                .iter()
+                .all(|(textbox, x, y)| functions[entry_box_index(x, y)] == textbox.text)
+    });
+    if unchanged {
+        return;
+    }
+
+    let mut functions: [String; 3] = Default::default();
+    for (textbox, x, y) in entry_boxes.iter() {
+        functions[entry_box_index(x, y)] = textbox.text.clone();
+    }
+    shot.parsed = (!functions.iter().all(|f| f.trim().is_empty())).then(|| {
+        let env = position_env(player, &players, &player_comps);
+        mode.parse_unvalidated(&functions[0], &functions[1], &functions[2], &env)
+    });
+    shot.typed = Some((player, mode, functions));
+}
+
 /// Labels function display textboxes
 #[derive(Component)]
 pub struct FunctionDisplayBox;
@@ -805,16 +1362,48 @@ pub struct FunctionY;
 #[derive(Component)]
 pub struct FunctionWhere;
 
+/// Labels the text that shows how much ammo the current player has
+#[derive(Component)]
+pub struct AmmoText;
+
+pub fn update_ammo_text(
+    mut text: Query<&mut Text, With<AmmoText>>,
+    players: Res<Vec<Player>>,
+    game: Res<Game>,
+    rules: Res<GameRules>,
+) {
+    let value = if let Some(max_ammo) = rules.ammo {
+        format!("Ammo: {}/{}", players[game.player_turn() as usize].ammo, max_ammo)
+    } else {
+        String::new()
+    };
+
+    let mut text = text.single_mut();
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
 /// Labels status text, which shows "success" or an error message in the first section
 #[derive(Component)]
 pub struct FunctionStatus;
 
 pub fn update_textboxes(
-    mut textboxes: Query<(&mut Textbox, &EguiId, &Node, &GlobalTransform)>,
+    mut textboxes: Query<(
+        &mut Textbox,
+        &EguiId,
+        &Node,
+        &GlobalTransform,
+        Option<&FunctionEntryBox>,
+        Option<&FunctionX>,
+        Option<&FunctionY>,
+    )>,
     mut egui_ctx: ResMut<EguiContext>,
     textboxes_editable: Res<TextboxesEditable>,
+    mut submit: ResMut<SubmitRequested>,
+    mut focused: ResMut<FocusedEntryBox>,
 ) {
-    for (mut textbox, id, size, transform) in textboxes.iter_mut() {
+    for (mut textbox, id, size, transform, entry_box, x, y) in textboxes.iter_mut() {
         if size.size.x == 0.0 && size.size.y == 0.0 {
             continue;
         }
@@ -833,26 +1422,40 @@ pub fn update_textboxes(
                     ui: &mut egui::Ui,
                     text: &'r mut dyn egui::TextBuffer,
                     text_edit_fn: impl Fn(&'r mut dyn egui::TextBuffer) -> egui::TextEdit<'r>,
-                ) {
+                ) -> egui::Response {
                     ui.add_sized(
                         ui.available_size(),
                         text_edit_fn(text).font(egui::FontId {
                             family: egui::FontFamily::Monospace,
                             size: FONT_SIZE,
                         }),
-                    );
+                    )
                 }
 
                 if textbox.multiline {
                     egui::ScrollArea::vertical().show(ui, |ui| {
                         if textboxes_editable.0 {
-                            add_textbox(ui, &mut textbox.text, egui::TextEdit::multiline)
+                            let response =
+                                add_textbox(ui, &mut textbox.text, egui::TextEdit::multiline);
+                            if entry_box.is_some() && response.gained_focus() {
+                                focused.0 = entry_box_index(x, y);
+                            }
                         } else {
-                            add_textbox(ui, &mut textbox.text.as_str(), egui::TextEdit::multiline)
+                            add_textbox(ui, &mut textbox.text.as_str(), egui::TextEdit::multiline);
                         }
                     });
                 } else if textboxes_editable.0 {
-                    add_textbox(ui, &mut textbox.text, egui::TextEdit::singleline);
+                    let response = add_textbox(ui, &mut textbox.text, egui::TextEdit::singleline);
+                    if entry_box.is_some() && response.gained_focus() {
+                        focused.0 = entry_box_index(x, y);
+                    }
+                    // Single-line textboxes give up focus when Enter is pressed
+                    if entry_box.is_some()
+                        && response.lost_focus()
+                        && ui.input().key_pressed(egui::Key::Enter)
+                    {
+                        submit.0 = true;
+                    }
                 } else {
                     add_textbox(ui, &mut textbox.text.as_str(), egui::TextEdit::singleline);
                 }
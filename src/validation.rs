@@ -0,0 +1,231 @@
+use std::fmt;
+
+use crate::{
+    analysis::PATH_SAMPLES,
+    core::lang::{
+        parse_parametric_in, Env, Function, Parametric, ParseError, INTEGRAL_STEPS,
+        MAX_SERIES_TERMS,
+    },
+    rules::GameRules,
+    Player,
+};
+
+/// Most nodes the functions of a shot can have together
+pub const COMPLEXITY_BUDGET: usize = 300;
+/// Most nodes a shot can take to evaluate for one point, so nested sums, products,
+/// and integrals can't freeze the game while it's checked, previewed, and flown
+pub const MAX_POINT_COST: u64 = 100_000;
+
+/// Why a shot didn't get sent
+#[derive(Debug)]
+pub enum Rejection {
+    Parse(ParseError),
+    /// The functions have more nodes than the budget allows
+    TooComplex {
+        nodes: usize,
+    },
+    /// The functions take too long to evaluate for each point of the path
+    TooSlow {
+        cost: u64,
+    },
+    /// The curve isn't a number at some parameter, like with `sqrt(-1)` or `1/0`
+    NotFinite {
+        param: f32,
+    },
+    OutOfAmmo,
+    /// The shot takes more ink to write than the player has left
+    OutOfInk {
+        cost: u32,
+        left: u32,
+    },
+    /// The player sent a rocket too recently
+    Cooldown {
+        wait: f64,
+    },
+    /// The player's launcher ran out of energy and hasn't cooled down yet
+    Overheated {
+        wait: f64,
+    },
+    RocketLimit {
+        max_rockets: u32,
+    },
+}
+
+impl From<ParseError> for Rejection {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(error) => write!(f, "{}", error),
+            Self::TooComplex { nodes } => {
+                write!(f, "Functions too long ({} of {} terms)", nodes, COMPLEXITY_BUDGET)
+            }
+            Self::TooSlow { cost } => {
+                write!(f, "Functions too slow ({} of {} steps per point)", cost, MAX_POINT_COST)
+            }
+            Self::NotFinite { param } => write!(f, "Curve is undefined at t={:.2}", param),
+            Self::OutOfAmmo => write!(f, "Out of ammo (fire with empty textboxes to pass)"),
+            Self::OutOfInk { cost, left } => write!(
+                f,
+                "Not enough ink ({} needed, {} left; fire with empty textboxes to pass)",
+                cost, left
+            ),
+            Self::Cooldown { wait } => {
+                write!(f, "Wait {:.1}s before sending another rocket", wait)
+            }
+            Self::Overheated { wait } => {
+                write!(f, "Overheated! Cooling down for {:.1}s", wait)
+            }
+            Self::RocketLimit { max_rockets } => {
+                write!(f, "Rocket limit reached ({} per turn)", max_rockets)
+            }
+        }
+    }
+}
+
+/// Number of nodes in a function
+fn count_nodes(function: &Function) -> usize {
+    1 + match function {
+        Function::Var(_) | Function::Const(_) | Function::ImagUnit | Function::Index(_) => 0,
+        Function::Add(terms) | Function::Mul(terms) => {
+            terms.iter().map(|(term, _)| count_nodes(term)).sum()
+        }
+        Function::Exp(factors) => factors.iter().map(count_nodes).sum(),
+        Function::Neg(inner) | Function::Integral(inner) => count_nodes(inner),
+        Function::Call1(_, arg) => count_nodes(arg),
+        Function::Call2(_, args) | Function::Cmp(_, args) | Function::Rand(_, args) => {
+            args.iter().map(count_nodes).sum()
+        }
+        Function::If(args) | Function::Series(_, _, args) => args.iter().map(count_nodes).sum(),
+    }
+}
+
+/// Number of nodes in all the functions of a parametric. Complex shots don't use y.
+pub fn complexity(parametric: &Parametric) -> usize {
+    let y = (!parametric.complex).then(|| &parametric.y);
+    [&parametric.x].into_iter().chain(y).chain(&parametric.assigns).map(count_nodes).sum()
+}
+
+/// Rough number of nodes evaluated to find one point of a function. Variables get evaluated
+/// again wherever they're used, integrals evaluate their function many times, and both branches
+/// of an `if` count. `costs` holds the cost of each variable once it's known.
+fn eval_cost(function: &Function, assigns: &[Function], costs: &mut [Option<u64>]) -> u64 {
+    let all = |fs: &mut dyn Iterator<Item = &Function>, costs: &mut [Option<u64>]| {
+        fs.fold(0u64, |acc, f| acc.saturating_add(eval_cost(f, assigns, costs)))
+    };
+    1u64.saturating_add(match function {
+        Function::Var(Some(index)) => match costs[*index] {
+            Some(cost) => cost,
+            None => {
+                let cost = eval_cost(&assigns[*index], assigns, costs);
+                costs[*index] = Some(cost);
+                cost
+            }
+        },
+        Function::Var(None) | Function::Const(_) | Function::ImagUnit | Function::Index(_) => 0,
+        Function::Add(terms) | Function::Mul(terms) => {
+            all(&mut terms.iter().map(|(f, _)| f), costs)
+        }
+        Function::Exp(factors) => all(&mut factors.iter(), costs),
+        Function::Neg(inner) | Function::Call1(_, inner) => eval_cost(inner, assigns, costs),
+        Function::Integral(inner) => {
+            eval_cost(inner, assigns, costs).saturating_mul(INTEGRAL_STEPS as u64 + 1)
+        }
+        Function::Call2(_, args) | Function::Cmp(_, args) | Function::Rand(_, args) => {
+            all(&mut args.iter(), costs)
+        }
+        Function::If(args) => all(&mut args.iter(), costs),
+        // Bounds that aren't numbers could go up to the most terms
+        Function::Series(_, _, args) => {
+            let terms = match (&args[0], &args[1]) {
+                (Function::Const(first), Function::Const(last)) => {
+                    (last.round() - first.round() + 1.0).clamp(0.0, MAX_SERIES_TERMS as f64) as u64
+                }
+                _ => MAX_SERIES_TERMS as u64,
+            };
+            all(&mut args[..2].iter(), costs)
+                .saturating_add(eval_cost(&args[2], assigns, costs).saturating_mul(terms))
+        }
+    })
+}
+
+/// Rough number of nodes evaluated to find one point of a parametric
+pub fn point_cost(parametric: &Parametric) -> u64 {
+    let mut costs = vec![None; parametric.assigns.len()];
+    let mut cost = eval_cost(&parametric.x, &parametric.assigns, &mut costs);
+    if !parametric.complex {
+        cost = cost.saturating_add(eval_cost(&parametric.y, &parametric.assigns, &mut costs));
+    }
+    cost
+}
+
+/// Checks that a shot is small and fast enough, and defined along its whole path
+pub fn validate_shot(parametric: &Parametric) -> Result<(), Rejection> {
+    let nodes = complexity(parametric);
+    if nodes > COMPLEXITY_BUDGET {
+        return Err(Rejection::TooComplex { nodes });
+    }
+    let cost = point_cost(parametric);
+    if cost > MAX_POINT_COST {
+        return Err(Rejection::TooSlow { cost });
+    }
+
+    for i in 0..=PATH_SAMPLES {
+        let t = i as f64 / PATH_SAMPLES as f64;
+        if !parametric.eval(t).is_finite() {
+            return Err(Rejection::NotFinite { param: t as f32 });
+        }
+    }
+    Ok(())
+}
+
+/// Parses a shot and validates it
+pub fn parse_shot(fx: &str, fy: &str, assigns: &str) -> Result<Parametric, Rejection> {
+    parse_shot_in(fx, fy, assigns, &[])
+}
+
+/// Like `parse_shot`, but the shot can also use the values in `env`
+pub fn parse_shot_in(
+    fx: &str,
+    fy: &str,
+    assigns: &str,
+    env: &Env,
+) -> Result<Parametric, Rejection> {
+    let parametric = parse_parametric_in(fx, fy, assigns, env)?;
+    validate_shot(&parametric)?;
+    Ok(parametric)
+}
+
+/// Checks that a player is allowed to send another rocket at time `now`, in seconds since startup
+pub fn check_send(player: &Player, rules: &GameRules, now: f64) -> Result<(), Rejection> {
+    if rules.ammo.is_some() && player.ammo == 0 {
+        return Err(Rejection::OutOfAmmo);
+    }
+    if now < player.next_fire_time {
+        return Err(Rejection::Cooldown { wait: player.next_fire_time - now });
+    }
+    if let Some(wait) = player.energy.overheat_wait(now).filter(|_| rules.energy) {
+        return Err(Rejection::Overheated { wait });
+    }
+    if player.parametrics.len() as u32 >= rules.max_rockets + player.extra_rockets {
+        return Err(Rejection::RocketLimit { max_rockets: rules.max_rockets });
+    }
+    Ok(())
+}
+
+/// Checks that a player has enough ink left to send a shot, and returns how much it costs
+pub fn check_ink(
+    player: &Player,
+    rules: &GameRules,
+    parametric: &Parametric,
+) -> Result<u32, Rejection> {
+    let cost = parametric.ink_cost();
+    if rules.ink.is_some() && cost > player.ink {
+        return Err(Rejection::OutOfInk { cost, left: player.ink });
+    }
+    Ok(cost)
+}
@@ -0,0 +1,97 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    analysis::PATH_SAMPLES,
+    asset,
+    projectile::{Projectile, SamplePath},
+    DespawnOnExit, Field, Owner, PlayState, PlayerLabel, RenderLayer,
+};
+
+/// How close an enemy rocket's path has to pass to a player to warn them
+const WARNING_DIST: f32 = 0.6;
+/// How far from the player the warning arrow is
+const ARROW_DIST: f32 = 0.45;
+
+/// Arrow next to a player pointing at an enemy rocket that's going to pass close by
+#[derive(Component)]
+pub struct InterceptWarning {
+    projectile: Entity,
+    player: Entity,
+    /// Parameter of the projectile's motion after which it's no longer a threat
+    until: f32,
+}
+
+/// Adds warnings for new rockets whose paths pass close to other players
+pub fn add_intercept_warnings(
+    mut commands: Commands,
+    projectiles: Query<(Entity, &Owner, &SamplePath), Added<SamplePath>>,
+    players: Query<(Entity, &Owner, &Transform), With<PlayerLabel>>,
+    field: Query<Entity, With<Field>>,
+    images: Res<Assets<Image>>,
+) {
+    for (projectile, owner, path) in projectiles.iter() {
+        for (player, player_owner, transform) in players.iter() {
+            if player_owner.0 == owner.0 {
+                continue;
+            }
+
+            let pos = transform.translation.xy();
+            let last_close = path.0.iter().rposition(|sample| sample.distance(pos) < WARNING_DIST);
+            if let Some(index) = last_close {
+                commands.entity(field.single()).with_children(|node| {
+                    node.spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgb(1.0, 0.3, 0.3),
+                            custom_size: Some(Vec2::new(2.8, 1.4)),
+                            ..Default::default()
+                        },
+                        texture: images.get_handle(asset::Rocket(owner.0)),
+                        transform: Transform::from_translation(pos.extend(RenderLayer::Score.z()))
+                            .with_scale(Vec3::from([0.12; 3])),
+                        visibility: Visibility { is_visible: false },
+                        ..Default::default()
+                    })
+                    .insert(InterceptWarning {
+                        projectile,
+                        player,
+                        until: index as f32 / PATH_SAMPLES as f32,
+                    })
+                    .insert(DespawnOnExit(PlayState::Fire));
+                });
+            }
+        }
+    }
+}
+
+/// Points warnings at their rockets, and removes them once the rockets are gone or past
+pub fn update_intercept_warnings(
+    mut commands: Commands,
+    mut warnings: Query<(Entity, &InterceptWarning, &mut Transform, &mut Visibility)>,
+    projectiles: Query<(&Transform, &Timer), (With<Projectile>, Without<InterceptWarning>)>,
+    players: Query<&Transform, (With<PlayerLabel>, Without<InterceptWarning>)>,
+) {
+    for (entity, warning, mut transform, mut visibility) in warnings.iter_mut() {
+        let (rocket_transform, timer) = match projectiles.get(warning.projectile) {
+            Ok(projectile) => projectile,
+            Err(_) => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+        let player_transform = if let Ok(p) = players.get(warning.player) { p } else { continue };
+        if timer.percent() > warning.until {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let player_pos = player_transform.translation.xy();
+        let dir = (rocket_transform.translation.xy() - player_pos).normalize_or_zero();
+        if dir == Vec2::ZERO {
+            continue;
+        }
+        // Arrow on the rocket's side of the player, pointing at the player
+        transform.translation = (player_pos + dir * ARROW_DIST).extend(RenderLayer::Score.z());
+        transform.rotation = Quat::from_rotation_arc_2d(Vec2::X, -dir);
+        visibility.is_visible = true;
+    }
+}
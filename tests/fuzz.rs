@@ -0,0 +1,89 @@
+//! Property tests for the function language: parsing never panics,
+//! undefined values never make it into positions the game uses,
+//! and compiled functions evaluate the same as the syntax tree.
+
+use bevy::math::Vec2;
+use graph_war::{
+    analysis::{sample_path, PATH_SAMPLES},
+    core::lang::parse_parametric,
+    projectile::MotionModel,
+    validation::validate_shot,
+};
+use proptest::prelude::*;
+
+/// Expressions that always parse
+fn well_formed() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![
+        Just("t".to_owned()),
+        Just("pi".to_owned()),
+        Just("e".to_owned()),
+        (0.0..100.0f64).prop_map(|c| format!("{:.3}", c)),
+        (0..100u32).prop_map(|c| c.to_string()),
+    ];
+    leaf.prop_recursive(6, 64, 2, |inner| {
+        let unary =
+            ["sin", "cos", "tan", "asin", "acosh", "ln", "log2", "sqrt", "abs", "floor", "fract"];
+        let binary = ["min", "max", "atan2"];
+        let ops = ["+", "-", "*", "/", "//", "%", "^"];
+        prop_oneof![
+            (inner.clone(), proptest::sample::select(&ops[..]), inner.clone())
+                .prop_map(|(a, op, b)| format!("({}) {} ({})", a, op, b)),
+            inner.clone().prop_map(|a| format!("-({})", a)),
+            (proptest::sample::select(&unary[..]), inner.clone())
+                .prop_map(|(f, a)| format!("{}({})", f, a)),
+            (proptest::sample::select(&binary[..]), inner.clone(), inner)
+                .prop_map(|(f, a, b)| format!("{} ({}) ({})", f, a, b)),
+        ]
+    })
+}
+
+/// Strings made of the characters functions use, which mostly don't parse
+fn malformed() -> impl Strategy<Value = String> {
+    "[-+*/%^() .0-9a-z=\n]{0,40}"
+}
+
+proptest! {
+    #[test]
+    fn well_formed_expressions_parse(x in well_formed(), y in well_formed()) {
+        prop_assert!(parse_parametric(&x, &y, "").is_ok());
+    }
+
+    #[test]
+    fn malformed_expressions_dont_panic(x in malformed(), assigns in malformed()) {
+        let _ = parse_parametric(&x, "t", "");
+        let _ = parse_parametric("t", "t", &assigns);
+        let _ = parse_parametric(&x, &x, &assigns);
+    }
+
+    #[test]
+    fn guarded_positions_are_finite(x in well_formed(), y in well_formed(), t in 0.0..=1.0f32) {
+        let parametric = parse_parametric(&x, &y, "").unwrap();
+        if let Some(pos) = parametric.try_eval(t as f64) {
+            prop_assert!(pos.is_finite());
+        }
+
+        let motion = MotionModel::parametric(parametric, Vec2::ZERO);
+        if let Some(pos) = motion.try_position(t) {
+            prop_assert!(pos.is_finite());
+        }
+        prop_assert!(sample_path(&motion).iter().all(|pos| pos.is_finite()));
+    }
+
+    #[test]
+    fn compiling_keeps_values(x in well_formed(), y in well_formed(), t in 0.0..=1.0f64) {
+        let parametric = parse_parametric(&x, &y, "").unwrap();
+        let (tree, compiled) = (parametric.eval(t), parametric.clone().compile().eval(t));
+        for (a, b) in [(tree.x, compiled.x), (tree.y, compiled.y)] {
+            prop_assert!(a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan()));
+        }
+    }
+
+    #[test]
+    fn valid_shots_are_finite_where_sampled(x in well_formed(), y in well_formed()) {
+        let parametric = parse_parametric(&x, &y, "").unwrap();
+        if validate_shot(&parametric).is_ok() {
+            let motion = MotionModel::parametric(parametric, Vec2::ZERO);
+            prop_assert_eq!(sample_path(&motion).len(), PATH_SAMPLES + 1);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Golden tests for the function language. The corpora are in `tests/golden`.
+
+use std::fmt::Debug;
+
+use graph_war::{
+    core::{
+        interval::domain_warnings,
+        lang::{parse_complex_in, parse_parametric, Function, OpType, Parametric, SeriesOp},
+    },
+    validation::{validate_shot, Rejection},
+};
+
+/// Name of a builtin, as it's written in functions
+fn call_name(call: &impl Debug) -> String {
+    format!("{:?}", call).to_lowercase()
+}
+
+/// Writes a function as an s-expression, to compare against the corpus
+fn to_sexpr(function: &Function) -> String {
+    let terms = |name: &str, terms: &[(Function, OpType)], ops: [&str; 4]| {
+        let terms = terms
+            .iter()
+            .map(|(term, op)| {
+                let tag = ops[*op as usize];
+                format!("{}{}", tag, to_sexpr(term))
+            })
+            .collect::<Vec<_>>();
+        format!("({} {})", name, terms.join(" "))
+    };
+
+    match function {
+        Function::Var(None) => "t".to_owned(),
+        Function::Var(Some(index)) => format!("v{}", index),
+        Function::Const(c) => c.to_string(),
+        Function::ImagUnit => "i".to_owned(),
+        Function::Index(slot) => format!("k{}", slot),
+        Function::Add(ts) => terms("add", ts, ["", "sub:", "", ""]),
+        Function::Mul(ts) => terms("mul", ts, ["", "div:", "fdiv:", "mod:"]),
+        Function::Exp(factors) => {
+            let factors = factors.iter().map(to_sexpr).collect::<Vec<_>>();
+            format!("(pow {})", factors.join(" "))
+        }
+        Function::Neg(inner) => format!("(neg {})", to_sexpr(inner)),
+        Function::Integral(inner) => format!("(int {})", to_sexpr(inner)),
+        Function::Series(op, slot, args) => {
+            let name = if *op == SeriesOp::Sum { "sum" } else { "prod" };
+            let args = args.iter().map(to_sexpr).collect::<Vec<_>>();
+            format!("({} k{} {})", name, slot, args.join(" "))
+        }
+        Function::Call1(call, arg) => format!("({} {})", call_name(call), to_sexpr(arg)),
+        Function::Call2(call, args) => {
+            format!("({} {} {})", call_name(call), to_sexpr(&args[0]), to_sexpr(&args[1]))
+        }
+        Function::Cmp(op, args) => {
+            format!("({} {} {})", call_name(op), to_sexpr(&args[0]), to_sexpr(&args[1]))
+        }
+        Function::If(args) => {
+            let args = args.iter().map(to_sexpr).collect::<Vec<_>>();
+            format!("(if {})", args.join(" "))
+        }
+        Function::Rand(index, args) => {
+            format!("(rand{} {} {})", index, to_sexpr(&args[0]), to_sexpr(&args[1]))
+        }
+    }
+}
+
+/// Parses `x(t) | where` from the corpus, with `;` standing for newlines in the where clause
+fn parse(source: &str) -> Result<Parametric, String> {
+    let (x, assigns) = source.split_once(" | ").unwrap_or((source, ""));
+    parse_parametric(x.trim(), "0", &assigns.replace("; ", "\n")).map_err(|e| e.to_string())
+}
+
+/// Lines of a corpus that aren't blank or comments, along with their line numbers
+fn corpus_lines(corpus: &str) -> impl Iterator<Item = (usize, &str)> {
+    corpus
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+}
+
+/// Whether a value is close enough to the corpus's, which is rounded to f32 precision
+fn matches(actual: f32, expected: f32) -> bool {
+    if expected.is_nan() {
+        actual.is_nan()
+    } else if expected.is_infinite() {
+        actual == expected
+    } else {
+        (actual - expected).abs() <= 1e-5 * expected.abs().max(1.0)
+    }
+}
+
+fn check_all(name: &str, failures: Vec<String>) {
+    assert!(failures.is_empty(), "{} mismatches:\n{}", name, failures.join("\n"));
+}
+
+#[test]
+fn golden_ast() {
+    let mut failures = vec![];
+    for (line_num, line) in corpus_lines(include_str!("golden/ast.txt")) {
+        let (source, expected) = line.rsplit_once("=> ").expect("missing `=>`");
+        let actual = match (parse(source.trim_end()), expected.strip_prefix("error: ")) {
+            (Ok(parametric), None) => to_sexpr(&parametric.x),
+            (Err(error), Some(message)) if error.contains(message) => continue,
+            (Ok(parametric), Some(_)) => to_sexpr(&parametric.x),
+            (Err(error), _) => error,
+        };
+        if actual != expected {
+            failures.push(format!("line {}: `{}` gave `{}`", line_num, source.trim(), actual));
+        }
+    }
+    check_all("AST", failures);
+}
+
+#[test]
+fn golden_eval() {
+    let mut failures = vec![];
+    for (line_num, line) in corpus_lines(include_str!("golden/eval.txt")) {
+        let (source, expected) = line.rsplit_once(" => ").expect("missing `=>`");
+        let (source, t) = source.rsplit_once(" @ ").expect("missing `@`");
+        let expected = expected.parse::<f32>().expect("bad expected value");
+        let t = t.parse::<f64>().expect("bad t");
+
+        let actual = match parse(source) {
+            Ok(parametric) => {
+                let compiled = parametric.clone().compile().eval(t).x;
+                if !matches(compiled, expected) {
+                    failures.push(format!(
+                        "line {}: `{}` compiled gave {}",
+                        line_num, source, compiled
+                    ));
+                }
+                parametric.eval(t).x
+            }
+            Err(error) => {
+                failures
+                    .push(format!("line {}: `{}` failed to parse: {}", line_num, source, error));
+                continue;
+            }
+        };
+        if !matches(actual, expected) {
+            failures.push(format!("line {}: `{}` gave {}", line_num, source, actual));
+        }
+    }
+    check_all("Evaluation", failures);
+}
+
+#[test]
+fn golden_complex() {
+    let mut failures = vec![];
+    for (line_num, line) in corpus_lines(include_str!("golden/complex.txt")) {
+        let (source, expected) = line.rsplit_once(" => ").expect("missing `=>`");
+        let (source, t) = source.rsplit_once(" @ ").expect("missing `@`");
+        let (x, assigns) = source.split_once(" | ").unwrap_or((source, ""));
+        let (re, im) = expected.split_once(' ').expect("missing imaginary part");
+        let expected = [re, im].map(|part| part.parse::<f32>().expect("bad expected value"));
+        let t = t.parse::<f64>().expect("bad t");
+
+        let actual = match parse_complex_in(x.trim(), &assigns.replace("; ", "\n"), &[]) {
+            Ok(parametric) => parametric.eval(t),
+            Err(error) => {
+                failures
+                    .push(format!("line {}: `{}` failed to parse: {}", line_num, source, error));
+                continue;
+            }
+        };
+        if !matches(actual.x, expected[0]) || !matches(actual.y, expected[1]) {
+            failures
+                .push(format!("line {}: `{}` gave {} {}", line_num, source, actual.x, actual.y));
+        }
+    }
+    check_all("Complex evaluation", failures);
+}
+
+#[test]
+fn golden_domain() {
+    let mut failures = vec![];
+    for (line_num, line) in corpus_lines(include_str!("golden/domain.txt")) {
+        let (source, expected) = line.rsplit_once(" => ").expect("missing `=>`");
+        let actual = match parse(source) {
+            Ok(parametric) => {
+                let warnings = domain_warnings(&parametric);
+                if warnings.is_empty() {
+                    "none".to_owned()
+                } else {
+                    warnings.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+                }
+            }
+            Err(error) => {
+                failures
+                    .push(format!("line {}: `{}` failed to parse: {}", line_num, source, error));
+                continue;
+            }
+        };
+        if actual != expected {
+            failures.push(format!("line {}: `{}` gave `{}`", line_num, source, actual));
+        }
+    }
+    check_all("Domain warning", failures);
+}
+
+#[test]
+fn golden_simplify() {
+    let mut failures = vec![];
+    for (line_num, line) in corpus_lines(include_str!("golden/simplify.txt")) {
+        let (source, expected) = line.rsplit_once(" => ").expect("missing `=>`");
+        let parametric = match parse(source) {
+            Ok(parametric) => parametric,
+            Err(error) => {
+                failures
+                    .push(format!("line {}: `{}` failed to parse: {}", line_num, source, error));
+                continue;
+            }
+        };
+        let simplified = parametric.clone().simplify();
+
+        let actual = to_sexpr(&simplified.x);
+        if actual != expected {
+            failures.push(format!("line {}: `{}` gave `{}`", line_num, source, actual));
+        }
+        for t in [0.0, 0.3, 0.7, 1.0] {
+            let (before, after) = (parametric.eval(t).x, simplified.eval(t).x);
+            if !matches(after, before) {
+                failures.push(format!(
+                    "line {}: `{}` changed from {} to {} at t={}",
+                    line_num, source, before, after, t
+                ));
+            }
+        }
+    }
+    check_all("Simplification", failures);
+}
+
+#[test]
+fn golden_ink() {
+    let mut failures = vec![];
+    for (line_num, line) in corpus_lines(include_str!("golden/ink.txt")) {
+        let (source, expected) = line.rsplit_once(" => ").expect("missing `=>`");
+        let actual = match parse(source) {
+            Ok(parametric) => (parametric.ink_cost() - parametric.y.ink_cost()).to_string(),
+            Err(error) => {
+                failures
+                    .push(format!("line {}: `{}` failed to parse: {}", line_num, source, error));
+                continue;
+            }
+        };
+        if actual != expected {
+            failures.push(format!("line {}: `{}` gave {}", line_num, source, actual));
+        }
+    }
+    check_all("Ink cost", failures);
+}
+
+#[test]
+fn golden_validate() {
+    let mut failures = vec![];
+    for (line_num, line) in corpus_lines(include_str!("golden/validate.txt")) {
+        let (source, expected) = line.rsplit_once(" => ").expect("missing `=>`");
+        let actual = match parse(source).map(|parametric| validate_shot(&parametric)) {
+            Ok(Ok(())) => "ok",
+            Ok(Err(Rejection::TooComplex { .. })) => "too complex",
+            Ok(Err(Rejection::TooSlow { .. })) => "too slow",
+            Ok(Err(Rejection::NotFinite { .. })) => "undefined",
+            Ok(Err(error)) => {
+                failures.push(format!("line {}: `{}` gave {}", line_num, source, error));
+                continue;
+            }
+            Err(error) => {
+                failures
+                    .push(format!("line {}: `{}` failed to parse: {}", line_num, source, error));
+                continue;
+            }
+        };
+        if actual != expected {
+            failures.push(format!("line {}: `{}` gave {}", line_num, source, actual));
+        }
+    }
+    check_all("Validation", failures);
+}